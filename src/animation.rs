@@ -0,0 +1,83 @@
+use std::{fs::File, io::BufWriter, time::Duration};
+
+use anyhow::{anyhow, Result};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops, Delay, Frame, RgbaImage,
+};
+
+use crate::{
+    atlas::grid_columns,
+    pipeline::{suffixed_name, AnimatedOutput},
+    texture::write_image,
+};
+
+/// `out/water.png` becomes `out/water_0012.png` for the frame 12.
+pub fn frame_file_name(fname: &str, frame: u32) -> String {
+    suffixed_name(fname, &format!("_{frame:04}"))
+}
+
+/// Sequences are written frame by frame as regular outputs instead.
+pub fn write(animation: AnimatedOutput, frames: &[RgbaImage], fps: f32, fname: &str) -> Result<()> {
+    if frames.is_empty() {
+        return Err(anyhow!("Animation `{fname}` has no frames"));
+    }
+    match animation {
+        AnimatedOutput::Sequence => Err(anyhow!("Sequence `{fname}` is written frame by frame")),
+        AnimatedOutput::Flipbook(columns) => write_image(&flipbook(frames, columns), fname),
+        AnimatedOutput::Gif => write_gif(frames, fps, fname),
+        AnimatedOutput::Apng => write_apng(frames, fps, fname),
+    }
+}
+
+pub fn flipbook(frames: &[RgbaImage], columns: u32) -> RgbaImage {
+    let count = frames.len() as u32;
+    let columns = grid_columns(count, Some(columns));
+    let rows = count.div_ceil(columns);
+    let (w, h) = frames.first().map_or((0, 0), |it| it.dimensions());
+
+    let mut sheet = RgbaImage::new(columns * w, rows * h);
+    for (i, frame) in frames.iter().enumerate() {
+        let i = i as u32;
+        let (x, y) = ((i % columns) * w, (i / columns) * h);
+        imageops::replace(&mut sheet, frame, x as i64, y as i64);
+    }
+    sheet
+}
+
+pub fn frame_delay(fps: f32) -> (u16, u16) {
+    (
+        100,
+        (fps * 100.0).round().clamp(1.0, u16::MAX as f32) as u16,
+    )
+}
+
+fn write_gif(frames: &[RgbaImage], fps: f32, fname: &str) -> Result<()> {
+    let delay = Delay::from_saturating_duration(Duration::from_secs_f32(1.0 / fps));
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(fname)?), 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(
+        frames
+            .iter()
+            .map(|it| Frame::from_parts(it.clone(), 0, 0, delay)),
+    )?;
+    Ok(())
+}
+
+fn write_apng(frames: &[RgbaImage], fps: f32, fname: &str) -> Result<()> {
+    let (w, h) = frames[0].dimensions();
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(fname)?), w, h);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Zero plays loop forever.
+    encoder.set_animated(frames.len() as u32, 0)?;
+    let (numerator, denominator) = frame_delay(fps);
+    encoder.set_frame_delay(numerator, denominator)?;
+
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(frame.as_raw())?;
+    }
+    writer.finish()?;
+    Ok(())
+}
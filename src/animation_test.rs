@@ -0,0 +1,110 @@
+use std::{fs::File, io::BufReader};
+
+use image::{codecs::gif::GifDecoder, AnimationDecoder, Rgba, RgbaImage};
+
+use crate::{
+    animation::{flipbook, frame_delay, frame_file_name, write},
+    pipeline::{AnimatedOutput, Animation, Pipeline},
+    test_util::TempDir,
+};
+
+fn frames(count: u8) -> Vec<RgbaImage> {
+    (0..count)
+        .map(|i| RgbaImage::from_pixel(2, 2, Rgba([40 * i, 0, 0, 255])))
+        .collect()
+}
+
+#[test]
+fn test_animation_parse() {
+    let animation: Animation = serde_yaml::from_str("{ end: 48, fps: 24 }").unwrap();
+
+    assert_eq!(animation.frames(), 0..48);
+    assert_eq!(animation.time(12), 0.5);
+
+    let pipeline: Pipeline = serde_yaml::from_str("{ variables: {}, pipeline: [] }").unwrap();
+    assert_eq!(pipeline.frames(), 0..1);
+    assert_eq!(pipeline.time(0), 0.0);
+}
+
+#[test]
+fn test_animation_validate() {
+    let animation = |src: &str| serde_yaml::from_str::<Animation>(src).unwrap();
+
+    assert!(animation("{ end: 48, fps: 24 }").validate().is_ok());
+    assert!(animation("{ start: 4, end: 5 }").validate().is_ok());
+    assert!(animation("{ end: 48, fps: 0 }").validate().is_err());
+    assert!(animation("{ end: 48, fps: -24 }").validate().is_err());
+    assert!(animation("{ start: 10, end: 4 }").validate().is_err());
+    // The end is exclusive, so an animation ending at its start has no frames.
+    assert!(animation("{ start: 4, end: 4 }").validate().is_err());
+}
+
+#[test]
+fn test_animated_output_parse() {
+    let parse = |it: &str| -> AnimatedOutput {
+        serde_yaml::with::singleton_map::deserialize(serde_yaml::Deserializer::from_str(it))
+            .unwrap()
+    };
+
+    assert_eq!(parse("sequence"), AnimatedOutput::Sequence);
+    assert_eq!(parse("{ flipbook: 8 }"), AnimatedOutput::Flipbook(8));
+    assert_eq!(parse("apng"), AnimatedOutput::Apng);
+}
+
+#[test]
+fn test_frame_file_name() {
+    assert_eq!(frame_file_name("out/water.png", 12), "out/water_0012.png");
+    assert_eq!(frame_file_name("water", 3), "water_0003");
+}
+
+#[test]
+fn test_flipbook() {
+    let sheet = flipbook(&frames(5), 2);
+
+    assert_eq!(sheet.dimensions(), (4, 6));
+    assert_eq!(sheet.get_pixel(3, 1), &Rgba([40, 0, 0, 255]));
+    assert_eq!(sheet.get_pixel(0, 5), &Rgba([160, 0, 0, 255]));
+    assert_eq!(sheet.get_pixel(3, 5), &Rgba([0, 0, 0, 0]));
+}
+
+#[test]
+fn test_frame_delay() {
+    assert_eq!(frame_delay(30.0), (100, 3000));
+    assert_eq!(frame_delay(0.0), (100, 1));
+}
+
+#[test]
+fn test_write_gif() {
+    let dir = TempDir::new("animation");
+    let fname = dir.file("fire.gif");
+
+    write(AnimatedOutput::Gif, &frames(3), 10.0, &fname).unwrap();
+
+    let decoder = GifDecoder::new(BufReader::new(File::open(&fname).unwrap())).unwrap();
+    let decoded = decoder.into_frames().collect_frames().unwrap();
+    assert_eq!(decoded.len(), 3);
+    assert_eq!(decoded[0].delay().numer_denom_ms(), (100, 1));
+    assert_eq!(decoded[2].buffer().get_pixel(1, 1), &Rgba([80, 0, 0, 255]));
+}
+
+#[test]
+fn test_write_apng() {
+    let dir = TempDir::new("animation");
+    let fname = dir.file("water.png");
+
+    write(AnimatedOutput::Apng, &frames(4), 30.0, &fname).unwrap();
+
+    let decoder = png::Decoder::new(File::open(&fname).unwrap());
+    let reader = decoder.read_info().unwrap();
+    let control = reader.info().animation_control.unwrap();
+    assert_eq!((control.num_frames, control.num_plays), (4, 0));
+}
+
+#[test]
+fn test_write_sequence_fails() {
+    let dir = TempDir::new("animation");
+    let fname = dir.file("sequence.png");
+
+    assert!(write(AnimatedOutput::Sequence, &frames(2), 30.0, &fname).is_err());
+    assert!(write(AnimatedOutput::Gif, &[], 30.0, &fname).is_err());
+}
@@ -0,0 +1,167 @@
+use anyhow::{anyhow, Result};
+use image::{Rgba, RgbaImage};
+use serde::Serialize;
+
+use crate::{
+    formats,
+    pipeline::{Atlas, AtlasLayout, ColorSpace, Compression},
+    texture::write_image,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packed {
+    Grid(RgbaImage),
+    Array(Vec<RgbaImage>),
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub width: u32,
+    pub height: u32,
+    pub layers: u32,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Both rectangles are measured from the top left corner.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub layer: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub uv: [f32; 4],
+}
+
+impl Manifest {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+pub fn pack(atlas: &Atlas, sources: &[(String, RgbaImage)]) -> Result<(Packed, Manifest)> {
+    if sources.is_empty() {
+        return Err(anyhow!("Atlas `{}` has no sources", atlas.name));
+    }
+    match atlas.layout {
+        AtlasLayout::Grid => Ok(pack_grid(atlas, sources)),
+        AtlasLayout::Array => pack_array(atlas, sources),
+    }
+}
+
+pub fn grid_columns(count: u32, columns: Option<u32>) -> u32 {
+    let columns = columns.unwrap_or_else(|| (count as f64).sqrt().ceil() as u32);
+    columns.clamp(1, count.max(1))
+}
+
+/// Cells have the size of the largest image.
+fn pack_grid(atlas: &Atlas, sources: &[(String, RgbaImage)]) -> (Packed, Manifest) {
+    let count = sources.len() as u32;
+    let columns = grid_columns(count, atlas.columns);
+    let rows = count.div_ceil(columns);
+    let padding = atlas.padding;
+
+    let cell_w = sources.iter().map(|it| it.1.width()).max().unwrap_or(0) + 2 * padding;
+    let cell_h = sources.iter().map(|it| it.1.height()).max().unwrap_or(0) + 2 * padding;
+    let (w, h) = (columns * cell_w, rows * cell_h);
+
+    let mut image = RgbaImage::from_pixel(w, h, Rgba([0, 0, 0, 0]));
+    let mut entries = vec![];
+    for (i, (name, source)) in sources.iter().enumerate() {
+        let i = i as u32;
+        let x0 = (i % columns) * cell_w + padding;
+        let y0 = (i / columns) * cell_h + padding;
+        let (sw, sh) = source.dimensions();
+
+        // With bleeding, the padding repeats the closest pixel of the source.
+        let border = if atlas.bleed { padding } else { 0 };
+        for y in y0 - border..y0 + sh + border {
+            for x in x0 - border..x0 + sw + border {
+                let sx = (x as i64 - x0 as i64).clamp(0, sw as i64 - 1) as u32;
+                let sy = (y as i64 - y0 as i64).clamp(0, sh as i64 - 1) as u32;
+                image.put_pixel(x, y, *source.get_pixel(sx, sy));
+            }
+        }
+
+        entries.push(ManifestEntry {
+            name: name.clone(),
+            layer: 0,
+            x: x0,
+            y: y0,
+            width: sw,
+            height: sh,
+            uv: [
+                x0 as f32 / w as f32,
+                y0 as f32 / h as f32,
+                (x0 + sw) as f32 / w as f32,
+                (y0 + sh) as f32 / h as f32,
+            ],
+        });
+    }
+
+    let manifest = Manifest {
+        width: w,
+        height: h,
+        layers: 1,
+        entries,
+    };
+    (Packed::Grid(image), manifest)
+}
+
+fn pack_array(atlas: &Atlas, sources: &[(String, RgbaImage)]) -> Result<(Packed, Manifest)> {
+    let (w, h) = sources[0].1.dimensions();
+    if let Some((name, _)) = sources.iter().find(|it| it.1.dimensions() != (w, h)) {
+        return Err(anyhow!(
+            "Layers of the array `{}` need the same size, `{}` differs",
+            atlas.name,
+            name
+        ));
+    }
+
+    let entries = sources
+        .iter()
+        .enumerate()
+        .map(|(layer, (name, _))| ManifestEntry {
+            name: name.clone(),
+            layer: layer as u32,
+            x: 0,
+            y: 0,
+            width: w,
+            height: h,
+            uv: [0.0, 0.0, 1.0, 1.0],
+        })
+        .collect();
+    let layers = sources.iter().map(|it| it.1.clone()).collect();
+
+    let manifest = Manifest {
+        width: w,
+        height: h,
+        layers: sources.len() as u32,
+        entries,
+    };
+    Ok((Packed::Array(layers), manifest))
+}
+
+pub fn write(
+    packed: &Packed,
+    fname: &str,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<()> {
+    match packed {
+        Packed::Grid(image) if !formats::is_container(fname) => {
+            if compression.is_some() {
+                return Err(anyhow!("Compression needs a .dds or .ktx2 output: {fname}"));
+            }
+            write_image(image, fname)
+        }
+        Packed::Grid(image) => {
+            formats::write(std::slice::from_ref(image), fname, compression, color_space)
+        }
+        Packed::Array(layers) => {
+            let layers: Vec<&[RgbaImage]> = layers.iter().map(std::slice::from_ref).collect();
+            formats::write_layers(&layers, fname, compression, color_space)
+        }
+    }
+}
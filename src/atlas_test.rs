@@ -0,0 +1,127 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    atlas::{grid_columns, pack, Packed},
+    pipeline::{Atlas, AtlasLayout},
+};
+
+fn atlas(layout: AtlasLayout, columns: Option<u32>, padding: u32, bleed: bool) -> Atlas {
+    Atlas {
+        name: "out/atlas.png".to_string(),
+        sources: vec![],
+        layout,
+        columns,
+        padding,
+        bleed,
+        manifest: None,
+        compression: None,
+        color_space: Default::default(),
+    }
+}
+
+fn sources(sizes: &[(u32, u32)]) -> Vec<(String, RgbaImage)> {
+    sizes
+        .iter()
+        .enumerate()
+        .map(|(i, &(w, h))| {
+            let value = 50 * (i as u8 + 1);
+            (
+                format!("src{i}"),
+                RgbaImage::from_pixel(w, h, Rgba([value, 0, 0, 255])),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_grid_columns() {
+    assert_eq!(grid_columns(1, None), 1);
+    assert_eq!(grid_columns(4, None), 2);
+    assert_eq!(grid_columns(5, None), 3);
+    assert_eq!(grid_columns(5, Some(5)), 5);
+    assert_eq!(grid_columns(3, Some(8)), 3);
+    assert_eq!(grid_columns(3, Some(0)), 1);
+}
+
+#[test]
+fn test_grid_layout() {
+    let atlas = atlas(AtlasLayout::Grid, Some(2), 0, false);
+
+    let (packed, manifest) = pack(&atlas, &sources(&[(4, 2), (4, 2), (4, 2)])).unwrap();
+
+    let Packed::Grid(image) = packed else {
+        panic!("Grid atlas packed as an array");
+    };
+    assert_eq!(image.dimensions(), (8, 4));
+    assert_eq!(
+        (manifest.width, manifest.height, manifest.layers),
+        (8, 4, 1)
+    );
+    assert_eq!(image.get_pixel(5, 1), &Rgba([100, 0, 0, 255]));
+    assert_eq!(image.get_pixel(1, 3), &Rgba([150, 0, 0, 255]));
+    // The cell without a source stays transparent.
+    assert_eq!(image.get_pixel(5, 3), &Rgba([0, 0, 0, 0]));
+
+    let entry = &manifest.entries[1];
+    assert_eq!(entry.name, "src1");
+    assert_eq!((entry.x, entry.y, entry.width, entry.height), (4, 0, 4, 2));
+    assert_eq!(entry.uv, [0.5, 0.0, 1.0, 0.5]);
+}
+
+#[test]
+fn test_grid_padding() {
+    let atlas = atlas(AtlasLayout::Grid, None, 2, false);
+
+    let (packed, manifest) = pack(&atlas, &sources(&[(4, 4), (4, 4)])).unwrap();
+
+    let Packed::Grid(image) = packed else {
+        panic!("Grid atlas packed as an array");
+    };
+    assert_eq!(image.dimensions(), (16, 8));
+    assert_eq!((manifest.entries[1].x, manifest.entries[1].y), (10, 2));
+    assert_eq!(image.get_pixel(1, 1), &Rgba([0, 0, 0, 0]));
+    assert_eq!(image.get_pixel(2, 2), &Rgba([50, 0, 0, 255]));
+    assert_eq!(manifest.entries[0].uv, [0.125, 0.25, 0.375, 0.75]);
+}
+
+#[test]
+fn test_grid_bleed() {
+    let atlas = atlas(AtlasLayout::Grid, None, 2, true);
+    let mut source = RgbaImage::from_pixel(2, 2, Rgba([10, 10, 10, 255]));
+    source.put_pixel(0, 0, Rgba([200, 0, 0, 255]));
+
+    let (packed, _) = pack(&atlas, &[("src".to_string(), source)]).unwrap();
+
+    let Packed::Grid(image) = packed else {
+        panic!("Grid atlas packed as an array");
+    };
+    assert_eq!(image.dimensions(), (6, 6));
+    // The corner of the padding repeats the closest pixel of the source.
+    assert_eq!(image.get_pixel(0, 0), &Rgba([200, 0, 0, 255]));
+    assert_eq!(image.get_pixel(5, 0), &Rgba([10, 10, 10, 255]));
+    assert_eq!(image.get_pixel(0, 5), &Rgba([10, 10, 10, 255]));
+}
+
+#[test]
+fn test_array_layers() {
+    let atlas = atlas(AtlasLayout::Array, None, 4, true);
+
+    let (packed, manifest) = pack(&atlas, &sources(&[(4, 4), (4, 4), (4, 4)])).unwrap();
+
+    let Packed::Array(layers) = packed else {
+        panic!("Array packed as a grid");
+    };
+    assert_eq!(layers.len(), 3);
+    assert_eq!(layers[2].get_pixel(0, 0), &Rgba([150, 0, 0, 255]));
+    assert_eq!(manifest.layers, 3);
+    assert_eq!(manifest.entries[2].layer, 2);
+    assert_eq!(manifest.entries[2].uv, [0.0, 0.0, 1.0, 1.0]);
+}
+
+#[test]
+fn test_array_needs_same_size() {
+    let atlas = atlas(AtlasLayout::Array, None, 0, false);
+
+    assert!(pack(&atlas, &sources(&[(4, 4), (2, 2)])).is_err());
+    assert!(pack(&atlas, &[]).is_err());
+}
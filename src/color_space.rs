@@ -0,0 +1,72 @@
+use image::{DynamicImage, Rgba32FImage};
+
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn decode_srgb32f(image: &mut Rgba32FImage) {
+    map_colors_32f(image, srgb_to_linear);
+}
+
+pub fn encode_srgb32f(image: &mut Rgba32FImage) {
+    map_colors_32f(image, linear_to_srgb);
+}
+
+fn map_colors_32f(image: &mut Rgba32FImage, f: fn(f32) -> f32) {
+    for pixel in image.pixels_mut() {
+        for v in pixel.0.iter_mut().take(3) {
+            *v = f(*v);
+        }
+    }
+}
+
+pub fn decode_srgb(image: &DynamicImage) -> DynamicImage {
+    let mut image = image.to_rgba32f();
+    decode_srgb32f(&mut image);
+    image.into()
+}
+
+/// Every channel is in `[0, 1]`.
+pub fn hsv_to_rgb([h, s, v]: [f32; 3]) -> [f32; 3] {
+    let h = h.rem_euclid(1.0) * 6.0;
+    let f = h - h.floor();
+    let (p, q, t) = (v * (1.0 - s), v * (1.0 - s * f), v * (1.0 - s * (1.0 - f)));
+    match h as u32 {
+        0 => [v, t, p],
+        1 => [q, v, p],
+        2 => [p, v, t],
+        3 => [p, q, v],
+        4 => [t, p, v],
+        _ => [v, p, q],
+    }
+}
+
+/// The hue of grays is 0.
+pub fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    if delta <= 0.0 {
+        return [0.0, 0.0, max];
+    }
+
+    let h = if max == r {
+        (g - b) / delta
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    [(h / 6.0).rem_euclid(1.0), delta / max, max]
+}
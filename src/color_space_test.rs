@@ -0,0 +1,65 @@
+use image::{DynamicImage, ImageBuffer, Rgba, Rgba32FImage};
+
+use crate::color_space::{
+    decode_srgb, decode_srgb32f, encode_srgb32f, hsv_to_rgb, linear_to_srgb, rgb_to_hsv,
+    srgb_to_linear,
+};
+
+#[test]
+fn test_transfer_functions() {
+    assert_eq!(srgb_to_linear(0.0), 0.0);
+    assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+    assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+    assert!((linear_to_srgb(0.214) - 0.5).abs() < 1e-3);
+
+    for i in 0..=100 {
+        let v = i as f32 / 100.0;
+        assert!((linear_to_srgb(srgb_to_linear(v)) - v).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_decode_srgb_16_bit() {
+    let image = ImageBuffer::<Rgba<u16>, _>::from_pixel(1, 1, Rgba([32768, 65535, 0, 32768]));
+
+    let decoded = decode_srgb(&DynamicImage::ImageRgba16(image));
+
+    let [r, g, b, a] = decoded.to_rgba32f().get_pixel(0, 0).0;
+    assert!((r - 0.214).abs() < 1e-3);
+    assert_eq!((g, b), (1.0, 0.0));
+    assert!((a - 0.5).abs() < 1e-4);
+}
+
+#[test]
+fn test_hsv() {
+    assert_eq!(hsv_to_rgb([0.0, 1.0, 1.0]), [1.0, 0.0, 0.0]);
+    assert_eq!(hsv_to_rgb([0.5, 1.0, 0.5]), [0.0, 0.5, 0.5]);
+    assert_eq!(hsv_to_rgb([1.0, 1.0, 1.0]), [1.0, 0.0, 0.0]);
+    assert_eq!(hsv_to_rgb([0.3, 0.0, 0.25]), [0.25, 0.25, 0.25]);
+    assert_eq!(rgb_to_hsv([0.25, 0.25, 0.25]), [0.0, 0.0, 0.25]);
+
+    for rgb in [
+        [1.0, 0.5, 0.0],
+        [0.2, 0.9, 0.4],
+        [0.1, 0.3, 0.8],
+        [0.7, 0.0, 0.6],
+    ] {
+        let back = hsv_to_rgb(rgb_to_hsv(rgb));
+        for (a, b) in back.iter().zip(rgb) {
+            assert!((a - b).abs() < 1e-5, "{rgb:?} -> {back:?}");
+        }
+    }
+}
+
+#[test]
+fn test_srgb32f_keeps_alpha() {
+    let mut image = Rgba32FImage::from_pixel(1, 1, Rgba([0.5, 1.0, 0.0, 0.5]));
+
+    decode_srgb32f(&mut image);
+    let [r, g, b, a] = image.get_pixel(0, 0).0;
+    assert!((r - 0.214).abs() < 1e-3);
+    assert_eq!((g, b, a), (1.0, 0.0, 0.5));
+
+    encode_srgb32f(&mut image);
+    assert!((image.get_pixel(0, 0)[0] - 0.5).abs() < 1e-5);
+}
@@ -1,51 +1,109 @@
 use anyhow::{anyhow, Context, Result};
 use std::{
     collections::{HashMap, HashSet},
-    fs::File,
+    fs::{self, File},
     hash::Hash,
-    time::SystemTime,
+    path::Path,
+    time::{Duration, SystemTime},
 };
 
 use crate::{
+    encoder::Encoder,
     expirable::Expirable,
-    mesh::Mesh,
-    pipeline::{Expr, Input, Pipeline, Preview, Source, Stage},
+    formats,
+    mesh::{load_geometry, Geometry, Mesh},
+    pipeline::{
+        update_variables_yaml, AnimatedOutput, Atlas, AtlasLayout, ColorSpace, Compression,
+        CubemapLayout, Depth, Expr, Input, MeshShape, MipFilter, Op, Pipeline, Preview, Source,
+        Stage, StageError, StageKind,
+    },
     preprocessor::preprocess_shader,
+    preview::{Panel, TextCache, View},
+    profiler::GpuTimer,
     project_path::ProjectPath,
     shader::ShaderProgram,
+    storage_buffer::StorageBuffer,
     texture::Texture,
+    texture_pool::TexturePool,
+    trace,
 };
 
 pub struct Ctx {
     pub project_path: ProjectPath,
 
     pub textures: HashMap<String, Expirable<Texture>>,
+    pub pool: TexturePool,
+    pub encoder: Encoder,
     pub shaders: HashMap<String, Expirable<ShaderProgram>>,
+    pub meshes: HashMap<String, Expirable<Mesh>>,
+    pub buffers: HashMap<String, StorageBuffer>,
     pub variables: HashMap<String, Expr>,
+    pub dirty_variables: HashSet<String>,
+    pub compile_times: HashMap<String, Duration>,
+    /// Kept between executions, the results are read once the GPU is done.
+    pub gpu_timers: HashMap<String, GpuTimer>,
+    pub executions: u64,
+    pub upload_times: HashMap<String, Duration>,
+    image_settings: HashMap<String, (Option<Depth>, ColorSpace)>,
 
     pub default_shader: ShaderProgram,
+    pub preview_shader: ShaderProgram,
+    pub material_shader: ShaderProgram,
+    pub mesh_shader: ShaderProgram,
     pub default_mesh: Mesh,
     pub reversed_mesh: Mesh,
 
+    pub view: View,
+    pub text: TextCache,
+    pub panel: Panel,
+
     pub logs_enabled: bool,
 }
 
 const DEFAULT_VERTEX_SHADER: &str = include_str!("shaders/default.vert");
 const DEFAULT_FRAGMENT_SHADER: &str = include_str!("shaders/default.frag");
+const PREVIEW_FRAGMENT_SHADER: &str = include_str!("shaders/preview.frag");
+const MATERIAL_FRAGMENT_SHADER: &str = include_str!("shaders/material.frag");
+const MESH_VERTEX_SHADER: &str = include_str!("shaders/mesh.vert");
+const MESH_FRAGMENT_SHADER: &str = include_str!("shaders/mesh.frag");
+
+const PLANE_SUBDIVISIONS: u32 = 128;
+const SPHERE_SEGMENTS: u32 = 64;
 
 impl Ctx {
-    pub fn load(project_path: ProjectPath, pipe: &Expirable<Pipeline>) -> Result<Self> {
+    pub fn load(
+        project_path: ProjectPath,
+        pipe: &Expirable<Pipeline>,
+        window_size: (u32, u32),
+    ) -> Result<Self> {
         let mut ctx = Self {
             project_path,
             textures: HashMap::new(),
+            pool: TexturePool::default(),
+            encoder: Encoder::with_available_parallelism(),
             shaders: HashMap::new(),
+            meshes: HashMap::new(),
+            buffers: HashMap::new(),
             variables: HashMap::new(),
+            dirty_variables: HashSet::new(),
+            compile_times: HashMap::new(),
+            gpu_timers: HashMap::new(),
+            executions: 0,
+            upload_times: HashMap::new(),
+            image_settings: HashMap::new(),
             default_shader: ShaderProgram::new(DEFAULT_VERTEX_SHADER, DEFAULT_FRAGMENT_SHADER)?,
+            preview_shader: ShaderProgram::new(DEFAULT_VERTEX_SHADER, PREVIEW_FRAGMENT_SHADER)?,
+            material_shader: ShaderProgram::new(DEFAULT_VERTEX_SHADER, MATERIAL_FRAGMENT_SHADER)?,
+            mesh_shader: ShaderProgram::new(MESH_VERTEX_SHADER, MESH_FRAGMENT_SHADER)?,
             default_mesh: Mesh::default_plain(false),
             reversed_mesh: Mesh::default_plain(true),
+            view: View::new(window_size),
+            text: TextCache::default(),
+            panel: Panel::default(),
             logs_enabled: true,
         };
 
+        trace::set_enabled(pipe.data().trace_file().is_some());
         ctx.refresh_variables(pipe.data());
         ctx.refresh_stages(pipe.data())?;
 
@@ -53,8 +111,7 @@ impl Ctx {
     }
 
     pub fn refresh_pipeline(&mut self, pipe: &mut Expirable<Pipeline>) -> Result<bool> {
-        let path = self.project_path.main();
-        let modified = file_modified(&path)?;
+        let modified = pipe.data().modified(&self.project_path)?;
 
         let mut changed = false;
 
@@ -63,7 +120,10 @@ impl Ctx {
             if self.logs_enabled {
                 println!("pipeline file expired");
             }
+            let span = trace::span("reload", "load_pipeline");
             *pipe = Expirable::now(Pipeline::load_from_file(&self.project_path)?);
+            drop(span);
+            trace::set_enabled(pipe.data().trace_file().is_some());
             self.refresh_variables(pipe.data());
         }
 
@@ -72,6 +132,31 @@ impl Ctx {
         Ok(changed)
     }
 
+    pub fn save_variables(&self, pipe: &Pipeline) -> Result<()> {
+        let path = self.project_path.main();
+        let src = fs::read_to_string(&path)?;
+
+        let mut variables: Vec<(&String, &Expr)> = pipe
+            .variables
+            .keys()
+            .filter(|name| !pipe.imported_variables.contains(*name))
+            .filter_map(|name| self.variables.get_key_value(name))
+            .collect();
+        variables.sort_by_key(|it| it.0);
+
+        fs::write(&path, update_variables_yaml(&src, &variables)?)?;
+
+        if self.logs_enabled {
+            println!("Variables saved to `{path}`");
+            if !pipe.imported_variables.is_empty() {
+                println!(
+                    "Parameters of imports are not saved, set them in `parameters` of the imports"
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn refresh_variables(&mut self, pipe: &Pipeline) {
         for (name, expr) in pipe.variables.iter() {
             self.variables.insert(name.clone(), expr.clone());
@@ -79,77 +164,272 @@ impl Ctx {
     }
 
     fn refresh_stages(&mut self, pipe: &Pipeline) -> Result<bool> {
+        let _span = trace::span("reload", "refresh_stages");
         let mut textures = HashSet::new();
         let mut shaders = HashSet::new();
+        let mut meshes = HashSet::new();
+        let mut buffers = HashMap::new();
+        let mut previewed = Vec::new();
+        let mut outputs = HashSet::new();
+
+        if let Some(animation) = &pipe.animation {
+            animation.validate()?;
+        }
 
         let mut changed = false;
 
-        for stage in pipe.pipeline.iter() {
-            changed |= self.refresh_shader(stage)?;
-            shaders.insert(stage.shader.clone());
+        for (idx, stage) in pipe.pipeline.iter().enumerate() {
+            changed |= self
+                .refresh_stage(stage, &mut textures, &outputs)
+                .with_context(|| StageError::new(idx, stage))?;
+            if stage.op.is_none() {
+                shaders.insert(stage.shader.clone());
+            }
+            if let Some(name) = mip_shader(stage) {
+                shaders.insert(name.clone());
+            }
 
-            for input in stage.inputs.iter() {
-                changed |= self.refresh_input(input, &mut textures)?;
+            for binding in stage.buffers.iter() {
+                let size = buffers.insert(binding.name.clone(), binding.size);
+                if size.is_some_and(|it| it != binding.size) {
+                    return Err(anyhow!(
+                        "Buffer `{}` is declared with different sizes",
+                        binding.name
+                    ))
+                    .with_context(|| StageError::new(idx, stage));
+                }
             }
 
             match stage.output.dst {
                 Source::Memory => {
-                    textures.insert(stage.output.name.clone());
+                    textures.extend(stage.output_names());
                 }
                 Source::File => (),
             }
+            outputs.extend(stage.output_names());
 
-            match stage.output.preview {
+            match &stage.output.preview {
                 Preview::Disabled => (),
-                Preview::Simple => {
+                Preview::Material(material) => {
+                    textures.insert(stage.output.name.clone());
+                    previewed.extend(material.textures());
+                }
+                Preview::Mesh(mesh) => {
+                    textures.insert(stage.output.name.clone());
+                    previewed.extend(mesh.textures());
+                    self.refresh_mesh(&mesh.mesh)?;
+                    meshes.insert(mesh.mesh.key());
+                }
+                _ => {
                     textures.insert(stage.output.name.clone());
                 }
             }
         }
 
+        // The sources are kept, so an atlas can be packed again
+        // when only some of them are reexecuted.
+        for atlas in pipe.atlases.iter() {
+            validate_atlas(atlas)?;
+            for name in pipe.atlas_sources(atlas) {
+                if !outputs.contains(&name) {
+                    return Err(anyhow!("Unknown resource in atlas: {}", name));
+                }
+                textures.insert(name);
+            }
+        }
+
+        for name in previewed {
+            if !textures.contains(name) {
+                return Err(anyhow!("Unknown resource in preview: {}", name));
+            }
+        }
+
         drain_filter(&mut self.textures, |it| textures.contains(it));
         drain_filter(&mut self.shaders, |it| shaders.contains(it));
+        drain_filter(&mut self.meshes, |it| meshes.contains(it));
+        drain_filter(&mut self.buffers, |it| buffers.contains_key(it));
+        drain_filter(&mut self.compile_times, |it| shaders.contains(it));
+        drain_filter(&mut self.gpu_timers, |it| {
+            pipe.pipeline.iter().any(|stage| &stage.output.name == it)
+        });
+        drain_filter(&mut self.upload_times, |it| textures.contains(it));
+        drain_filter(&mut self.image_settings, |it| textures.contains(it));
+
+        Ok(changed)
+    }
+
+    fn refresh_stage(
+        &mut self,
+        stage: &Stage,
+        textures: &mut HashSet<String>,
+        outputs: &HashSet<String>,
+    ) -> Result<bool> {
+        let mut changed = match (&stage.op, stage.shader.is_empty()) {
+            (Some(_), false) => return Err(anyhow!("Stage has both `shader` and `op`")),
+            (None, true) => return Err(anyhow!("Stage needs either `shader` or `op`")),
+            (Some(op), true) => self.refresh_op(op, stage, textures, outputs)?,
+            (None, false) => self.refresh_shader(stage)?,
+        };
+        changed |= self.refresh_buffers(stage)?;
+        validate_output(stage)?;
+        if let Some(name) = mip_shader(stage) {
+            changed |= self.refresh_program(name, StageKind::Fragment, None)?;
+        }
+
+        for input in stage.inputs.iter() {
+            changed |= self.refresh_input(input, textures)?;
+        }
+
+        Ok(changed)
+    }
+
+    /// Names which are not earlier outputs are loaded as image files.
+    fn refresh_op(
+        &mut self,
+        op: &Op,
+        stage: &Stage,
+        textures: &mut HashSet<String>,
+        outputs: &HashSet<String>,
+    ) -> Result<bool> {
+        let mut changed = false;
+
+        for name in op.inputs() {
+            if outputs.contains(name) {
+                // File outputs are kept in memory for the operation.
+                textures.insert(name.clone());
+                continue;
+            }
+            if textures.contains(name) {
+                continue;
+            }
+
+            let fname = self.project_path.path(name);
+            if !Path::new(&fname).is_file() {
+                return Err(anyhow!("Unknown resource in input: {}", name));
+            }
+            let settings = stage
+                .inputs
+                .iter()
+                .find_map(|input| match input {
+                    Input::File {
+                        name: file,
+                        depth,
+                        color_space,
+                        ..
+                    } if file == name => Some((*depth, *color_space)),
+                    _ => None,
+                })
+                .unwrap_or((None, ColorSpace::Data));
+            textures.insert(name.clone());
+            changed |= self.refresh_image(&fname, name, settings)?;
+        }
+
+        Ok(changed)
+    }
+
+    fn refresh_buffers(&mut self, stage: &Stage) -> Result<bool> {
+        let mut changed = false;
+
+        for binding in stage.buffers.iter() {
+            let buffer = self.buffers.get(&binding.name);
+            if buffer.is_some_and(|it| it.len() == binding.size) {
+                continue;
+            }
+
+            self.buffers
+                .insert(binding.name.clone(), StorageBuffer::new(binding.size));
+            changed = true;
+        }
 
         Ok(changed)
     }
 
     fn refresh_shader(&mut self, stage: &Stage) -> Result<bool> {
-        let fname = self.project_path.path(&stage.shader);
+        self.refresh_program(&stage.shader, stage.kind, stage.debug_shader.as_ref())
+    }
+
+    fn refresh_program(
+        &mut self,
+        name: &str,
+        kind: StageKind,
+        debug_shader: Option<&String>,
+    ) -> Result<bool> {
+        let fname = self.project_path.path(name);
         let modified = file_modified(&fname)?;
 
-        let shader = self.shaders.get(&stage.shader);
+        let compute = kind == StageKind::Compute;
+        let shader = self.shaders.get(name);
         if let Some(shader) = shader {
-            if !shader.expired(modified) {
+            if !shader.expired(modified) && shader.data().is_compute() == compute {
                 return Ok(false);
             }
         }
 
         if self.logs_enabled {
-            println!("Shader `{}` expired", stage.shader);
+            println!("Shader `{}` expired", name);
         }
+        let start = SystemTime::now();
         let shader = preprocess_shader(
             &fname,
-            &stage
-                .debug_shader
-                .as_ref()
-                .map(|path| self.project_path.path(path)),
+            &debug_shader.map(|path| self.project_path.path(path)),
         )?;
 
-        let shader = ShaderProgram::new(DEFAULT_VERTEX_SHADER, &shader)
-            .with_context(|| format!("Failed to create shader program: {fname}"))?;
+        let _span = trace::span("compile", "compile_shader").arg("shader", name);
+        let shader = match kind {
+            StageKind::Fragment => ShaderProgram::new(DEFAULT_VERTEX_SHADER, &shader),
+            StageKind::Compute => ShaderProgram::compute(&shader),
+        }
+        .with_context(|| format!("Failed to create shader program: {fname}"))?;
+        self.compile_times
+            .insert(name.to_string(), start.elapsed()?);
 
         self.shaders
-            .insert(stage.shader.to_string(), Expirable::now(shader));
+            .insert(name.to_string(), Expirable::now(shader));
 
         Ok(true)
     }
 
+    fn refresh_mesh(&mut self, shape: &MeshShape) -> Result<()> {
+        let key = shape.key();
+        let mesh = self.meshes.get(&key);
+
+        let geometry = match shape {
+            MeshShape::File(name) => {
+                let fname = self.project_path.path(name);
+                let modified = file_modified(&fname)?;
+                if mesh.is_some_and(|it| !it.expired(modified)) {
+                    return Ok(());
+                }
+
+                if self.logs_enabled {
+                    println!("Mesh `{}` expired", fname);
+                }
+                load_geometry(&fname)?
+            }
+            _ if mesh.is_some() => return Ok(()),
+            MeshShape::Plane => Geometry::plane(PLANE_SUBDIVISIONS),
+            MeshShape::Sphere => Geometry::sphere(SPHERE_SEGMENTS, SPHERE_SEGMENTS / 2),
+            MeshShape::Cube => Geometry::cube(),
+            MeshShape::Cylinder => Geometry::cylinder(SPHERE_SEGMENTS),
+        };
+
+        self.meshes
+            .insert(key, Expirable::now(Mesh::from_geometry(&geometry)));
+
+        Ok(())
+    }
+
     fn refresh_input(&mut self, input: &Input, r: &mut HashSet<String>) -> Result<bool> {
         match input {
-            Input::File { name, .. } => {
+            Input::File {
+                name,
+                depth,
+                color_space,
+                ..
+            } => {
                 let fname = self.project_path.path(name);
                 r.insert(name.clone());
-                Ok(self.refresh_image(&fname, name)?)
+                Ok(self.refresh_image(&fname, name, (*depth, *color_space))?)
             }
             Input::Memory { name, .. } => {
                 if !r.contains(name) && !self.variables.contains_key(name) {
@@ -161,11 +441,18 @@ impl Ctx {
         }
     }
 
-    fn refresh_image(&mut self, fname: &str, name: &str) -> Result<bool> {
+    /// Reloads the image when its settings change.
+    fn refresh_image(
+        &mut self,
+        fname: &str,
+        name: &str,
+        settings: (Option<Depth>, ColorSpace),
+    ) -> Result<bool> {
         if let Some(texture) = self.textures.get(name) {
             let modified = file_modified(fname)?;
+            let same_settings = self.image_settings.get(name) == Some(&settings);
 
-            if !texture.expired(modified) {
+            if !texture.expired(modified) && same_settings {
                 return Ok(false);
             }
         }
@@ -173,13 +460,189 @@ impl Ctx {
         if self.logs_enabled {
             println!("Image `{}` expired", fname);
         }
-        let texture = Expirable::now(Texture::from_file(fname)?);
+        let _span = trace::span("upload", "load_image").arg("file", fname);
+        let start = SystemTime::now();
+        let texture = Expirable::now(Texture::from_file(fname, settings.0, settings.1)?);
+        self.upload_times.insert(name.to_string(), start.elapsed()?);
+        self.image_settings.insert(name.to_string(), settings);
         self.textures.insert(name.to_string(), texture);
 
         Ok(true)
     }
 }
 
+pub fn validate_output(stage: &Stage) -> Result<()> {
+    let output = &stage.output;
+    let container = formats::is_container(&output.name);
+
+    if (container || output.mips.is_some()) && output.depth() != Depth::U8 {
+        return Err(anyhow!(
+            "Mips and .dds or .ktx2 outputs need the `u8` depth: {}",
+            output.name
+        ));
+    }
+    if output.compression.is_some() && !container {
+        return Err(anyhow!(
+            "Compressed output `{}` is not a .dds or .ktx2 file",
+            output.name
+        ));
+    }
+
+    if output.color_space == ColorSpace::Srgb {
+        if output.depth() != Depth::U8 {
+            return Err(anyhow!(
+                "sRGB output `{}` needs the `u8` depth",
+                output.name
+            ));
+        }
+        if stage.kind == StageKind::Compute {
+            return Err(anyhow!(
+                "Compute shaders cannot write the sRGB output `{}`",
+                output.name
+            ));
+        }
+        if let Some(compression @ (Compression::Bc4 | Compression::Bc5)) = output.compression {
+            return Err(anyhow!(
+                "{compression:?} has no sRGB variant, use `linear` or `data`: {}",
+                output.name
+            ));
+        }
+    }
+
+    if let Some(animation) = output.animation {
+        let extension = std::path::Path::new(&output.name)
+            .extension()
+            .map(|it| it.to_string_lossy().to_lowercase());
+        let expected = match animation {
+            AnimatedOutput::Sequence => None,
+            AnimatedOutput::Flipbook(_) => extension.clone(),
+            AnimatedOutput::Gif => Some("gif".to_string()),
+            AnimatedOutput::Apng => Some("png".to_string()),
+        };
+        if extension != expected {
+            return Err(anyhow!(
+                "{animation:?} output `{}` has the wrong file format",
+                output.name
+            ));
+        }
+        let single_image = output.mips.is_none() && !container && output.depth() == Depth::U8;
+        if animation != AnimatedOutput::Sequence && !single_image {
+            return Err(anyhow!(
+                "{animation:?} output `{}` needs the `u8` depth and no mips or container",
+                output.name
+            ));
+        }
+    }
+
+    if let Some(volume) = output.volume {
+        if volume.slices == 0 {
+            return Err(anyhow!("Volume `{}` has no slices", output.name));
+        }
+        if stage.op.is_some() {
+            return Err(anyhow!(
+                "Operations cannot write the volume `{}`",
+                output.name
+            ));
+        }
+        if output.mips.is_some() || output.compression.is_some() || container {
+            return Err(anyhow!(
+                "Volume `{}` cannot have mips, compression or a container",
+                output.name
+            ));
+        }
+        if output
+            .animation
+            .is_some_and(|it| it != AnimatedOutput::Sequence)
+        {
+            return Err(anyhow!(
+                "Volume `{}` can only be animated as a sequence",
+                output.name
+            ));
+        }
+        if matches!(output.preview, Preview::Material(_) | Preview::Mesh(_)) {
+            return Err(anyhow!(
+                "Volume `{}` has no material or mesh preview",
+                output.name
+            ));
+        }
+    }
+
+    if let Some(layout) = output.cubemap {
+        if stage.op.is_some() || output.volume.is_some() {
+            return Err(anyhow!(
+                "Operations and volumes cannot write the cubemap `{}`",
+                output.name
+            ));
+        }
+        if output.width != output.height {
+            return Err(anyhow!("Cubemap `{}` needs square faces", output.name));
+        }
+        if container && layout != CubemapLayout::Faces {
+            return Err(anyhow!(
+                "Cubemap `{}` is stored with its faces, it has no {layout:?} layout",
+                output.name
+            ));
+        }
+        if output.mips.is_some() && (!container || mip_shader(stage).is_some()) {
+            return Err(anyhow!(
+                "Cubemap `{}` needs a .dds or .ktx2 file and a built-in filter for mips",
+                output.name
+            ));
+        }
+        if output
+            .animation
+            .is_some_and(|it| it != AnimatedOutput::Sequence)
+        {
+            return Err(anyhow!(
+                "Cubemap `{}` can only be animated as a sequence",
+                output.name
+            ));
+        }
+        if matches!(output.preview, Preview::Material(_) | Preview::Mesh(_)) {
+            return Err(anyhow!(
+                "Cubemap `{}` has no material or mesh preview",
+                output.name
+            ));
+        }
+    }
+
+    if stage.op.is_some() && stage.variations() > 1 {
+        return Err(anyhow!(
+            "Operations have no variations: {}",
+            stage.output.name
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_atlas(atlas: &Atlas) -> Result<()> {
+    let container = formats::is_container(&atlas.name);
+
+    if atlas.layout == AtlasLayout::Array && !container {
+        return Err(anyhow!(
+            "Texture array `{}` is not a .dds or .ktx2 file",
+            atlas.name
+        ));
+    }
+    if atlas.compression.is_some() && !container {
+        return Err(anyhow!(
+            "Compressed atlas `{}` is not a .dds or .ktx2 file",
+            atlas.name
+        ));
+    }
+    if let Some(compression @ (Compression::Bc4 | Compression::Bc5)) = atlas.compression {
+        if atlas.color_space == ColorSpace::Srgb {
+            return Err(anyhow!(
+                "{compression:?} has no sRGB variant, use `linear` or `data`: {}",
+                atlas.name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 fn file_modified(path: &str) -> Result<SystemTime> {
     let time = File::open(path)?.metadata()?.modified()?;
     Ok(time)
@@ -199,3 +662,10 @@ where
         map.remove(&t);
     }
 }
+
+fn mip_shader(stage: &Stage) -> Option<&String> {
+    match stage.output.mips.as_ref().map(|it| &it.filter) {
+        Some(MipFilter::Shader(name)) => Some(name),
+        _ => None,
+    }
+}
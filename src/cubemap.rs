@@ -0,0 +1,202 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use anyhow::{anyhow, Result};
+use image::{imageops, DynamicImage, ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
+
+use crate::{
+    formats,
+    mips::{self, MipChain},
+    pipeline::{suffixed_name, ColorSpace, Compression, CubemapLayout},
+    texture::write_dynamic_image,
+    volume::blank_like,
+};
+
+/// `dir + (2u - 1) * u + (2v - 1) * v` points at the texture coordinates `u`, `v`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Face {
+    pub label: &'static str,
+    pub suffix: &'static str,
+    pub dir: [f32; 3],
+    pub u: [f32; 3],
+    pub v: [f32; 3],
+}
+
+pub const FACES: [Face; 6] = [
+    Face {
+        label: "+X",
+        suffix: "_px",
+        dir: [1.0, 0.0, 0.0],
+        u: [0.0, 0.0, -1.0],
+        v: [0.0, -1.0, 0.0],
+    },
+    Face {
+        label: "-X",
+        suffix: "_nx",
+        dir: [-1.0, 0.0, 0.0],
+        u: [0.0, 0.0, 1.0],
+        v: [0.0, -1.0, 0.0],
+    },
+    Face {
+        label: "+Y",
+        suffix: "_py",
+        dir: [0.0, 1.0, 0.0],
+        u: [1.0, 0.0, 0.0],
+        v: [0.0, 0.0, 1.0],
+    },
+    Face {
+        label: "-Y",
+        suffix: "_ny",
+        dir: [0.0, -1.0, 0.0],
+        u: [1.0, 0.0, 0.0],
+        v: [0.0, 0.0, -1.0],
+    },
+    Face {
+        label: "+Z",
+        suffix: "_pz",
+        dir: [0.0, 0.0, 1.0],
+        u: [1.0, 0.0, 0.0],
+        v: [0.0, -1.0, 0.0],
+    },
+    Face {
+        label: "-Z",
+        suffix: "_nz",
+        dir: [0.0, 0.0, -1.0],
+        u: [-1.0, 0.0, 0.0],
+        v: [0.0, -1.0, 0.0],
+    },
+];
+
+const CROSS_CELLS: [(u32, u32); 6] = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn face_coords(dir: [f32; 3]) -> (usize, f32, f32) {
+    let (face, major) = FACES
+        .iter()
+        .map(|it| dot(dir, it.dir))
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0));
+    let face_dir = &FACES[face];
+    let major = major.max(f32::EPSILON);
+    let u = (dot(dir, face_dir.u) / major + 1.0) * 0.5;
+    let v = (dot(dir, face_dir.v) / major + 1.0) * 0.5;
+    (face, u, v)
+}
+
+/// The center looks at -Z, with +X on its right.
+pub fn equirect_direction(x: u32, y: u32, width: u32, height: u32) -> [f32; 3] {
+    let longitude = (x as f32 + 0.5) / width as f32 * 2.0 * PI - PI;
+    let latitude = FRAC_PI_2 - (y as f32 + 0.5) / height as f32 * PI;
+    [
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+        -latitude.cos() * longitude.cos(),
+    ]
+}
+
+/// Containers store the faces with their mips, other files use the layout.
+pub fn write(
+    strip: DynamicImage,
+    layout: CubemapLayout,
+    fname: &str,
+    mips: MipChain,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<()> {
+    if formats::is_container(fname) {
+        return write_container(&strip, fname, mips, compression, color_space);
+    }
+    match layout {
+        CubemapLayout::Faces => {
+            for (face, image) in FACES.iter().zip(faces(&strip)) {
+                write_dynamic_image(&image, &suffixed_name(fname, face.suffix))?;
+            }
+            Ok(())
+        }
+        CubemapLayout::Cross => write_dynamic_image(&cross(&strip), fname),
+        CubemapLayout::Equirect => write_dynamic_image(&equirect(&strip), fname),
+    }
+}
+
+fn write_container(
+    strip: &DynamicImage,
+    fname: &str,
+    mips: MipChain,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<()> {
+    let levels: Vec<Vec<RgbaImage>> = faces(strip)
+        .into_iter()
+        .map(|face| match &mips {
+            MipChain::None => Ok(vec![face.into_rgba8()]),
+            MipChain::Generate {
+                filter,
+                edges,
+                levels,
+                srgb,
+            } => Ok(mips::generate(
+                face.into_rgba8(),
+                *filter,
+                *edges,
+                *levels,
+                *srgb,
+            )),
+            MipChain::Levels(_) => Err(anyhow!("Cubemap mips cannot be rendered by a shader")),
+        })
+        .collect::<Result<_>>()?;
+    let levels: Vec<&[RgbaImage]> = levels.iter().map(|it| it.as_slice()).collect();
+    formats::write_cubemap(&levels, fname, compression, color_space)
+}
+
+pub fn faces(strip: &DynamicImage) -> Vec<DynamicImage> {
+    let (w, h) = (strip.width(), strip.height() / 6);
+    (0..6)
+        .map(|face| strip.crop_imm(0, face * h, w, h))
+        .collect()
+}
+
+/// The middle row goes from -X to -Z around the horizon.
+pub fn cross(strip: &DynamicImage) -> DynamicImage {
+    let (w, h) = (strip.width(), strip.height() / 6);
+    let mut res = blank_like(strip, 4 * w, 3 * h);
+    for (face, (column, row)) in faces(strip).iter().zip(CROSS_CELLS) {
+        imageops::replace(&mut res, face, (column * w) as i64, (row * h) as i64);
+    }
+    res
+}
+
+pub fn equirect(strip: &DynamicImage) -> DynamicImage {
+    let size = strip.width();
+    let faces: Vec<Rgba32FImage> = faces(strip).iter().map(|it| it.to_rgba32f()).collect();
+    let (w, h) = (4 * size, 2 * size);
+    let panorama = ImageBuffer::from_fn(w, h, |x, y| {
+        let (face, u, v) = face_coords(equirect_direction(x, y, w, h));
+        sample_bilinear(&faces[face], u, v)
+    });
+
+    let panorama = DynamicImage::ImageRgba32F(panorama);
+    match strip {
+        DynamicImage::ImageRgba32F(_) => panorama,
+        DynamicImage::ImageRgba16(_) => DynamicImage::ImageRgba16(panorama.to_rgba16()),
+        _ => DynamicImage::ImageRgba8(panorama.to_rgba8()),
+    }
+}
+
+fn sample_bilinear(image: &Rgba32FImage, u: f32, v: f32) -> Rgba<f32> {
+    let (w, h) = image.dimensions();
+    let x = (u * w as f32 - 0.5).clamp(0.0, (w - 1) as f32);
+    let y = (v * h as f32 - 0.5).clamp(0.0, (h - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let lerp = |a: &Rgba<f32>, b: &Rgba<f32>, t: f32| -> [f32; 4] {
+        std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+    };
+    let top = lerp(image.get_pixel(x0, y0), image.get_pixel(x1, y0), fx);
+    let bottom = lerp(image.get_pixel(x0, y1), image.get_pixel(x1, y1), fx);
+    Rgba(std::array::from_fn(|i| top[i] + (bottom[i] - top[i]) * fy))
+}
@@ -0,0 +1,84 @@
+use image::Rgba;
+
+use crate::{
+    cubemap::{cross, equirect, equirect_direction, face_coords, write, FACES},
+    mips::MipChain,
+    pipeline::{ColorSpace, CubemapLayout, Stage},
+    test_util::{strip, TempDir},
+};
+
+#[test]
+fn test_cubemap_parse() {
+    let stage = r#"
+        shader: sky.glsl
+        output: { dst: file, name: out/sky.png, width: 64, height: 64, cubemap: equirect }
+    "#;
+    let stage: Stage = serde_yaml::from_str(stage).unwrap();
+
+    assert_eq!(stage.output.cubemap, Some(CubemapLayout::Equirect));
+}
+
+#[test]
+fn test_face_coords() {
+    for (idx, face) in FACES.iter().enumerate() {
+        assert_eq!(face_coords(face.dir), (idx, 0.5, 0.5));
+
+        let corner: [f32; 3] = std::array::from_fn(|i| face.dir[i] - 0.5 * face.u[i]);
+        let (found, u, v) = face_coords(corner);
+        assert_eq!((found, u, v), (idx, 0.25, 0.5));
+    }
+}
+
+#[test]
+fn test_equirect_direction() {
+    let [x, y, z] = equirect_direction(50, 25, 100, 50);
+    assert!(x.abs() < 0.05 && y.abs() < 0.05 && z < -0.99);
+
+    let [x, _, _] = equirect_direction(75, 25, 100, 50);
+    assert!(x > 0.99);
+}
+
+#[test]
+fn test_cross() {
+    let cross = cross(&strip(6)).to_rgba8();
+
+    assert_eq!(cross.dimensions(), (8, 6));
+    assert_eq!(cross.get_pixel(2, 0), &Rgba([2, 0, 0, 255]));
+    assert_eq!(cross.get_pixel(0, 2), &Rgba([1, 0, 0, 255]));
+    assert_eq!(cross.get_pixel(2, 2), &Rgba([4, 0, 0, 255]));
+    assert_eq!(cross.get_pixel(4, 2), &Rgba([0, 0, 0, 255]));
+    assert_eq!(cross.get_pixel(6, 2), &Rgba([5, 0, 0, 255]));
+    assert_eq!(cross.get_pixel(2, 4), &Rgba([3, 0, 0, 255]));
+    assert_eq!(cross.get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+}
+
+#[test]
+fn test_equirect() {
+    let panorama = equirect(&strip(6)).to_rgba8();
+
+    assert_eq!(panorama.dimensions(), (8, 4));
+    // Top and bottom rows look up and down, the middle of the center looks at -Z.
+    assert_eq!(panorama.get_pixel(0, 0), &Rgba([2, 0, 0, 255]));
+    assert_eq!(panorama.get_pixel(5, 3), &Rgba([3, 0, 0, 255]));
+    assert_eq!(panorama.get_pixel(4, 2), &Rgba([5, 0, 0, 255]));
+}
+
+#[test]
+fn test_write_faces() {
+    let dir = TempDir::new("cubemap");
+    let fname = dir.file("sky.png");
+
+    write(
+        strip(6),
+        CubemapLayout::Faces,
+        &fname,
+        MipChain::None,
+        None,
+        ColorSpace::Data,
+    )
+    .unwrap();
+
+    let face = image::open(dir.file("sky_nz.png")).unwrap().to_rgba8();
+    assert_eq!(face.dimensions(), (2, 2));
+    assert_eq!(face.get_pixel(1, 1), &Rgba([5, 0, 0, 255]));
+}
@@ -0,0 +1,202 @@
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use image::DynamicImage;
+
+use crate::{
+    cubemap, formats,
+    mips::{self, MipChain},
+    pipeline::{ColorSpace, Compression, CubemapLayout, Volume},
+    texture::write_dynamic_image,
+    volume,
+};
+
+const MAX_WORKERS: usize = 4;
+
+/// Mips and containers are 8-bit only.
+fn write(
+    image: DynamicImage,
+    fname: &str,
+    mips: MipChain,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+    volume: Option<Volume>,
+    cubemap: Option<CubemapLayout>,
+) -> Result<()> {
+    if let Some(volume) = volume {
+        return volume::write(&image, volume, fname);
+    }
+    if let Some(layout) = cubemap {
+        return cubemap::write(image, layout, fname, mips, compression, color_space);
+    }
+
+    let levels = match mips {
+        MipChain::None if compression.is_none() && !formats::is_container(fname) => {
+            return write_dynamic_image(&image, fname);
+        }
+        MipChain::None => vec![image.into_rgba8()],
+        MipChain::Generate {
+            filter,
+            edges,
+            levels,
+            srgb,
+        } => mips::generate(image.into_rgba8(), filter, edges, levels, srgb),
+        MipChain::Levels(rest) => std::iter::once(image.into_rgba8()).chain(rest).collect(),
+    };
+    mips::write_chain(&levels, fname, compression, color_space)
+}
+
+#[derive(Debug)]
+pub struct EncodeJob {
+    pub tag: usize,
+    pub image: DynamicImage,
+    pub fname: String,
+    pub mips: MipChain,
+    pub compression: Option<Compression>,
+    pub color_space: ColorSpace,
+    pub volume: Option<Volume>,
+    pub cubemap: Option<CubemapLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encoded {
+    pub tag: usize,
+    pub fname: String,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+pub struct Encoder {
+    jobs: Option<Sender<EncodeJob>>,
+    results: Receiver<Result<Encoded>>,
+    workers: Vec<JoinHandle<()>>,
+    pending: usize,
+}
+
+impl Encoder {
+    pub fn new(workers: usize) -> Self {
+        let (jobs, queue) = channel::<EncodeJob>();
+        let (results_sender, results) = channel();
+        let queue = Arc::new(Mutex::new(queue));
+
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let queue = queue.clone();
+                let results = results_sender.clone();
+                thread::spawn(move || worker(queue, results))
+            })
+            .collect();
+
+        Self {
+            jobs: Some(jobs),
+            results,
+            workers,
+            pending: 0,
+        }
+    }
+
+    pub fn with_available_parallelism() -> Self {
+        let workers = thread::available_parallelism().map_or(1, |it| it.get());
+        Self::new(workers.min(MAX_WORKERS))
+    }
+
+    pub fn submit(&mut self, job: EncodeJob) -> Result<()> {
+        self.jobs
+            .as_ref()
+            .ok_or_else(|| anyhow!("Encoder is shut down"))?
+            .send(job)
+            .map_err(|_| anyhow!("Encoder workers are gone"))?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Returns the first error if any image failed.
+    pub fn wait(&mut self) -> Result<Vec<Encoded>> {
+        let mut res = vec![];
+        let mut error = None;
+
+        while self.pending > 0 {
+            let result = self
+                .results
+                .recv()
+                .map_err(|_| anyhow!("Encoder workers are gone"))?;
+            self.pending -= 1;
+
+            match result {
+                Ok(encoded) => res.push(encoded),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(res),
+        }
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message,
+        (_, Some(message)) => message,
+        _ => "unknown error",
+    }
+}
+
+fn worker(queue: Arc<Mutex<Receiver<EncodeJob>>>, results: Sender<Result<Encoded>>) {
+    loop {
+        let job = match queue.lock() {
+            Ok(queue) => queue.recv(),
+            Err(_) => return,
+        };
+        let Ok(job) = job else {
+            return;
+        };
+
+        let start = Instant::now();
+        let fname = job.fname.clone();
+        let written = panic::catch_unwind(AssertUnwindSafe(|| {
+            write(
+                job.image,
+                &fname,
+                job.mips,
+                job.compression,
+                job.color_space,
+                job.volume,
+                job.cubemap,
+            )
+        }))
+        .unwrap_or_else(|panic| Err(anyhow!("Encoding panicked: {}", panic_message(&*panic))));
+        let result = written
+            .with_context(|| format!("Failed to write output: {}", job.fname))
+            .map(|_| Encoded {
+                tag: job.tag,
+                fname: job.fname,
+                elapsed: start.elapsed(),
+            });
+
+        if results.send(result).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    encoder::{EncodeJob, Encoder},
+    mips::MipChain,
+    pipeline::{ColorSpace, CubemapLayout},
+    test_util::TempDir,
+};
+
+#[test]
+fn test_encoder_writes_all_jobs() {
+    let dir = TempDir::new("encoder");
+    let mut encoder = Encoder::new(3);
+
+    let names: Vec<String> = (0..5).map(|i| dir.file(&format!("out{i}.png"))).collect();
+    for (tag, fname) in names.iter().enumerate() {
+        let image = RgbaImage::from_pixel(4, 2, Rgba([tag as u8, 0, 0, 255]));
+        encoder
+            .submit(EncodeJob {
+                tag,
+                image: image.into(),
+                fname: fname.clone(),
+                mips: MipChain::None,
+                compression: None,
+                color_space: ColorSpace::Data,
+                volume: None,
+                cubemap: None,
+            })
+            .unwrap();
+    }
+
+    let mut encoded = encoder.wait().unwrap();
+    encoded.sort_by_key(|it| it.tag);
+
+    assert_eq!(encoded.len(), 5);
+    for (tag, fname) in names.iter().enumerate() {
+        assert_eq!(encoded[tag].tag, tag);
+        let image = image::open(fname).unwrap().into_rgba8();
+        assert_eq!(image.get_pixel(1, 1), &Rgba([tag as u8, 0, 0, 255]));
+    }
+
+    assert!(encoder.wait().unwrap().is_empty());
+}
+
+#[test]
+fn test_encoder_reports_errors() {
+    let dir = TempDir::new("encoder");
+    let mut encoder = Encoder::new(2);
+
+    encoder
+        .submit(EncodeJob {
+            tag: 0,
+            image: RgbaImage::new(1, 1).into(),
+            fname: dir.file("missing/dir/out.png"),
+            mips: MipChain::None,
+            compression: None,
+            color_space: ColorSpace::Data,
+            volume: None,
+            cubemap: None,
+        })
+        .unwrap();
+    encoder
+        .submit(EncodeJob {
+            tag: 1,
+            image: RgbaImage::new(1, 1).into(),
+            fname: dir.file("ok.png"),
+            mips: MipChain::None,
+            compression: None,
+            color_space: ColorSpace::Data,
+            volume: None,
+            cubemap: None,
+        })
+        .unwrap();
+
+    let err = encoder.wait().unwrap_err();
+    assert!(err.to_string().contains("out.png"));
+
+    assert!(encoder.wait().unwrap().is_empty());
+}
+
+#[test]
+fn test_encoder_reports_panics() {
+    let dir = TempDir::new("encoder");
+    let mut encoder = Encoder::new(2);
+
+    // Too small for six faces, the equirect projection panics.
+    let fname = dir.file("sky.png");
+    encoder
+        .submit(EncodeJob {
+            tag: 0,
+            image: RgbaImage::new(1, 1).into(),
+            fname: fname.clone(),
+            mips: MipChain::None,
+            compression: None,
+            color_space: ColorSpace::Data,
+            volume: None,
+            cubemap: Some(CubemapLayout::Equirect),
+        })
+        .unwrap();
+
+    let err = encoder.wait().unwrap_err();
+    assert!(format!("{err:#}").contains("sky.png"));
+    assert!(format!("{err:#}").contains("panicked"));
+
+    // The worker keeps running after the panic.
+    encoder
+        .submit(EncodeJob {
+            tag: 1,
+            image: RgbaImage::new(1, 1).into(),
+            fname: dir.file("ok.png"),
+            mips: MipChain::None,
+            compression: None,
+            color_space: ColorSpace::Data,
+            volume: None,
+            cubemap: None,
+        })
+        .unwrap();
+    assert_eq!(encoder.wait().unwrap().len(), 1);
+}
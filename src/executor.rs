@@ -1,84 +1,728 @@
-use std::time::SystemTime;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, SystemTime},
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
+use image::{DynamicImage, RgbaImage};
 
 use crate::{
+    animation, atlas,
+    color_space::{decode_srgb32f, encode_srgb32f, hsv_to_rgb},
     context::Ctx,
+    cubemap,
+    encoder::{EncodeJob, Encoded},
     expirable::Expirable,
-    pipeline::{Input, Pipeline, Preview, Profiling, Source, Stage},
+    mesh::Mesh,
+    mips::{self, Downsample, MipChain},
+    ops,
+    pipeline::{
+        suffixed_name, texture_units, AnimatedOutput, Atlas, ColorSpace, Compression,
+        CubemapLayout, Edges, Input, MaterialPreview, MaterialShape, MeshPreview, MipFilter, Mips,
+        Op, Pipeline, Preview, Profiling, Source, Stage, StageError, StageKind, Sweep, Volume,
+    },
+    preview::{wrap, OrbitCamera, Picker, Rect, Row, Shading, CHAR_WIDTH, LINE_HEIGHT, ROW_HEIGHT},
+    profiler::{ProfileReport, ProfileSettings, StageProfile},
+    readback::PendingReadback,
     shader::ShaderProgram,
-    texture::Texture,
+    sweep::{cell_image, contact_sheet, label, variant_suffix},
+    texture::{max_difference, write_image, Texture, TextureFormat, TextureShape},
+    trace,
 };
 
-pub fn execute_pipeline<F: FnOnce(usize)>(
-    ctx: &mut Ctx,
-    pipe: &mut Expirable<Pipeline>,
-    force: bool,
-    preview_callback: F,
-) -> Result<()> {
+pub fn execute_pipeline(ctx: &mut Ctx, pipe: &mut Expirable<Pipeline>, force: bool) -> Result<()> {
+    let span = trace::span("pipeline", "execute_pipeline");
+    let res = run_pipeline(ctx, pipe, force);
+    drop(span);
+
+    // Only full executions are traced, partial ones happen every frame.
+    match (&res, pipe.data().trace_file()) {
+        (Ok(true), Some(fname)) => trace::write(&ctx.project_path.path(fname))?,
+        _ => trace::discard(),
+    }
+
+    res.map(|_| ())
+}
+
+/// Executes the pipeline again once something changed since the failure.
+pub fn retry_pipeline(ctx: &mut Ctx, pipe: &mut Expirable<Pipeline>) -> Result<bool> {
+    if !ctx.refresh_pipeline(pipe)? && ctx.dirty_variables.is_empty() {
+        return Ok(false);
+    }
+    execute_pipeline(ctx, pipe, true)?;
+    Ok(true)
+}
+
+/// Returns whether the whole pipeline was executed.
+fn run_pipeline(ctx: &mut Ctx, pipe: &mut Expirable<Pipeline>, force: bool) -> Result<bool> {
     if !ctx.refresh_pipeline(pipe)? && !force {
-        let mut e = Executor { ctx };
+        let changed = std::mem::take(&mut ctx.dirty_variables);
+        let mut e = Executor::new(ctx);
 
-        e.draw_previews(pipe.data());
+        let affected = pipe.data().affected_stages(&changed);
+        let mut outputs = HashSet::new();
+        for frame in pipe.data().frames() {
+            e.set_frame(pipe.data(), frame);
+            for &idx in affected.iter() {
+                let stage = &pipe.data().pipeline[idx];
+                e.execute_stage(idx, stage, false)
+                    .with_context(|| StageError::new(idx, stage))?;
+                outputs.extend(stage.output_names());
+            }
+        }
+        e.finish_outputs()?;
+        e.write_animations(pipe.data())?;
+        for atlas in pipe.data().affected_atlases(&outputs) {
+            e.write_atlas(pipe.data(), atlas)?;
+        }
 
-        return Ok(());
+        e.draw_previews(pipe.data())?;
+
+        return Ok(false);
     }
+    ctx.dirty_variables.clear();
 
     if ctx.logs_enabled {
         let datetime: DateTime<Utc> = SystemTime::now().into();
         println!("Reexecuting pipeline {}", datetime.format("%Y.%m.%d/ %T"));
     }
 
-    let mut e = Executor { ctx };
+    let mut e = Executor::new(ctx);
+    let settings = pipe.data().profile.as_ref();
+    let mut report = ProfileReport::default();
 
-    for stage in pipe.data().pipeline.iter() {
-        e.execute_stage(stage)?;
+    // The times of the later frames of an animation are added to the first one.
+    for frame in pipe.data().frames() {
+        e.set_frame(pipe.data(), frame);
+        for (idx, stage) in pipe.data().pipeline.iter().enumerate() {
+            let profile = e
+                .execute_stage(idx, stage, settings.is_some_and(|it| it.report_enabled()))
+                .with_context(|| StageError::new(idx, stage))?;
+            match report.stages.get_mut(idx) {
+                Some(first) => {
+                    first.gpu += profile.gpu;
+                    first.cpu += profile.cpu;
+                }
+                None => report.stages.push(profile),
+            }
+        }
     }
 
-    preview_callback(pipe.data().number_of_previews());
-    e.draw_previews(pipe.data());
+    if settings.is_some_and(|it| it.report_enabled()) {
+        for (idx, gpu) in e.wait_gpu_timers(pipe.data()) {
+            report.stages[idx].gpu += gpu.as_secs_f64();
+        }
+    }
+    for encoded in e.finish_outputs()? {
+        report.stages[encoded.tag].encode += encoded.elapsed.as_secs_f64();
+    }
+    for (idx, elapsed) in e.readback_times.drain(..) {
+        report.stages[idx].readback += elapsed.as_secs_f64();
+    }
+    e.write_animations(pipe.data())?;
+    for atlas in pipe.data().atlases.iter() {
+        e.write_atlas(pipe.data(), atlas)?;
+    }
+
+    if let Some(settings) = settings.filter(|it| it.report_enabled()) {
+        e.write_profile(settings, &report)?;
+    }
+
+    e.draw_previews(pipe.data())?;
+
+    Ok(true)
+}
+
+pub fn run_sweep(ctx: &mut Ctx, pipe: &Pipeline, sweep: &Sweep) -> Result<()> {
+    sweep.check(&ctx.variables)?;
+    let source = sweep.source.clone().or_else(|| {
+        pipe.previews()
+            .next()
+            .or(pipe.pipeline.last())
+            .map(|it| it.output.name.clone())
+    });
+
+    let variants = sweep.variants();
+    let mut cells = vec![];
+    for (idx, values) in variants.iter().enumerate() {
+        if ctx.logs_enabled {
+            println!("Rendering variant {}/{}", idx + 1, variants.len());
+        }
+        for (name, value) in values {
+            ctx.variables.insert(name.clone(), value.clone());
+        }
+
+        let mut e = Executor::new(ctx);
+        e.output_suffix = Some(variant_suffix(idx));
+        for frame in pipe.frames() {
+            e.set_frame(pipe, frame);
+            for (stage_idx, stage) in pipe.pipeline.iter().enumerate() {
+                e.execute_stage(stage_idx, stage, false)
+                    .with_context(|| StageError::new(stage_idx, stage))?;
+            }
+        }
+        e.finish_outputs()?;
+        e.write_animations(pipe)?;
+        for atlas in pipe.atlases.iter() {
+            e.write_atlas(pipe, atlas)?;
+        }
+        drop(e);
+
+        if let Some(source) = &source {
+            let texture = ctx
+                .textures
+                .get(source)
+                .ok_or_else(|| anyhow!("Sweep source `{source}` is not a stage output"))?;
+            let image = cell_image(&texture.data().read_back(), sweep.cell_size);
+            cells.push((image, label(idx, values, sweep.cell_size)));
+        }
+    }
 
+    if let Some(sheet) = sweep.sheet.as_ref().filter(|_| !cells.is_empty()) {
+        let fname = ctx.project_path.path(sheet);
+        write_image(&contact_sheet(&cells, sweep.columns), &fname)
+            .with_context(|| format!("Failed to write contact sheet: {fname}"))?;
+        if ctx.logs_enabled {
+            println!(
+                "Contact sheet {sheet} written with {} variants",
+                cells.len()
+            );
+        }
+    }
     Ok(())
 }
 
+/// Keeps the window alive while the pipeline fails.
+pub fn draw_previews(ctx: &mut Ctx, pipe: &Pipeline) -> Result<()> {
+    let mut e = Executor::new(ctx);
+    e.draw_previews(pipe)
+}
+
+const ITERATION_UNIFORM: &str = "tw_iteration";
+const PREVIOUS_UNIFORM: &str = "tw_previous";
+const OUTPUT_UNIFORM: &str = "tw_output";
+const OUTPUT_IMAGE_UNIT: u32 = 0;
+const MIP_SOURCE_UNIFORM: &str = "tw_source";
+const MIP_LEVEL_UNIFORM: &str = "tw_level";
+const VARIATION_UNIFORM: &str = "tw_variation";
+const TIME_UNIFORM: &str = "tw_time";
+const FRAME_UNIFORM: &str = "tw_frame";
+const SLICE_UNIFORM: &str = "tw_slice";
+const Z_UNIFORM: &str = "tw_z";
+const FACE_UNIFORM: &str = "tw_face";
+const FACE_DIR_UNIFORM: &str = "tw_face_dir";
+const FACE_U_UNIFORM: &str = "tw_face_u";
+const FACE_V_UNIFORM: &str = "tw_face_v";
+
+const LABEL_COLOR: [u8; 4] = [220, 220, 220, 255];
+const LABEL_BACKGROUND: [u8; 4] = [0, 0, 0, 0];
+const FAILED_BACKGROUND: [u8; 4] = [180, 20, 20, 255];
+const ERROR_COLOR: [u8; 4] = [255, 120, 120, 255];
+const ERROR_BACKGROUND: [u8; 4] = [0, 0, 0, 210];
+const STALE_MARK_WIDTH: u32 = 3;
+const STALE_MARK_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+const PANEL_HEADER: &str = "Parameters (S: save)";
+const PANEL_BACKGROUND: [f32; 4] = [0.12, 0.12, 0.12, 1.0];
+const SLIDER_BACKGROUND: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+const SLIDER_COLOR: [f32; 4] = [0.3, 0.5, 0.8, 1.0];
+const PICKER_CELLS: u32 = 24;
+const PICKER_MARKER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+fn fill_rect(rect: &Rect, color: [f32; 4], window_height: u32) {
+    unsafe {
+        gl::Enable(gl::SCISSOR_TEST);
+        rect.set_scissor(window_height);
+        gl::ClearColor(color[0], color[1], color[2], color[3]);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+        gl::Disable(gl::SCISSOR_TEST);
+    }
+}
+
+fn draw_picker(picker: &Picker, window_height: u32) {
+    let [hue, ..] = picker.hsv;
+    let cell = |rect: &Rect, column: u32, columns: u32, row: u32, rows: u32| {
+        let x = |i: u32| rect.x + (rect.w * i / columns) as i32;
+        let y = |i: u32| rect.y + (rect.h * i / rows) as i32;
+        Rect {
+            x: x(column),
+            y: y(row),
+            w: (x(column + 1) - x(column)) as u32,
+            h: (y(row + 1) - y(row)) as u32,
+        }
+    };
+
+    let max = (PICKER_CELLS - 1) as f32;
+    for row in 0..PICKER_CELLS {
+        for column in 0..PICKER_CELLS {
+            let rect = cell(&picker.square, column, PICKER_CELLS, row, PICKER_CELLS);
+            let [r, g, b] = hsv_to_rgb([hue, column as f32 / max, 1.0 - row as f32 / max]);
+            fill_rect(&rect, [r, g, b, 1.0], window_height);
+        }
+
+        let rect = cell(&picker.hue_bar, 0, 1, row, PICKER_CELLS);
+        let [r, g, b] = hsv_to_rgb([row as f32 / max, 1.0, 1.0]);
+        fill_rect(&rect, [r, g, b, 1.0], window_height);
+    }
+
+    let (x, y) = picker.square_marker();
+    let mark = Rect {
+        x: x - 1,
+        y: y - 1,
+        w: 3,
+        h: 3,
+    };
+    fill_rect(&mark, PICKER_MARKER_COLOR, window_height);
+    let mark = Rect {
+        y: picker.hue_marker() - 1,
+        h: 2,
+        ..picker.hue_bar
+    };
+    fill_rect(&mark, PICKER_MARKER_COLOR, window_height);
+}
+
+fn gpu_time(measured: &[(u64, Duration)], filter: impl Fn(u64) -> bool) -> Duration {
+    measured
+        .iter()
+        .filter(|(execution, _)| filter(*execution))
+        .map(|(_, elapsed)| *elapsed)
+        .sum()
+}
+
+fn draw_overlay(shader: &ShaderProgram, mesh: &Mesh, texture: &Texture, rect: &Rect, h: u32) {
+    shader.bind();
+    texture.activate_bind(0);
+    rect.set_viewport(h);
+
+    unsafe {
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    }
+    mesh.draw();
+    unsafe {
+        gl::Disable(gl::BLEND);
+        gl::Enable(gl::DEPTH_TEST);
+    }
+}
+
+struct PendingOutput {
+    stage_idx: usize,
+    fname: String,
+    readback: PendingReadback,
+    issued: Duration,
+    mips: MipChain,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+    volume: Option<Volume>,
+    cubemap: Option<CubemapLayout>,
+}
+
 struct Executor<'a> {
     ctx: &'a mut Ctx,
+    outputs: Vec<PendingOutput>,
+    readback_times: Vec<(usize, Duration)>,
+    frame: u32,
+    time: f32,
+    first_frame: bool,
+    animations: HashMap<String, (AnimatedOutput, Vec<RgbaImage>)>,
+    /// Tells the variants of a sweep apart.
+    output_suffix: Option<String>,
+    /// GPU times of earlier executions are not reported.
+    execution: u64,
+}
+
+impl<'a> Drop for Executor<'a> {
+    fn drop(&mut self) {
+        // Outputs submitted before a failure are still written.
+        if let Err(e) = self.ctx.encoder.wait() {
+            println!("Error: {e:?}");
+        }
+    }
 }
 
 impl<'a> Executor<'a> {
-    fn draw_previews(&mut self, pipe: &Pipeline) {
-        let mut preview = 0;
+    fn new(ctx: &'a mut Ctx) -> Self {
+        ctx.executions += 1;
+        let execution = ctx.executions;
+        Self {
+            ctx,
+            outputs: vec![],
+            readback_times: vec![],
+            frame: 0,
+            time: 0.0,
+            first_frame: true,
+            animations: HashMap::new(),
+            output_suffix: None,
+            execution,
+        }
+    }
 
-        for stage in pipe.pipeline.iter() {
-            match stage.output.preview {
+    fn output_file(&self, name: &str) -> String {
+        match &self.output_suffix {
+            Some(suffix) => self.ctx.project_path.path(&suffixed_name(name, suffix)),
+            None => self.ctx.project_path.path(name),
+        }
+    }
+
+    fn set_frame(&mut self, pipe: &Pipeline, frame: u32) {
+        self.frame = frame;
+        self.time = pipe.time(frame);
+        self.first_frame = frame == pipe.frames().start;
+    }
+
+    fn write_animations(&mut self, pipe: &Pipeline) -> Result<()> {
+        for (fname, (animation, frames)) in self.animations.drain() {
+            let _span = trace::span("save", "write_animation").arg("file", &fname);
+            animation::write(animation, &frames, pipe.fps(), &fname)
+                .with_context(|| format!("Failed to write animation: {fname}"))?;
+        }
+        Ok(())
+    }
+
+    /// Hands the finished readbacks to the encoder, all of them with `wait`.
+    fn flush_outputs(&mut self, wait: bool) -> Result<()> {
+        let mut idx = 0;
+        while idx < self.outputs.len() {
+            if !wait && !self.outputs[idx].readback.is_ready() {
+                idx += 1;
+                continue;
+            }
+
+            let output = self.outputs.remove(idx);
+            let start = SystemTime::now();
+            let image = output
+                .readback
+                .wait()
+                .with_context(|| format!("Failed to read back output: {}", output.fname))?;
+            self.readback_times
+                .push((output.stage_idx, output.issued + start.elapsed()?));
+
+            self.ctx.encoder.submit(EncodeJob {
+                tag: output.stage_idx,
+                image,
+                fname: output.fname,
+                mips: output.mips,
+                compression: output.compression,
+                color_space: output.color_space,
+                volume: output.volume,
+                cubemap: output.cubemap,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn finish_outputs(&mut self) -> Result<Vec<Encoded>> {
+        self.flush_outputs(true)?;
+        self.ctx.encoder.wait()
+    }
+    fn draw_previews(&mut self, pipe: &Pipeline) -> Result<()> {
+        let _span = trace::span("preview", "draw_previews");
+        // A failed stage may leave its canvas bound.
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        let aspects: Vec<f32> = pipe
+            .previews()
+            .map(|stage| stage.output.width as f32 / stage.output.height as f32)
+            .collect();
+        self.ctx.view.panel_width = self.ctx.panel.width();
+        self.ctx.view.update_layout(&aspects);
+
+        // Stepping past the last layer of the deepest volume or cubemap has no effect.
+        let shapes = pipe
+            .previews()
+            .map(|stage| TextureShape::of_output(&stage.output));
+        if let Some(layers) = shapes.filter_map(|it| it.layers()).max() {
+            self.ctx.view.slice = self.ctx.view.slice.min(layers.saturating_sub(1));
+        }
+
+        let inspect_at = self.ctx.view.take_inspect_request();
+        let previews = pipe
+            .pipeline
+            .iter()
+            .enumerate()
+            .filter(|(_, stage)| !matches!(stage.output.preview, Preview::Disabled));
+
+        for (preview, (idx, stage)) in previews.enumerate() {
+            let Some(cell) = self.ctx.view.cell(preview) else {
+                continue;
+            };
+            let rect = cell.image;
+
+            let (label, background) = match &self.ctx.view.error {
+                None => (stage.output.name.clone(), LABEL_BACKGROUND),
+                Some(error) if error.stage == Some(idx) => {
+                    (format!("{} (failed)", stage.output.name), FAILED_BACKGROUND)
+                }
+                Some(_) => (format!("{} (stale)", stage.output.name), LABEL_BACKGROUND),
+            };
+            let label = match TextureShape::of_output(&stage.output) {
+                TextureShape::Flat => label,
+                TextureShape::Volume(slices) => {
+                    let slice = self.ctx.view.slice_index(slices);
+                    format!("{label} [slice {}/{slices}]", slice + 1)
+                }
+                TextureShape::Cube => {
+                    let face = &cubemap::FACES[self.ctx.view.slice_index(6) as usize];
+                    format!("{label} [face {}]", face.label)
+                }
+            };
+            self.draw_label(&label, &cell.label, background)?;
+
+            // A minimized window or a cell too small for its label has no room left.
+            if !self.can_draw_preview(stage) || rect.is_empty() {
+                continue;
+            }
+
+            let texture = self.ctx.textures[&stage.output.name].data();
+            match &stage.output.preview {
                 Preview::Disabled => (),
-                Preview::Simple => {
-                    let texture = &self.ctx.textures[&stage.output.name];
-                    self.draw_simple_preview(texture.data(), preview);
-                    preview += 1;
+                Preview::Material(material) => {
+                    self.draw_material_preview(&stage.output.name, material, &rect)?;
+                }
+                Preview::Mesh(mesh) => {
+                    self.draw_mesh_preview(&stage.output.name, mesh, &rect)?;
+                }
+                other => {
+                    let mut shading = Shading::of(other).unwrap_or(Shading::Color);
+                    if let Some(channel) = self.ctx.view.channel {
+                        shading = Shading::Channel(channel);
+                    }
+                    // Volumes and cubemaps show a single slice or face, stepped through
+                    // with the bracket keys.
+                    let slice = match texture.layers() {
+                        Some(layers) => {
+                            let (w, h) = (texture.width(), texture.height());
+                            let target = self.ctx.pool.acquire(w, h, texture.format())?;
+                            texture.copy_layer(self.ctx.view.slice_index(layers), &target);
+                            Some(target)
+                        }
+                        None => None,
+                    };
+                    let shown = slice.as_ref().unwrap_or(texture);
+                    let drawn = self.draw_simple_preview(shown, &rect, shading);
+
+                    if let Some((x, y)) = inspect_at.filter(|(x, y)| rect.contains(*x, *y)) {
+                        self.inspect_pixel(&stage.output.name, shown, &rect, x, y);
+                    }
+                    if let Some(slice) = slice {
+                        self.ctx.pool.release(slice);
+                    }
+                    drawn?;
                 }
             }
+
+            if self.ctx.view.error.is_some() {
+                self.draw_stale_mark(&rect);
+            }
         }
+
+        self.draw_panel(pipe)?;
+        self.draw_error()
     }
 
-    fn execute_stage(&mut self, stage: &Stage) -> Result<()> {
-        let texture = Texture::from_size(stage.output.width, stage.output.height)?;
-        texture.bind_as_canvas();
+    fn draw_panel(&mut self, pipe: &Pipeline) -> Result<()> {
+        let panel = &mut self.ctx.panel;
+        if !panel.visible {
+            return Ok(());
+        }
 
-        let shader = &self.ctx.shaders[&stage.shader];
-        shader.data().bind();
+        let window = self.ctx.view.window_size;
+        panel.update_layout(
+            window,
+            &self.ctx.variables,
+            &pipe.parameters,
+            &pipe.imported_variables,
+        );
+        fill_rect(&panel.area, PANEL_BACKGROUND, window.1);
 
-        let mut idx = 0;
-        for input in stage.inputs.iter() {
-            self.handle_input(input, &mut idx, shader.data())?;
+        let header = Rect {
+            h: ROW_HEIGHT,
+            ..panel.area
+        };
+        let title = match panel.hidden {
+            (0, 0) => PANEL_HEADER.to_string(),
+            (above, below) => format!("{PANEL_HEADER} [{above} up, {below} down]"),
+        };
+        let mut labels = vec![(title, header)];
+
+        for row in self.ctx.panel.rows.iter() {
+            match row {
+                Row::Title {
+                    name,
+                    label,
+                    swatch,
+                    saved,
+                } => {
+                    let text = match saved {
+                        true => name.clone(),
+                        false => format!("{name} (not saved)"),
+                    };
+                    labels.push((text, *label));
+                    if let Some(([r, g, b], rect)) = swatch {
+                        fill_rect(rect, [*r, *g, *b, 1.0], window.1);
+                    }
+                }
+                Row::Picker(picker) => draw_picker(picker, window.1),
+                Row::Slider(slider) => {
+                    labels.push((format!("  {}", slider.text), slider.label));
+                    fill_rect(&slider.bar, SLIDER_BACKGROUND, window.1);
+                    let filled = Rect {
+                        w: (slider.bar.w as f32 * slider.fraction()) as u32,
+                        ..slider.bar
+                    };
+                    fill_rect(&filled, SLIDER_COLOR, window.1);
+                }
+            }
+        }
+
+        for (text, rect) in labels {
+            self.draw_label(&text, &rect, LABEL_BACKGROUND)?;
+        }
+
+        Ok(())
+    }
+
+    /// A failed pipeline may not have produced the resources of the preview.
+    fn can_draw_preview(&self, stage: &Stage) -> bool {
+        let textures = &self.ctx.textures;
+        let name = &stage.output.name;
+
+        textures.contains_key(name)
+            && match &stage.output.preview {
+                Preview::Material(material) => {
+                    material.textures().all(|it| textures.contains_key(it))
+                }
+                Preview::Mesh(mesh) => {
+                    mesh.textures().all(|it| textures.contains_key(it))
+                        && self.ctx.meshes.contains_key(&mesh.mesh.key())
+                }
+                _ => true,
+            }
+    }
+
+    fn draw_stale_mark(&self, rect: &Rect) {
+        let mark = Rect {
+            h: STALE_MARK_WIDTH.min(rect.h),
+            ..*rect
+        };
+        fill_rect(&mark, STALE_MARK_COLOR, self.ctx.view.window_size.1);
+    }
+
+    fn draw_error(&mut self) -> Result<()> {
+        let Some(error) = &self.ctx.view.error else {
+            return Ok(());
+        };
+
+        let (w, h) = self.ctx.view.window_size;
+        let columns = (w / CHAR_WIDTH) as usize;
+        let rows = (h / LINE_HEIGHT) as usize;
+        let text = wrap(&error.message, columns, rows);
+
+        let texture = self.ctx.text.get(&text, ERROR_COLOR, ERROR_BACKGROUND)?;
+        let rect = Rect {
+            x: 0,
+            y: 0,
+            w: texture.width(),
+            h: texture.height(),
+        };
+
+        let (shader, mesh) = (&self.ctx.default_shader, &self.ctx.default_mesh);
+        draw_overlay(shader, mesh, texture, &rect, h);
+        Ok(())
+    }
+
+    fn draw_label(&mut self, text: &str, rect: &Rect, background: [u8; 4]) -> Result<()> {
+        let max_chars = (rect.w / CHAR_WIDTH) as usize;
+        let text: String = match text.chars().count() > max_chars {
+            true => {
+                let skip = text.chars().count() + 1 - max_chars;
+                format!("~{}", text.chars().skip(skip).collect::<String>())
+            }
+            false => text.into(),
+        };
+
+        let texture = self.ctx.text.get(&text, LABEL_COLOR, background)?;
+        let label = Rect {
+            x: rect.x,
+            y: rect.y + (rect.h as i32 - texture.height() as i32) / 2,
+            w: texture.width(),
+            h: texture.height(),
+        };
+
+        let window_height = self.ctx.view.window_size.1;
+        let (shader, mesh) = (&self.ctx.default_shader, &self.ctx.default_mesh);
+        draw_overlay(shader, mesh, texture, &label, window_height);
+        Ok(())
+    }
+
+    fn wait_gpu_timers(&mut self, pipe: &Pipeline) -> Vec<(usize, Duration)> {
+        let timers = &mut self.ctx.gpu_timers;
+        let execution = self.execution;
+        pipe.pipeline
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, stage)| {
+                let measured = timers.get_mut(&stage.output.name)?.wait();
+                Some((idx, gpu_time(&measured, |it| it == execution)))
+            })
+            .collect()
+    }
+
+    fn execute_stage(
+        &mut self,
+        stage_idx: usize,
+        stage: &Stage,
+        gpu_timing: bool,
+    ) -> Result<StageProfile> {
+        let _span = trace::span("stage", stage.name()).arg("idx", stage_idx);
+        if let Some(op) = &stage.op {
+            return self.execute_op(stage_idx, stage, op);
         }
 
+        let iterations = stage.iterations.as_ref().map_or(1, |it| it.max()).max(1);
+        let variations = stage.variations();
+
+        let name = &stage.output.name;
+        let mut timer = match (&stage.profiling, gpu_timing) {
+            (Profiling::Gpu, _) | (_, true) => {
+                Some(self.ctx.gpu_timers.remove(name).unwrap_or_default())
+            }
+            _ => None,
+        };
         let start = SystemTime::now();
 
-        self.ctx.reversed_mesh.draw();
+        let draw_span = trace::span("draw", "draw")
+            .arg("iterations", iterations)
+            .arg("variations", variations);
+        if let Some(timer) = &mut timer {
+            timer.begin(self.execution);
+        }
+        let mut textures = vec![];
+        for variation in 0..variations {
+            textures.push(self.draw_variation(stage, variation)?);
+        }
+        if let Some(timer) = &mut timer {
+            timer.end();
+        }
+        drop(draw_span);
 
         let elapsed = start.elapsed()?;
+        // Only the measurements finished by now are read, the rest by
+        // later stages or `wait_gpu_timers`.
+        let measured = match timer {
+            Some(mut timer) => {
+                let measured = timer.take_finished();
+                self.ctx.gpu_timers.insert(name.clone(), timer);
+                measured
+            }
+            None => vec![],
+        };
+        let gpu = gpu_time(&measured, |it| it == self.execution);
+        let earlier = gpu_time(&measured, |it| it != self.execution);
 
         match stage.profiling {
             Profiling::Disabled => (),
@@ -89,36 +733,439 @@ impl<'a> Executor<'a> {
                     elapsed.as_secs_f64()
                 );
             }
+            Profiling::Gpu => {
+                if !gpu.is_zero() {
+                    println!(
+                        "Shader {} executed on GPU in {} sec",
+                        stage.shader,
+                        gpu.as_secs_f64()
+                    );
+                }
+                if !earlier.is_zero() {
+                    println!(
+                        "Shader {} executed on GPU in {} sec (earlier execution)",
+                        stage.shader,
+                        earlier.as_secs_f64()
+                    );
+                }
+            }
+        }
+
+        let mut profile = StageProfile {
+            idx: stage_idx,
+            shader: stage.shader.clone(),
+            output: stage.output.name.clone(),
+            gpu: gpu.as_secs_f64(),
+            cpu: elapsed.as_secs_f64(),
+            compile: self
+                .ctx
+                .compile_times
+                .get(&stage.shader)
+                .map_or(0.0, |it| it.as_secs_f64()),
+            ..Default::default()
+        };
+        for input in stage.inputs.iter() {
+            if let Input::File { name, .. } = input {
+                profile.upload += self
+                    .ctx
+                    .upload_times
+                    .get(name)
+                    .map_or(0.0, |it| it.as_secs_f64());
+            }
+        }
+
+        for (variation, texture) in textures.into_iter().enumerate() {
+            self.handle_output(stage_idx, stage, variation as u32, texture)?;
+        }
+        self.flush_outputs(false)?;
+
+        Ok(profile)
+    }
+
+    fn draw_variation(&mut self, stage: &Stage, variation: u32) -> Result<Texture> {
+        let (w, h) = (stage.output.width, stage.output.height);
+        let iterations = stage.iterations.as_ref().map_or(1, |it| it.max()).max(1);
+
+        let format = TextureFormat::new(stage.output.depth(), stage.output.color_space);
+        let shape = TextureShape::of_output(&stage.output);
+        let mut texture = self.ctx.pool.acquire_shaped(w, h, shape, format)?;
+        let mut previous = match iterations {
+            1 => None,
+            _ => Some(self.ctx.pool.acquire_shaped(w, h, shape, format)?),
+        };
+
+        let shader = &self.ctx.shaders[&stage.shader];
+        shader.data().bind();
+        if shader.data().has_uniform(VARIATION_UNIFORM) {
+            shader
+                .data()
+                .uniform_1i(VARIATION_UNIFORM, variation as i32)?;
+        }
+        if shader.data().has_uniform(TIME_UNIFORM) {
+            shader.data().uniform_1f(TIME_UNIFORM, self.time)?;
+        }
+        if shader.data().has_uniform(FRAME_UNIFORM) {
+            shader.data().uniform_1i(FRAME_UNIFORM, self.frame as i32)?;
+        }
+
+        let (units, free_unit) =
+            texture_units(&stage.inputs, |name| self.ctx.textures.contains_key(name));
+        for (input, unit) in stage.inputs.iter().zip(units) {
+            self.handle_input(input, unit, shader.data())?;
+        }
+        for binding in stage.buffers.iter() {
+            let buffer = &self.ctx.buffers[&binding.name];
+            if binding.clear {
+                buffer.clear();
+            }
+            buffer.bind(binding.binding);
         }
 
-        texture.unbind_as_canvas();
+        for iteration in 0..iterations {
+            self.bind_iteration(shader.data(), free_unit, iteration, previous.as_ref())?;
+            self.run_shader(stage.kind, shader.data(), &texture)?;
 
-        self.handle_output(stage, texture)?;
+            let Some(previous) = previous.as_mut() else {
+                break;
+            };
 
+            let converged = match stage
+                .iterations
+                .as_ref()
+                .and_then(|it| it.should_check(iteration))
+            {
+                Some(epsilon) => {
+                    max_difference(&texture.read_back_f32(), &previous.read_back_f32()) < epsilon
+                }
+                None => false,
+            };
+            if converged {
+                if self.ctx.logs_enabled {
+                    println!(
+                        "Shader {} converged after {} iterations",
+                        stage.shader,
+                        iteration + 1
+                    );
+                }
+                break;
+            }
+
+            if iteration + 1 < iterations {
+                std::mem::swap(&mut texture, previous);
+            }
+        }
+
+        if let Some(previous) = previous {
+            self.ctx.pool.release(previous);
+        }
+
+        Ok(texture)
+    }
+
+    fn write_atlas(&mut self, pipe: &Pipeline, atlas: &Atlas) -> Result<()> {
+        let _span = trace::span("atlas", "write_atlas").arg("file", &atlas.name);
+
+        let mut sources = vec![];
+        for name in pipe.atlas_sources(atlas) {
+            let texture = self
+                .ctx
+                .textures
+                .get(&name)
+                .ok_or_else(|| anyhow!("Unknown resource in atlas: {}", name))?
+                .data();
+            // Converted in float, atlases are quantized to 8 bits only afterwards.
+            let mut image = texture.read_back_f32();
+            match (
+                texture.format().is_srgb(),
+                atlas.color_space == ColorSpace::Srgb,
+            ) {
+                (true, false) => decode_srgb32f(&mut image),
+                (false, true) => encode_srgb32f(&mut image),
+                _ => (),
+            }
+            sources.push((name, DynamicImage::from(image).into_rgba8()));
+        }
+
+        let (packed, manifest) = atlas::pack(atlas, &sources)?;
+        let fname = self.output_file(&atlas.name);
+        atlas::write(&packed, &fname, atlas.compression, atlas.color_space)
+            .with_context(|| format!("Failed to write atlas: {fname}"))?;
+
+        let fname = self.output_file(&atlas.manifest_name());
+        std::fs::write(&fname, manifest.to_json()?)
+            .with_context(|| format!("Failed to write atlas manifest: {fname}"))?;
+
+        if self.ctx.logs_enabled {
+            println!(
+                "Atlas {} written with {} sources",
+                atlas.name,
+                sources.len()
+            );
+        }
         Ok(())
     }
 
-    fn draw_simple_preview(&self, texture: &Texture, idx: isize) {
-        self.ctx.default_shader.bind();
-        texture.activate_bind(0);
-        unsafe {
-            gl::Viewport(idx as i32 * 200, 0, 200, 200);
+    fn write_profile(&self, settings: &ProfileSettings, report: &ProfileReport) -> Result<()> {
+        if settings.table {
+            print!("{}", report.to_table());
+        }
+        if let Some(json) = &settings.json {
+            let fname = self.ctx.project_path.path(json);
+            std::fs::write(&fname, report.to_json()?)
+                .with_context(|| format!("Failed to write profile report: {fname}"))?;
         }
+        Ok(())
+    }
+
+    fn draw_simple_preview(&self, texture: &Texture, rect: &Rect, shading: Shading) -> Result<()> {
+        let view = &self.ctx.view;
+        let shader = &self.ctx.preview_shader;
+
+        shader.bind();
+        // Previews show the stored values, so sRGB outputs look like their files.
+        texture.activate_bind_raw(0);
+        shader.uniform_1f("zoom", view.zoom)?;
+        shader.uniform_2f("pan", view.pan)?;
+        shader.uniform_1i("tiles", view.tiles())?;
+        shader.uniform_1i("mode", shading.mode())?;
+        shader.uniform_1i("channel", shading.channel())?;
+
+        rect.set_viewport(view.window_size.1);
         self.ctx.default_mesh.draw();
+        Ok(())
     }
 
-    fn handle_input(&self, input: &Input, idx: &mut i32, shader: &ShaderProgram) -> Result<()> {
+    fn draw_material_preview(
+        &self,
+        name: &str,
+        material: &MaterialPreview,
+        rect: &Rect,
+    ) -> Result<()> {
+        let view = &self.ctx.view;
+        let shader = &self.ctx.material_shader;
+        let texture = |name: &Option<String>| name.as_ref().map(|it| self.ctx.textures[it].data());
+
+        shader.bind();
+        let albedo = texture(&material.albedo).unwrap_or(self.ctx.textures[name].data());
+        albedo.activate_bind(0);
+        shader.uniform_1i("albedo", 0)?;
+
+        let normal = texture(&material.normal);
+        if let Some(normal) = normal {
+            normal.activate_bind(1);
+            shader.uniform_1i("normal", 1)?;
+        }
+        shader.uniform_1i("has_normal", normal.is_some() as i32)?;
+
+        let roughness = texture(&material.roughness);
+        if let Some(roughness) = roughness {
+            roughness.activate_bind(2);
+            shader.uniform_1i("roughness", 2)?;
+        }
+        shader.uniform_1i("has_roughness", roughness.is_some() as i32)?;
+
+        let sphere = material.shape == MaterialShape::Sphere;
+        shader.uniform_1i("sphere", sphere as i32)?;
+        shader.uniform_1i("tiles", view.tiles())?;
+
+        rect.set_viewport(view.window_size.1);
+        self.ctx.default_mesh.draw();
+        Ok(())
+    }
+
+    fn draw_mesh_preview(&self, name: &str, preview: &MeshPreview, rect: &Rect) -> Result<()> {
+        let view = &self.ctx.view;
+        let shader = &self.ctx.mesh_shader;
+        let texture = |name: &Option<String>| name.as_ref().map(|it| self.ctx.textures[it].data());
+
+        shader.bind();
+        let albedo = texture(&preview.albedo).unwrap_or(self.ctx.textures[name].data());
+        albedo.activate_bind(0);
+        shader.uniform_1i("albedo", 0)?;
+
+        let optional = [
+            ("normal", &preview.normal),
+            ("roughness", &preview.roughness),
+            ("displacement", &preview.displacement),
+        ];
+        for (idx, (uniform, name)) in optional.into_iter().enumerate() {
+            let texture = texture(name);
+            if let Some(texture) = texture {
+                texture.activate_bind(idx as u32 + 1);
+                shader.uniform_1i(uniform, idx as i32 + 1)?;
+            }
+            shader.uniform_1i(&format!("has_{uniform}"), texture.is_some() as i32)?;
+        }
+        shader.uniform_1f("displacement_scale", preview.displacement_scale)?;
+        shader.uniform_1i("tiles", view.tiles())?;
+
+        let camera = OrbitCamera::from_view(view);
+        let aspect = rect.w as f32 / rect.h as f32;
+        shader.uniform_matrix_4f("model_view", &camera.model_view())?;
+        shader.uniform_matrix_4f("projection", &camera.projection(aspect))?;
+
+        rect.set_viewport(view.window_size.1);
+        self.ctx.meshes[&preview.mesh.key()].data().draw();
+        Ok(())
+    }
+
+    fn inspect_pixel(&self, name: &str, texture: &Texture, rect: &Rect, x: i32, y: i32) {
+        let Some([u, v]) = self.ctx.view.texel_uv(rect, x, y) else {
+            return;
+        };
+
+        let tx = ((u * texture.width() as f32) as u32).min(texture.width() - 1);
+        let ty = ((v * texture.height() as f32) as u32).min(texture.height() - 1);
+        let [r, g, b, a] = texture.read_pixel(tx, ty);
+
+        println!("`{name}` at ({tx}, {ty}): rgba({r}, {g}, {b}, {a})");
+    }
+
+    fn execute_op(&mut self, stage_idx: usize, stage: &Stage, op: &Op) -> Result<StageProfile> {
+        let size = (stage.output.width, stage.output.height);
+        let start = SystemTime::now();
+
+        let format = TextureFormat::new(stage.output.depth(), stage.output.color_space);
+
+        // Operations work on the stored values, sRGB inputs are converted
+        // only when the output is in a different color space.
+        let mut images = HashMap::new();
+        for name in op.inputs() {
+            let texture = self
+                .ctx
+                .textures
+                .get(name)
+                .ok_or_else(|| anyhow!("Unknown resource in input: {}", name))?
+                .data();
+            let mut image = texture.read_back_f32();
+            match (texture.format().is_srgb(), format.is_srgb()) {
+                (true, false) => decode_srgb32f(&mut image),
+                (false, true) => encode_srgb32f(&mut image),
+                _ => (),
+            }
+            images.insert(name.clone(), image);
+        }
+
+        let image = ops::apply(op, size, &images)?;
+        let texture = self.ctx.pool.acquire(size.0, size.1, format)?;
+        texture.upload(&image);
+
+        let elapsed = start.elapsed()?;
+        if let Profiling::Clock | Profiling::Gpu = stage.profiling {
+            println!(
+                "Operation {} executed in {} sec",
+                op.name(),
+                elapsed.as_secs_f64()
+            );
+        }
+
+        let profile = StageProfile {
+            idx: stage_idx,
+            shader: op.name().to_string(),
+            output: stage.output.name.clone(),
+            cpu: elapsed.as_secs_f64(),
+            upload: images
+                .keys()
+                .filter_map(|name| self.ctx.upload_times.get(name))
+                .map(|it| it.as_secs_f64())
+                .sum(),
+            ..Default::default()
+        };
+
+        self.handle_output(stage_idx, stage, 0, texture)?;
+        self.flush_outputs(false)?;
+
+        Ok(profile)
+    }
+
+    fn run_shader(&self, kind: StageKind, shader: &ShaderProgram, texture: &Texture) -> Result<()> {
+        match (kind, texture.shape()) {
+            (StageKind::Fragment, TextureShape::Flat) => {
+                texture.bind_as_canvas();
+                self.ctx.reversed_mesh.draw();
+                texture.unbind_as_canvas();
+            }
+            (StageKind::Fragment, TextureShape::Volume(slices)) => {
+                for slice in 0..slices {
+                    if shader.has_uniform(SLICE_UNIFORM) {
+                        shader.uniform_1i(SLICE_UNIFORM, slice as i32)?;
+                    }
+                    if shader.has_uniform(Z_UNIFORM) {
+                        shader.uniform_1f(Z_UNIFORM, (slice as f32 + 0.5) / slices as f32)?;
+                    }
+                    texture.bind_layer_as_canvas(slice);
+                    self.ctx.reversed_mesh.draw();
+                    texture.unbind_as_canvas();
+                }
+            }
+            (StageKind::Fragment, TextureShape::Cube) => {
+                for (idx, face) in cubemap::FACES.iter().enumerate() {
+                    if shader.has_uniform(FACE_UNIFORM) {
+                        shader.uniform_1i(FACE_UNIFORM, idx as i32)?;
+                    }
+                    if shader.has_uniform(FACE_DIR_UNIFORM) {
+                        shader.uniform_3f(FACE_DIR_UNIFORM, face.dir)?;
+                    }
+                    if shader.has_uniform(FACE_U_UNIFORM) {
+                        shader.uniform_3f(FACE_U_UNIFORM, face.u)?;
+                    }
+                    if shader.has_uniform(FACE_V_UNIFORM) {
+                        shader.uniform_3f(FACE_V_UNIFORM, face.v)?;
+                    }
+                    texture.bind_layer_as_canvas(idx as u32);
+                    self.ctx.reversed_mesh.draw();
+                    texture.unbind_as_canvas();
+                }
+            }
+            (StageKind::Compute, shape) => {
+                texture.bind_as_image(OUTPUT_IMAGE_UNIT);
+                if shader.has_uniform(OUTPUT_UNIFORM) {
+                    shader.uniform_1i(OUTPUT_UNIFORM, OUTPUT_IMAGE_UNIT as i32)?;
+                }
+
+                let [x, y, z] = shader.work_group_size();
+                unsafe {
+                    gl::DispatchCompute(
+                        texture.width().div_ceil(x),
+                        texture.height().div_ceil(y),
+                        shape.layers().map_or(1, |it| it.div_ceil(z)),
+                    );
+                    gl::MemoryBarrier(gl::ALL_BARRIER_BITS);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn bind_iteration(
+        &self,
+        shader: &ShaderProgram,
+        unit: u32,
+        iteration: u32,
+        previous: Option<&Texture>,
+    ) -> Result<()> {
+        if shader.has_uniform(ITERATION_UNIFORM) {
+            shader.uniform_1i(ITERATION_UNIFORM, iteration as i32)?;
+        }
+        if let Some(previous) = previous.filter(|_| shader.has_uniform(PREVIOUS_UNIFORM)) {
+            previous.activate_bind(unit);
+            shader.uniform_1i(PREVIOUS_UNIFORM, unit as i32)?;
+        }
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &Input, unit: Option<u32>, shader: &ShaderProgram) -> Result<()> {
         match input {
-            Input::File { name, uniform } => {
+            Input::File { name, uniform, .. } => {
                 let texture = self.ctx.textures.get(name).unwrap();
-                texture.data().activate_bind(*idx as u32);
-                shader.uniform_1i(uniform, *idx)?;
+                let unit = unit.unwrap_or_default();
+                texture.data().activate_bind(unit);
+                shader.uniform_1i(uniform, unit as i32)?;
             }
             Input::Memory { name, uniform } => {
-                let texture = self.ctx.textures.get(name);
-                if let Some(texture) = texture {
-                    texture.data().activate_bind(*idx as u32);
-                    shader.uniform_1i(uniform, *idx)?;
+                if let Some((texture, unit)) = self.ctx.textures.get(name).zip(unit) {
+                    texture.data().activate_bind(unit);
+                    shader.uniform_1i(uniform, unit as i32)?;
 
                     return Ok(());
                 }
@@ -134,18 +1181,133 @@ impl<'a> Executor<'a> {
         Ok(())
     }
 
-    fn handle_output(&mut self, stage: &Stage, texture: Texture) -> Result<()> {
-        match stage.output.dst {
-            Source::File => {
-                let fname = self.ctx.project_path.path(&stage.output.name);
-                texture.save_to_file(&fname)?;
+    /// Shader filtered levels are read back right away, the others left to the encoder.
+    fn mip_chain(&mut self, mips: &Mips, texture: &Texture) -> Result<MipChain> {
+        let filter = match &mips.filter {
+            MipFilter::Box => Downsample::Box,
+            MipFilter::Kaiser => Downsample::Kaiser,
+            MipFilter::Shader(name) => {
+                let levels = self.render_mips(name, mips, texture)?;
+                return Ok(MipChain::Levels(levels));
+            }
+        };
+        Ok(MipChain::Generate {
+            filter,
+            edges: mips.edges,
+            levels: mips.levels,
+            srgb: texture.format().is_srgb(),
+        })
+    }
+
+    fn render_mips(
+        &mut self,
+        name: &str,
+        mips: &Mips,
+        texture: &Texture,
+    ) -> Result<Vec<RgbaImage>> {
+        let size = (texture.width(), texture.height());
+        let count = mips::level_count(size, mips.levels);
+        let clamped = mips.edges == Edges::Clamp;
+
+        let mut levels = vec![];
+        let mut previous: Option<Texture> = None;
+        for level in 1..count {
+            let (w, h) = mips::level_size(size, level);
+            let target = self.ctx.pool.acquire(w, h, texture.format())?;
+            let source = previous.as_ref().unwrap_or(texture);
+
+            let shader = self
+                .ctx
+                .shaders
+                .get(name)
+                .ok_or_else(|| anyhow!("Mip shader `{name}` is not loaded"))?
+                .data();
+            shader.bind();
+            source.set_clamped(clamped);
+            source.activate_bind(0);
+            if shader.has_uniform(MIP_SOURCE_UNIFORM) {
+                shader.uniform_1i(MIP_SOURCE_UNIFORM, 0)?;
+            }
+            if shader.has_uniform(MIP_LEVEL_UNIFORM) {
+                shader.uniform_1i(MIP_LEVEL_UNIFORM, level as i32)?;
+            }
+
+            target.bind_as_canvas();
+            self.ctx.reversed_mesh.draw();
+            target.unbind_as_canvas();
+            source.set_clamped(false);
+
+            levels.push(target.read_back());
+            if let Some(previous) = previous.replace(target) {
+                self.ctx.pool.release(previous);
+            }
+        }
+        if let Some(previous) = previous {
+            self.ctx.pool.release(previous);
+        }
+
+        Ok(levels)
+    }
+
+    fn submit_output(
+        &mut self,
+        stage_idx: usize,
+        stage: &Stage,
+        name: &str,
+        texture: &Texture,
+    ) -> Result<()> {
+        let start = SystemTime::now();
+        let readback = texture.read_back_async();
+        let issued = start.elapsed()?;
+        let mips = match &stage.output.mips {
+            None => MipChain::None,
+            Some(mips) => self.mip_chain(mips, texture)?,
+        };
+        self.outputs.push(PendingOutput {
+            stage_idx,
+            fname: self.output_file(name),
+            readback,
+            issued,
+            mips,
+            compression: stage.output.compression,
+            color_space: stage.output.color_space,
+            volume: stage.output.volume,
+            cubemap: stage.output.cubemap,
+        });
+        Ok(())
+    }
+
+    fn handle_output(
+        &mut self,
+        stage_idx: usize,
+        stage: &Stage,
+        variation: u32,
+        texture: Texture,
+    ) -> Result<()> {
+        let name = stage.output.variation_name(variation);
+        match (&stage.output.dst, stage.output.animation) {
+            (Source::File, None) if self.first_frame => {
+                self.submit_output(stage_idx, stage, &name, &texture)?;
+            }
+            (Source::File, Some(AnimatedOutput::Sequence)) => {
+                let fname = animation::frame_file_name(&name, self.frame);
+                self.submit_output(stage_idx, stage, &fname, &texture)?;
             }
-            Source::Memory => (),
+            (Source::File, Some(animation)) => {
+                let fname = self.output_file(&name);
+                self.animations
+                    .entry(fname)
+                    .or_insert_with(|| (animation, vec![]))
+                    .1
+                    .push(texture.read_back());
+            }
+            _ => (),
         }
 
-        self.ctx
-            .textures
-            .insert(stage.output.name.clone(), Expirable::now(texture));
+        let old = self.ctx.textures.insert(name, Expirable::now(texture));
+        if let Some(old) = old {
+            self.ctx.pool.release(old.into_data());
+        }
 
         Ok(())
     }
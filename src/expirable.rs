@@ -29,4 +29,8 @@ impl<T> Expirable<T> {
     pub fn data(&self) -> &'_ T {
         &self.data
     }
+
+    pub fn into_data(self) -> T {
+        self.data
+    }
 }
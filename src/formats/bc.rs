@@ -0,0 +1,254 @@
+use image::RgbaImage;
+
+use crate::pipeline::Compression;
+
+type Block = [[u8; 4]; 16];
+
+/// Out of 64.
+const BC7_WEIGHTS: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+const BC7_MODE_6: u128 = 1 << 6;
+
+pub fn compressed_size((w, h): (u32, u32), compression: Compression) -> usize {
+    (w.div_ceil(4) * h.div_ceil(4)) as usize * compression.block_bytes()
+}
+
+/// Runs on the CPU, BC7 blocks are always encoded in mode 6.
+pub fn compress(image: &RgbaImage, compression: Compression) -> Vec<u8> {
+    let (w, h) = image.dimensions();
+    let mut res = Vec::with_capacity(compressed_size((w, h), compression));
+
+    for by in 0..h.div_ceil(4) {
+        for bx in 0..w.div_ceil(4) {
+            let block = read_block(image, bx * 4, by * 4);
+            match compression {
+                Compression::Bc1 => res.extend(encode_bc1(&block, true)),
+                Compression::Bc3 => {
+                    res.extend(encode_bc4(&block, 3));
+                    res.extend(encode_bc1(&block, false));
+                }
+                Compression::Bc4 => res.extend(encode_bc4(&block, 0)),
+                Compression::Bc5 => {
+                    res.extend(encode_bc4(&block, 0));
+                    res.extend(encode_bc4(&block, 1));
+                }
+                Compression::Bc7 => res.extend(encode_bc7(&block)),
+            }
+        }
+    }
+
+    res
+}
+
+fn read_block(image: &RgbaImage, x0: u32, y0: u32) -> Block {
+    let (w, h) = image.dimensions();
+    let mut block = [[0; 4]; 16];
+    for (i, pixel) in block.iter_mut().enumerate() {
+        let x = (x0 + i as u32 % 4).min(w - 1);
+        let y = (y0 + i as u32 / 4).min(h - 1);
+        *pixel = image.get_pixel(x, y).0;
+    }
+    block
+}
+
+/// Fits the line along the principal axis of the colors.
+fn endpoints<const N: usize>(colors: &[[f32; N]]) -> ([f32; N], [f32; N]) {
+    let n = colors.len() as f32;
+    let mut mean = [0.0; N];
+    for color in colors {
+        for c in 0..N {
+            mean[c] += color[c] / n;
+        }
+    }
+
+    let mut cov = [[0.0; N]; N];
+    for color in colors {
+        for i in 0..N {
+            for j in 0..N {
+                cov[i][j] += (color[i] - mean[i]) * (color[j] - mean[j]);
+            }
+        }
+    }
+
+    // Power iteration converges to the principal axis quickly enough for 16 pixels.
+    let mut axis = [1.0; N];
+    for _ in 0..8 {
+        let mut next = [0.0; N];
+        for i in 0..N {
+            for j in 0..N {
+                next[i] += cov[i][j] * axis[j];
+            }
+        }
+        let len = next.iter().map(|it| it * it).sum::<f32>().sqrt();
+        if len < 1e-6 {
+            return (mean, mean);
+        }
+        axis = next.map(|it| it / len);
+    }
+
+    let project = |color: &[f32; N]| (0..N).map(|c| (color[c] - mean[c]) * axis[c]).sum::<f32>();
+    let (min, max) = colors
+        .iter()
+        .map(project)
+        .fold((f32::MAX, f32::MIN), |(min, max), t| {
+            (min.min(t), max.max(t))
+        });
+
+    let point = |t: f32| {
+        let mut res = [0.0; N];
+        for c in 0..N {
+            res[c] = (mean[c] + axis[c] * t).clamp(0.0, 255.0);
+        }
+        res
+    };
+    (point(min), point(max))
+}
+
+fn nearest<const N: usize>(palette: &[[i32; N]], color: &[u8; 4]) -> usize {
+    let distance =
+        |entry: &[i32; N]| -> i32 { (0..N).map(|c| (entry[c] - color[c] as i32).pow(2)).sum() };
+    (0..palette.len())
+        .min_by_key(|&i| distance(&palette[i]))
+        .unwrap_or(0)
+}
+
+fn to_565(color: [f32; 3]) -> u16 {
+    let [r, g, b] = color.map(|it| it.round() as u16);
+    (((r * 31 + 127) / 255) << 11) | (((g * 63 + 127) / 255) << 5) | ((b * 31 + 127) / 255)
+}
+
+fn from_565(color: u16) -> [i32; 3] {
+    let (r, g, b) = (
+        (color >> 11) as i32 & 31,
+        (color >> 5) as i32 & 63,
+        color as i32 & 31,
+    );
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+/// With `alpha`, pixels below one half use the 3 color mode, which BC3 lacks.
+fn encode_bc1(block: &Block, alpha: bool) -> [u8; 8] {
+    let transparent = alpha && block.iter().any(|it| it[3] < 128);
+    let colors: Vec<[f32; 3]> = block
+        .iter()
+        .filter(|it| !transparent || it[3] >= 128)
+        .map(|it| [it[0] as f32, it[1] as f32, it[2] as f32])
+        .collect();
+    if colors.is_empty() {
+        return [0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+    }
+
+    let (lo, hi) = endpoints(&colors);
+    let (mut c0, mut c1) = (to_565(hi), to_565(lo));
+    // The order of the endpoints selects the mode: 4 colors when c0 > c1.
+    if transparent == (c0 > c1) {
+        std::mem::swap(&mut c0, &mut c1);
+    }
+
+    let (e0, e1) = (from_565(c0), from_565(c1));
+    let palette: Vec<[i32; 3]> = if transparent {
+        vec![e0, e1, [0, 1, 2].map(|c| (e0[c] + e1[c]) / 2)]
+    } else {
+        vec![
+            e0,
+            e1,
+            [0, 1, 2].map(|c| (2 * e0[c] + e1[c]) / 3),
+            [0, 1, 2].map(|c| (e0[c] + 2 * e1[c]) / 3),
+        ]
+    };
+
+    let mut indices = 0_u32;
+    for (i, pixel) in block.iter().enumerate() {
+        let idx = if transparent && pixel[3] < 128 {
+            3
+        } else {
+            nearest(&palette, pixel)
+        };
+        indices |= (idx as u32) << (2 * i);
+    }
+
+    let mut res = [0; 8];
+    res[..2].copy_from_slice(&c0.to_le_bytes());
+    res[2..4].copy_from_slice(&c1.to_le_bytes());
+    res[4..].copy_from_slice(&indices.to_le_bytes());
+    res
+}
+
+fn encode_bc4(block: &Block, channel: usize) -> [u8; 8] {
+    let values = block.map(|it| it[channel]);
+    let min = *values.iter().min().unwrap_or(&0);
+    let max = *values.iter().max().unwrap_or(&0);
+
+    let mut res = [max, min, 0, 0, 0, 0, 0, 0];
+    if min == max {
+        return res;
+    }
+
+    let (c0, c1) = (max as i32, min as i32);
+    let mut palette = vec![[c0], [c1]];
+    palette.extend((2..8).map(|i| [((8 - i) * c0 + (i - 1) * c1) / 7]));
+
+    let mut indices = 0_u64;
+    for (i, v) in values.iter().enumerate() {
+        let idx = nearest(&palette, &[*v, 0, 0, 0]);
+        indices |= (idx as u64) << (3 * i);
+    }
+    res[2..].copy_from_slice(&indices.to_le_bytes()[..6]);
+    res
+}
+
+/// 7 bits per channel and a shared p-bit.
+fn quantize_bc7(color: [f32; 4]) -> ([u8; 4], u8) {
+    (0..2)
+        .map(|p| {
+            let q = color.map(|it| ((it - p as f32) / 2.0).round().clamp(0.0, 127.0) as u8);
+            let error: f32 = (0..4)
+                .map(|c| ((q[c] as f32 * 2.0 + p as f32) - color[c]).powi(2))
+                .sum();
+            ((q, p), error)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|it| it.0)
+        .unwrap_or(([0; 4], 0))
+}
+
+/// Mode 6: a single RGBA line with 16 levels.
+fn encode_bc7(block: &Block) -> [u8; 16] {
+    let colors: Vec<[f32; 4]> = block.iter().map(|it| it.map(|v| v as f32)).collect();
+    let (lo, hi) = endpoints(&colors);
+    let (mut e0, mut e1) = (quantize_bc7(lo), quantize_bc7(hi));
+
+    let unquantize = |(e, p): ([u8; 4], u8)| e.map(|v| ((v as u32) << 1 | p as u32) as i32);
+    let (a, b) = (unquantize(e0), unquantize(e1));
+    let palette: Vec<[i32; 4]> = BC7_WEIGHTS
+        .iter()
+        .map(|&w| {
+            let w = w as i32;
+            [0, 1, 2, 3].map(|c| ((64 - w) * a[c] + w * b[c] + 32) >> 6)
+        })
+        .collect();
+
+    let mut indices = block.map(|it| nearest(&palette, &it) as u128);
+    // The most significant bit of the first index is implicit zero.
+    if indices[0] >= 8 {
+        std::mem::swap(&mut e0, &mut e1);
+        indices = indices.map(|it| 15 - it);
+    }
+
+    let mut bits = BC7_MODE_6;
+    let mut pos = 7;
+    let mut push = |value: u128, len: u32| {
+        bits |= value << pos;
+        pos += len;
+    };
+    for c in 0..4 {
+        push(e0.0[c] as u128, 7);
+        push(e1.0[c] as u128, 7);
+    }
+    push(e0.1 as u128, 1);
+    push(e1.1 as u128, 1);
+    for (i, idx) in indices.iter().enumerate() {
+        push(*idx, if i == 0 { 3 } else { 4 });
+    }
+
+    bits.to_le_bytes()
+}
@@ -0,0 +1,199 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    formats::{compress, compressed_size},
+    pipeline::Compression,
+};
+
+fn rgb565(c: u16) -> [i32; 3] {
+    let (r, g, b) = ((c >> 11) as i32 & 31, (c >> 5) as i32 & 63, c as i32 & 31);
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+/// Decodes a BC1 block into RGBA, with alpha 0 for transparent pixels.
+fn decode_bc1(block: &[u8]) -> Vec<[i32; 4]> {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let palette = if c0 > c1 {
+        [
+            e0,
+            e1,
+            [0, 1, 2].map(|c| (2 * e0[c] + e1[c]) / 3),
+            [0, 1, 2].map(|c| (e0[c] + 2 * e1[c]) / 3),
+        ]
+    } else {
+        [e0, e1, [0, 1, 2].map(|c| (e0[c] + e1[c]) / 2), [0; 3]]
+    };
+    let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+
+    (0..16)
+        .map(|i| {
+            let idx = (indices >> (2 * i) & 3) as usize;
+            let [r, g, b] = palette[idx];
+            let a = if c0 <= c1 && idx == 3 { 0 } else { 255 };
+            [r, g, b, a]
+        })
+        .collect()
+}
+
+fn decode_bc4(block: &[u8]) -> Vec<i32> {
+    let (c0, c1) = (block[0] as i32, block[1] as i32);
+    let mut palette = vec![c0, c1];
+    if c0 > c1 {
+        palette.extend((2..8).map(|i| ((8 - i) * c0 + (i - 1) * c1) / 7));
+    } else {
+        palette.extend((2..6).map(|i| ((6 - i) * c0 + (i - 1) * c1) / 5));
+        palette.extend([0, 255]);
+    }
+    let mut bytes = [0; 8];
+    bytes[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bytes);
+
+    (0..16)
+        .map(|i| palette[(indices >> (3 * i) & 7) as usize])
+        .collect()
+}
+
+/// Decodes a BC7 mode 6 block.
+fn decode_bc7_mode6(block: &[u8]) -> Vec<[i32; 4]> {
+    let bits = u128::from_le_bytes(block.try_into().unwrap());
+    assert_eq!(bits & 0x7f, 0x40, "not a mode 6 block");
+
+    let read = |pos: u32, len: u32| (bits >> pos & ((1 << len) - 1)) as i32;
+    let (p0, p1) = (read(63, 1), read(64, 1));
+    let e0: Vec<i32> = (0..4).map(|c| read(7 + 14 * c, 7) << 1 | p0).collect();
+    let e1: Vec<i32> = (0..4).map(|c| read(14 + 14 * c, 7) << 1 | p1).collect();
+
+    let weights = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+    (0..16)
+        .map(|i| {
+            let idx = if i == 0 {
+                read(65, 3)
+            } else {
+                read(68 + 4 * (i - 1), 4)
+            };
+            let w = weights[idx as usize];
+            [0, 1, 2, 3].map(|c| ((64 - w) * e0[c] + w * e1[c] + 32) >> 6)
+        })
+        .collect()
+}
+
+fn gradient() -> RgbaImage {
+    RgbaImage::from_fn(4, 4, |x, y| {
+        let t = (x + y * 4) as u8 * 16;
+        Rgba([t, 255 - t, t / 2, 255 - t / 4])
+    })
+}
+
+fn max_error(decoded: &[[i32; 4]], image: &RgbaImage, channels: usize) -> i32 {
+    image
+        .pixels()
+        .zip(decoded)
+        .flat_map(|(pixel, decoded)| {
+            (0..channels).map(move |c| (pixel[c] as i32 - decoded[c]).abs())
+        })
+        .max()
+        .unwrap()
+}
+
+#[test]
+fn test_compressed_size() {
+    assert_eq!(compressed_size((16, 16), Compression::Bc1), 128);
+    assert_eq!(compressed_size((16, 16), Compression::Bc7), 256);
+    assert_eq!(compressed_size((5, 1), Compression::Bc4), 16);
+    assert_eq!(compressed_size((1, 1), Compression::Bc5), 16);
+
+    let image = RgbaImage::new(6, 5);
+    for compression in [
+        Compression::Bc1,
+        Compression::Bc3,
+        Compression::Bc4,
+        Compression::Bc5,
+        Compression::Bc7,
+    ] {
+        assert_eq!(
+            compress(&image, compression).len(),
+            compressed_size((6, 5), compression)
+        );
+    }
+}
+
+#[test]
+fn test_bc1_solid_block() {
+    let image = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+    let data = compress(&image, Compression::Bc1);
+
+    assert!(decode_bc1(&data).iter().all(|it| it == &[255, 0, 0, 255]));
+}
+
+#[test]
+fn test_bc1_gradient() {
+    let image = gradient();
+    let decoded = decode_bc1(&compress(&image, Compression::Bc1));
+
+    assert!(decoded.iter().all(|it| it[3] == 255));
+    assert!(max_error(&decoded, &image, 3) <= 48);
+}
+
+#[test]
+fn test_bc1_transparent_pixels() {
+    let image = RgbaImage::from_fn(4, 4, |x, _| {
+        if x < 2 {
+            Rgba([0, 0, 255, 0])
+        } else {
+            Rgba([0, 255, 0, 255])
+        }
+    });
+    let decoded = decode_bc1(&compress(&image, Compression::Bc1));
+
+    for (i, pixel) in decoded.iter().enumerate() {
+        if i % 4 < 2 {
+            assert_eq!(pixel[3], 0);
+        } else {
+            assert_eq!(pixel, &[0, 255, 0, 255]);
+        }
+    }
+}
+
+#[test]
+fn test_bc4_and_bc5() {
+    let image = gradient();
+
+    let bc4 = decode_bc4(&compress(&image, Compression::Bc4));
+    let bc5 = compress(&image, Compression::Bc5);
+    let (red, green) = (decode_bc4(&bc5[..8]), decode_bc4(&bc5[8..]));
+
+    for (i, pixel) in image.pixels().enumerate() {
+        assert!((pixel[0] as i32 - bc4[i]).abs() <= 18);
+        assert_eq!(red[i], bc4[i]);
+        assert!((pixel[1] as i32 - green[i]).abs() <= 18);
+    }
+}
+
+#[test]
+fn test_bc3_alpha() {
+    let image = gradient();
+    let data = compress(&image, Compression::Bc3);
+
+    let alpha = decode_bc4(&data[..8]);
+    for (i, pixel) in image.pixels().enumerate() {
+        assert!((pixel[3] as i32 - alpha[i]).abs() <= 5);
+    }
+}
+
+#[test]
+fn test_bc7_gradient() {
+    let image = gradient();
+    let decoded = decode_bc7_mode6(&compress(&image, Compression::Bc7));
+
+    assert!(max_error(&decoded, &image, 4) <= 12);
+}
+
+#[test]
+fn test_bc7_solid_block() {
+    let image = RgbaImage::from_pixel(4, 4, Rgba([12, 34, 200, 255]));
+    let decoded = decode_bc7_mode6(&compress(&image, Compression::Bc7));
+
+    assert!(max_error(&decoded, &image, 4) <= 1);
+}
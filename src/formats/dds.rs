@@ -0,0 +1,179 @@
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+
+use super::level_data;
+use crate::pipeline::{ColorSpace, Compression};
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: u32 = 124;
+const PIXEL_FORMAT_SIZE: u32 = 32;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+
+const FOURCC_DX10: &[u8; 4] = b"DX10";
+const DIMENSION_TEXTURE2D: u32 = 3;
+/// Arrays always use the DX10 header, which has no legacy RGBA layout.
+const DXGI_FORMAT_R8G8B8A8_UNORM: u32 = 28;
+
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xfe00;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// `None` for the legacy header. BC4 and BC5 have no sRGB variants.
+fn dxgi_format(compression: Option<Compression>, srgb: bool) -> Option<u32> {
+    let format = match (compression, srgb) {
+        (None, false) => return None,
+        (None, true) => 29,
+        (Some(Compression::Bc1), false) => 71,
+        (Some(Compression::Bc1), true) => 72,
+        (Some(Compression::Bc3), false) => 77,
+        (Some(Compression::Bc3), true) => 78,
+        (Some(Compression::Bc4), _) => 80,
+        (Some(Compression::Bc5), _) => 83,
+        (Some(Compression::Bc7), false) => 98,
+        (Some(Compression::Bc7), true) => 99,
+    };
+    Some(format)
+}
+
+pub fn encode_dds(
+    levels: &[RgbaImage],
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<Vec<u8>> {
+    encode_dds_layers(&[levels], compression, color_space)
+}
+
+/// Every layer is followed by its mip levels.
+pub fn encode_dds_layers(
+    layers: &[&[RgbaImage]],
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<Vec<u8>> {
+    encode(layers, false, compression, color_space)
+}
+
+pub fn encode_dds_cubemap(
+    faces: &[&[RgbaImage]],
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<Vec<u8>> {
+    if faces.len() != 6 {
+        return Err(anyhow!("DDS cubemaps need six faces"));
+    }
+    encode(faces, true, compression, color_space)
+}
+
+fn encode(
+    layers: &[&[RgbaImage]],
+    cube: bool,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<Vec<u8>> {
+    let levels = *layers
+        .first()
+        .ok_or_else(|| anyhow!("DDS needs at least one layer"))?;
+    let first = levels
+        .first()
+        .ok_or_else(|| anyhow!("DDS needs at least one mip level"))?;
+    let (w, h) = first.dimensions();
+    if layers.iter().any(|it| it.len() != levels.len()) {
+        return Err(anyhow!("DDS layers need the same number of mip levels"));
+    }
+
+    let data: Vec<Vec<u8>> = layers
+        .iter()
+        .flat_map(|it| it.iter())
+        .map(|it| level_data(it, compression))
+        .collect();
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT;
+    let mut caps = DDSCAPS_TEXTURE;
+    if levels.len() > 1 {
+        flags |= DDSD_MIPMAPCOUNT;
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+    let mut caps2 = 0;
+    if cube {
+        caps |= DDSCAPS_COMPLEX;
+        caps2 = DDSCAPS2_CUBEMAP_ALLFACES;
+    }
+
+    let pitch = match compression {
+        Some(_) => {
+            flags |= DDSD_LINEARSIZE;
+            data[0].len() as u32
+        }
+        None => {
+            flags |= DDSD_PITCH;
+            w * 4
+        }
+    };
+
+    let header = [HEADER_SIZE, flags, h, w, pitch, 0, levels.len() as u32];
+    let dxgi_format = dxgi_format(compression, color_space == ColorSpace::Srgb)
+        .or((layers.len() > 1 && !cube).then_some(DXGI_FORMAT_R8G8B8A8_UNORM));
+    let pixel_format = match dxgi_format {
+        Some(_) => [
+            PIXEL_FORMAT_SIZE,
+            DDPF_FOURCC,
+            u32::from_le_bytes(*FOURCC_DX10),
+            0,
+            0,
+            0,
+            0,
+            0,
+        ],
+        None => [
+            PIXEL_FORMAT_SIZE,
+            DDPF_RGB | DDPF_ALPHAPIXELS,
+            0,
+            32,
+            0x0000_00ff,
+            0x0000_ff00,
+            0x00ff_0000,
+            0xff00_0000,
+        ],
+    };
+    let caps = [caps, caps2, 0, 0, 0];
+
+    let mut res = MAGIC.to_vec();
+    let words = header
+        .iter()
+        .chain([0; 11].iter())
+        .chain(pixel_format.iter())
+        .chain(caps.iter());
+    for word in words {
+        res.extend(word.to_le_bytes());
+    }
+
+    if let Some(dxgi_format) = dxgi_format {
+        // A cubemap is an array of a single cube.
+        let (misc, array_size) = match cube {
+            true => (DDS_RESOURCE_MISC_TEXTURECUBE, 1),
+            false => (0, layers.len() as u32),
+        };
+        let dx10 = [dxgi_format, DIMENSION_TEXTURE2D, misc, array_size, 0];
+        for word in dx10 {
+            res.extend(word.to_le_bytes());
+        }
+    }
+
+    for level in data {
+        res.extend(level);
+    }
+
+    Ok(res)
+}
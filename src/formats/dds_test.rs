@@ -0,0 +1,107 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    formats::{encode_dds, encode_dds_cubemap, encode_dds_layers},
+    pipeline::{ColorSpace, Compression},
+};
+
+fn word(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn test_dds_header() {
+    let levels = vec![
+        RgbaImage::from_pixel(4, 2, Rgba([1, 2, 3, 4])),
+        RgbaImage::from_pixel(2, 1, Rgba([5, 6, 7, 8])),
+        RgbaImage::from_pixel(1, 1, Rgba([9, 10, 11, 12])),
+    ];
+
+    let data = encode_dds(&levels, None, ColorSpace::Data).unwrap();
+
+    assert_eq!(&data[..4], b"DDS ");
+    assert_eq!(word(&data, 4), 124);
+    assert_eq!(word(&data, 12), 2);
+    assert_eq!(word(&data, 16), 4);
+    assert_eq!(word(&data, 20), 16);
+    assert_eq!(word(&data, 28), 3);
+    assert_eq!(data.len(), 128 + (8 + 2 + 1) * 4);
+}
+
+#[test]
+fn test_dds_levels_follow_the_header() {
+    let levels = vec![
+        RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 4])),
+        RgbaImage::from_pixel(1, 1, Rgba([9, 10, 11, 12])),
+    ];
+
+    let data = encode_dds(&levels, None, ColorSpace::Data).unwrap();
+
+    assert_eq!(&data[128..132], &[1, 2, 3, 4]);
+    assert_eq!(&data[128 + 16..], &[9, 10, 11, 12]);
+}
+
+#[test]
+fn test_dds_without_levels() {
+    assert!(encode_dds(&[], None, ColorSpace::Data).is_err());
+}
+
+#[test]
+fn test_dds_compressed() {
+    let levels = vec![RgbaImage::new(8, 8), RgbaImage::new(4, 4)];
+
+    let data = encode_dds(&levels, Some(Compression::Bc1), ColorSpace::Data).unwrap();
+
+    assert_eq!(word(&data, 20), 32);
+    assert_eq!(&data[84..88], b"DX10");
+    assert_eq!(word(&data, 128), 71);
+    assert_eq!(data.len(), 148 + 32 + 8);
+}
+
+#[test]
+fn test_dds_srgb() {
+    let levels = vec![RgbaImage::new(4, 4)];
+
+    let bc1 = encode_dds(&levels, Some(Compression::Bc1), ColorSpace::Srgb).unwrap();
+    let rgba = encode_dds(&levels, None, ColorSpace::Srgb).unwrap();
+
+    assert_eq!(word(&bc1, 128), 72);
+    assert_eq!(&rgba[84..88], b"DX10");
+    assert_eq!(word(&rgba, 128), 29);
+    assert_eq!(rgba.len(), 148 + 64);
+}
+
+#[test]
+fn test_dds_array() {
+    let first = vec![RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 4]))];
+    let second = vec![RgbaImage::from_pixel(2, 2, Rgba([5, 6, 7, 8]))];
+
+    let data = encode_dds_layers(&[&first, &second], None, ColorSpace::Data).unwrap();
+
+    // Arrays are described by the DX10 header, which follows the legacy one.
+    assert_eq!(&data[84..88], b"DX10");
+    assert_eq!(word(&data, 128), 28);
+    assert_eq!(word(&data, 140), 2);
+    assert_eq!(data.len(), 148 + 2 * 16);
+    assert_eq!(&data[148..152], &[1, 2, 3, 4]);
+    assert_eq!(&data[164..168], &[5, 6, 7, 8]);
+}
+
+#[test]
+fn test_dds_cubemap() {
+    let faces: Vec<Vec<RgbaImage>> = (0..6)
+        .map(|i| vec![RgbaImage::from_pixel(2, 2, Rgba([i, 0, 0, 255]))])
+        .collect();
+    let faces: Vec<&[RgbaImage]> = faces.iter().map(|it| it.as_slice()).collect();
+
+    let data = encode_dds_cubemap(&faces, None, ColorSpace::Data).unwrap();
+    let srgb = encode_dds_cubemap(&faces, None, ColorSpace::Srgb).unwrap();
+
+    // Uncompressed linear cubemaps keep the legacy header.
+    assert_eq!(word(&data, 108) & 0x8, 0x8);
+    assert_eq!(word(&data, 112), 0xfe00);
+    assert_eq!(data.len(), 128 + 6 * 16);
+    assert_eq!(&data[128 + 5 * 16..128 + 5 * 16 + 4], &[5, 0, 0, 255]);
+    assert_eq!((word(&srgb, 136), word(&srgb, 140)), (0x4, 1));
+    assert!(encode_dds_cubemap(&faces[..5], None, ColorSpace::Data).is_err());
+}
@@ -0,0 +1,222 @@
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+
+use super::level_data;
+use crate::pipeline::{ColorSpace, Compression};
+
+const IDENTIFIER: &[u8; 12] = b"\xabKTX 20\xbb\r\n\x1a\n";
+const HEADER_SIZE: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+
+const KHR_DF_VERSION: u32 = 2;
+const KHR_DF_MODEL_RGBSDA: u8 = 1;
+const KHR_DF_PRIMARIES_UNSPECIFIED: u8 = 0;
+const KHR_DF_PRIMARIES_BT709: u8 = 1;
+const KHR_DF_TRANSFER_LINEAR: u8 = 1;
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+const KHR_DF_CHANNEL_ALPHA: u8 = 15;
+/// Required for the alpha of sRGB textures.
+const KHR_DF_SAMPLE_DATATYPE_LINEAR: u8 = 0x10;
+
+/// BC4 and BC5 have no sRGB formats.
+fn format(compression: Compression, srgb: bool) -> (u32, u8) {
+    let (vk_format, model) = match compression {
+        Compression::Bc1 => (133, 128),
+        Compression::Bc3 => (137, 130),
+        Compression::Bc4 => return (139, 131),
+        Compression::Bc5 => return (141, 132),
+        Compression::Bc7 => (145, 134),
+    };
+    (vk_format + srgb as u32, model)
+}
+
+struct Sample {
+    offset: u16,
+    bits: u8,
+    channel: u8,
+    upper: u32,
+}
+
+fn data_format_descriptor(compression: Option<Compression>, color_space: ColorSpace) -> Vec<u8> {
+    let (model, block_size, bytes, samples) = match compression {
+        None => {
+            let samples = [0, 1, 2, KHR_DF_CHANNEL_ALPHA]
+                .iter()
+                .enumerate()
+                .map(|(i, &channel)| Sample {
+                    offset: i as u16 * 8,
+                    bits: 8,
+                    channel,
+                    upper: 255,
+                })
+                .collect();
+            (KHR_DF_MODEL_RGBSDA, 0, 4, samples)
+        }
+        Some(compression) => {
+            let (_, model) = format(compression, false);
+            let channels: &[u8] = match compression {
+                // The alpha of BC1 is only present or not.
+                Compression::Bc1 => &[1],
+                Compression::Bc3 => &[KHR_DF_CHANNEL_ALPHA, 0],
+                Compression::Bc4 | Compression::Bc7 => &[0],
+                Compression::Bc5 => &[0, 1],
+            };
+            let bits = (compression.block_bytes() * 8 / channels.len()) as u16;
+            let samples = channels
+                .iter()
+                .enumerate()
+                .map(|(i, &channel)| Sample {
+                    offset: i as u16 * bits,
+                    bits: bits as u8,
+                    channel,
+                    upper: u32::MAX,
+                })
+                .collect::<Vec<_>>();
+            (model, 3, compression.block_bytes() as u8, samples)
+        }
+    };
+
+    let (primaries, transfer) = match color_space {
+        ColorSpace::Srgb => (KHR_DF_PRIMARIES_BT709, KHR_DF_TRANSFER_SRGB),
+        ColorSpace::Linear => (KHR_DF_PRIMARIES_BT709, KHR_DF_TRANSFER_LINEAR),
+        ColorSpace::Data => (KHR_DF_PRIMARIES_UNSPECIFIED, KHR_DF_TRANSFER_LINEAR),
+    };
+
+    let block_len = 24 + 16 * samples.len() as u32;
+    let mut res = vec![];
+    res.extend((4 + block_len).to_le_bytes());
+    res.extend(0_u32.to_le_bytes());
+    res.extend((KHR_DF_VERSION | block_len << 16).to_le_bytes());
+    res.extend([model, primaries, transfer, 0, block_size, block_size, 0, 0]);
+    res.extend([bytes, 0, 0, 0, 0, 0, 0, 0]);
+    for sample in samples {
+        let channel = match sample.channel {
+            KHR_DF_CHANNEL_ALPHA if transfer == KHR_DF_TRANSFER_SRGB => {
+                KHR_DF_CHANNEL_ALPHA | KHR_DF_SAMPLE_DATATYPE_LINEAR
+            }
+            channel => channel,
+        };
+        res.extend(sample.offset.to_le_bytes());
+        res.extend([sample.bits - 1, channel, 0, 0, 0, 0]);
+        res.extend(0_u32.to_le_bytes());
+        res.extend(sample.upper.to_le_bytes());
+    }
+    res
+}
+
+pub fn encode_ktx2(
+    levels: &[RgbaImage],
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<Vec<u8>> {
+    encode_ktx2_layers(&[levels], compression, color_space)
+}
+
+/// Every mip level stores all the layers one after another.
+pub fn encode_ktx2_layers(
+    layers: &[&[RgbaImage]],
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<Vec<u8>> {
+    encode(layers, false, compression, color_space)
+}
+
+pub fn encode_ktx2_cubemap(
+    faces: &[&[RgbaImage]],
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<Vec<u8>> {
+    if faces.len() != 6 {
+        return Err(anyhow!("KTX2 cubemaps need six faces"));
+    }
+    encode(faces, true, compression, color_space)
+}
+
+fn encode(
+    layers: &[&[RgbaImage]],
+    cube: bool,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<Vec<u8>> {
+    let levels = *layers
+        .first()
+        .ok_or_else(|| anyhow!("KTX2 needs at least one layer"))?;
+    let first = levels
+        .first()
+        .ok_or_else(|| anyhow!("KTX2 needs at least one mip level"))?;
+    let (w, h) = first.dimensions();
+    if layers.iter().any(|it| it.len() != levels.len()) {
+        return Err(anyhow!("KTX2 layers need the same number of mip levels"));
+    }
+
+    let srgb = color_space == ColorSpace::Srgb;
+    let (vk_format, type_size, alignment) = match compression {
+        Some(compression) => (format(compression, srgb).0, 1, compression.block_bytes()),
+        None if srgb => (VK_FORMAT_R8G8B8A8_SRGB, 1, 4),
+        None => (VK_FORMAT_R8G8B8A8_UNORM, 1, 4),
+    };
+
+    let dfd = data_format_descriptor(compression, color_space);
+    let dfd_offset = HEADER_SIZE + LEVEL_INDEX_ENTRY_SIZE * levels.len();
+
+    // Levels are stored from the smallest one, each aligned to the texel block size.
+    let data: Vec<Vec<u8>> = (0..levels.len())
+        .map(|level| {
+            layers
+                .iter()
+                .flat_map(|it| level_data(&it[level], compression))
+                .collect()
+        })
+        .collect();
+    let mut offsets = vec![0; levels.len()];
+    let mut end = dfd_offset + dfd.len();
+    for (level, bytes) in data.iter().enumerate().rev() {
+        end = end.next_multiple_of(alignment);
+        offsets[level] = end;
+        end += bytes.len();
+    }
+
+    let mut res = IDENTIFIER.to_vec();
+    let header = [
+        vk_format,
+        type_size,
+        w,
+        h,
+        0,
+        // Zero layers is not an array, the faces of a cubemap are not layers.
+        if layers.len() > 1 && !cube {
+            layers.len() as u32
+        } else {
+            0
+        },
+        if cube { 6 } else { 1 },
+        levels.len() as u32,
+        0,
+        dfd_offset as u32,
+        dfd.len() as u32,
+        0,
+        0,
+    ];
+    for word in header {
+        res.extend(word.to_le_bytes());
+    }
+    // No supercompression global data.
+    res.extend([0; 16]);
+
+    for (level, bytes) in data.iter().enumerate() {
+        res.extend((offsets[level] as u64).to_le_bytes());
+        res.extend((bytes.len() as u64).to_le_bytes());
+        res.extend((bytes.len() as u64).to_le_bytes());
+    }
+    res.extend(dfd);
+
+    for (level, bytes) in data.iter().enumerate().rev() {
+        res.resize(offsets[level], 0);
+        res.extend(bytes);
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,147 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    formats::{compressed_size, encode_ktx2, encode_ktx2_cubemap, encode_ktx2_layers},
+    pipeline::{ColorSpace, Compression},
+};
+
+fn word(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn long(data: &[u8], offset: usize) -> usize {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap()) as usize
+}
+
+#[test]
+fn test_ktx2_header() {
+    let levels = vec![
+        RgbaImage::from_pixel(8, 4, Rgba([1, 2, 3, 4])),
+        RgbaImage::from_pixel(4, 2, Rgba([5, 6, 7, 8])),
+    ];
+
+    let data = encode_ktx2(&levels, None, ColorSpace::Data).unwrap();
+
+    assert_eq!(&data[..12], b"\xabKTX 20\xbb\r\n\x1a\n");
+    assert_eq!(word(&data, 12), 37);
+    assert_eq!(word(&data, 20), 8);
+    assert_eq!(word(&data, 24), 4);
+    assert_eq!(word(&data, 36), 1);
+    assert_eq!(word(&data, 40), 2);
+
+    // The data format descriptor follows the level index.
+    let dfd_offset = word(&data, 48) as usize;
+    assert_eq!(dfd_offset, 80 + 2 * 24);
+    assert_eq!(word(&data, 52), word(&data, dfd_offset));
+}
+
+#[test]
+fn test_ktx2_levels_from_smallest() {
+    let levels = vec![
+        RgbaImage::from_pixel(8, 4, Rgba([1, 2, 3, 4])),
+        RgbaImage::from_pixel(4, 2, Rgba([5, 6, 7, 8])),
+    ];
+
+    let data = encode_ktx2(&levels, None, ColorSpace::Data).unwrap();
+
+    let (offset0, len0) = (long(&data, 80), long(&data, 88));
+    let (offset1, len1) = (long(&data, 104), long(&data, 112));
+    assert_eq!((len0, len1), (8 * 4 * 4, 4 * 2 * 4));
+    assert!(offset1 < offset0);
+    assert_eq!(offset0 + len0, data.len());
+    assert_eq!(&data[offset0..offset0 + 4], &[1, 2, 3, 4]);
+    assert_eq!(&data[offset1..offset1 + 4], &[5, 6, 7, 8]);
+}
+
+#[test]
+fn test_ktx2_compressed_levels_are_aligned() {
+    let levels = vec![
+        RgbaImage::new(8, 8),
+        RgbaImage::new(4, 4),
+        RgbaImage::new(2, 2),
+    ];
+
+    let data = encode_ktx2(&levels, Some(Compression::Bc7), ColorSpace::Data).unwrap();
+
+    assert_eq!(word(&data, 12), 145);
+    for (level, image) in levels.iter().enumerate() {
+        let offset = long(&data, 80 + level * 24);
+        let len = long(&data, 88 + level * 24);
+        assert_eq!(offset % 16, 0);
+        assert_eq!(len, compressed_size(image.dimensions(), Compression::Bc7));
+    }
+}
+
+#[test]
+fn test_ktx2_srgb() {
+    let levels = vec![RgbaImage::new(4, 4)];
+
+    let bc7 = encode_ktx2(&levels, Some(Compression::Bc7), ColorSpace::Srgb).unwrap();
+    let rgba = encode_ktx2(&levels, None, ColorSpace::Srgb).unwrap();
+    let bc5 = encode_ktx2(&levels, Some(Compression::Bc5), ColorSpace::Data).unwrap();
+
+    assert_eq!(word(&bc7, 12), 146);
+    assert_eq!(word(&rgba, 12), 43);
+    assert_eq!(word(&bc5, 12), 141);
+
+    // Transfer function of the data format descriptor.
+    let dfd = word(&bc7, 48) as usize;
+    assert_eq!(bc7[dfd + 14], 2);
+    assert_eq!(bc5[word(&bc5, 48) as usize + 14], 1);
+}
+
+#[test]
+fn test_ktx2_srgb_alpha_is_linear() {
+    let levels = vec![RgbaImage::new(4, 4)];
+    // Channel of a sample, the samples follow the 28 bytes of headers.
+    let channel = |data: &[u8], sample: usize| data[word(data, 48) as usize + 28 + 16 * sample + 3];
+
+    let rgba = encode_ktx2(&levels, None, ColorSpace::Srgb).unwrap();
+    assert_eq!(channel(&rgba, 0), 0);
+    assert_eq!(channel(&rgba, 3), 0x1f);
+
+    let bc3 = encode_ktx2(&levels, Some(Compression::Bc3), ColorSpace::Srgb).unwrap();
+    assert_eq!(channel(&bc3, 0), 0x1f);
+    assert_eq!(channel(&bc3, 1), 0);
+
+    let linear = encode_ktx2(&levels, None, ColorSpace::Linear).unwrap();
+    assert_eq!(channel(&linear, 3), 15);
+}
+
+#[test]
+fn test_ktx2_array() {
+    let first = vec![
+        RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 4])),
+        RgbaImage::from_pixel(1, 1, Rgba([9, 9, 9, 9])),
+    ];
+    let second = vec![
+        RgbaImage::from_pixel(2, 2, Rgba([5, 6, 7, 8])),
+        RgbaImage::from_pixel(1, 1, Rgba([7, 7, 7, 7])),
+    ];
+
+    let data = encode_ktx2_layers(&[&first, &second], None, ColorSpace::Data).unwrap();
+
+    assert_eq!(word(&data, 32), 2);
+    // Every level stores the layers one after another.
+    let (offset0, len0) = (long(&data, 80), long(&data, 88));
+    let (offset1, len1) = (long(&data, 104), long(&data, 112));
+    assert_eq!((len0, len1), (2 * 16, 2 * 4));
+    assert_eq!(&data[offset0..offset0 + 4], &[1, 2, 3, 4]);
+    assert_eq!(&data[offset0 + 16..offset0 + 20], &[5, 6, 7, 8]);
+    assert_eq!(&data[offset1..offset1 + 8], &[9, 9, 9, 9, 7, 7, 7, 7]);
+}
+
+#[test]
+fn test_ktx2_cubemap() {
+    let faces: Vec<Vec<RgbaImage>> = (0..6)
+        .map(|i| vec![RgbaImage::from_pixel(2, 2, Rgba([i, 0, 0, 255]))])
+        .collect();
+    let faces: Vec<&[RgbaImage]> = faces.iter().map(|it| it.as_slice()).collect();
+
+    let data = encode_ktx2_cubemap(&faces, None, ColorSpace::Data).unwrap();
+
+    assert_eq!((word(&data, 32), word(&data, 36)), (0, 6));
+    let (offset, len) = (long(&data, 80), long(&data, 88));
+    assert_eq!(len, 6 * 16);
+    assert_eq!(&data[offset + 5 * 16..offset + 5 * 16 + 4], &[5, 0, 0, 255]);
+}
@@ -0,0 +1,74 @@
+mod bc;
+#[cfg(test)]
+pub mod bc_test;
+mod dds;
+#[cfg(test)]
+pub mod dds_test;
+mod ktx2;
+#[cfg(test)]
+pub mod ktx2_test;
+
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+
+use crate::pipeline::{ColorSpace, Compression};
+
+pub use bc::{compress, compressed_size};
+pub use dds::{encode_dds, encode_dds_cubemap, encode_dds_layers};
+pub use ktx2::{encode_ktx2, encode_ktx2_cubemap, encode_ktx2_layers};
+
+pub fn is_container(fname: &str) -> bool {
+    extension(fname).is_some_and(|it| it == "dds" || it == "ktx2")
+}
+
+pub fn write(
+    levels: &[RgbaImage],
+    fname: &str,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<()> {
+    write_layers(&[levels], fname, compression, color_space)
+}
+
+pub fn write_layers(
+    layers: &[&[RgbaImage]],
+    fname: &str,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<()> {
+    let data = match extension(fname).as_deref() {
+        Some("dds") => encode_dds_layers(layers, compression, color_space)?,
+        Some("ktx2") => encode_ktx2_layers(layers, compression, color_space)?,
+        _ => return Err(anyhow!("Unsupported container format: {fname}")),
+    };
+    std::fs::write(fname, data)?;
+    Ok(())
+}
+
+pub fn write_cubemap(
+    faces: &[&[RgbaImage]],
+    fname: &str,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<()> {
+    let data = match extension(fname).as_deref() {
+        Some("dds") => encode_dds_cubemap(faces, compression, color_space)?,
+        Some("ktx2") => encode_ktx2_cubemap(faces, compression, color_space)?,
+        _ => return Err(anyhow!("Unsupported container format: {fname}")),
+    };
+    std::fs::write(fname, data)?;
+    Ok(())
+}
+
+fn level_data(level: &RgbaImage, compression: Option<Compression>) -> Vec<u8> {
+    match compression {
+        Some(compression) => compress(level, compression),
+        None => level.as_raw().clone(),
+    }
+}
+
+fn extension(fname: &str) -> Option<String> {
+    std::path::Path::new(fname)
+        .extension()
+        .map(|it| it.to_string_lossy().to_lowercase())
+}
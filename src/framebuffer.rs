@@ -37,6 +37,18 @@ impl Framebuffer {
             );
         }
     }
+
+    pub fn attach_layer(&self, texture: &Texture, layer: u32) {
+        unsafe {
+            gl::NamedFramebufferTextureLayer(
+                self.id,
+                gl::COLOR_ATTACHMENT0,
+                texture.get_id(),
+                0,
+                layer as i32,
+            );
+        }
+    }
 }
 
 impl Default for Framebuffer {
@@ -4,36 +4,83 @@ use std::{thread, time::Duration};
 
 use context::Ctx;
 use expirable::Expirable;
-use pipeline::Pipeline;
+use pipeline::{Pipeline, Sweep};
+use preview::{ErrorOverlay, LABEL_HEIGHT, MAX_COLUMNS, PREVIEW_SIZE};
 use project_path::ProjectPath;
 
+pub mod animation;
+#[cfg(test)]
+pub mod animation_test;
+pub mod atlas;
+#[cfg(test)]
+pub mod atlas_test;
+pub mod color_space;
+#[cfg(test)]
+pub mod color_space_test;
 pub mod context;
+pub mod cubemap;
+#[cfg(test)]
+pub mod cubemap_test;
+pub mod encoder;
+#[cfg(test)]
+pub mod encoder_test;
 pub mod executor;
 pub mod expirable;
+pub mod formats;
 pub mod framebuffer;
 pub mod mesh;
+pub mod mips;
+#[cfg(test)]
+pub mod mips_test;
+pub mod ops;
 pub mod pipeline;
 pub mod preprocessor;
+pub mod preview;
+pub mod profiler;
+#[cfg(test)]
+pub mod profiler_test;
 pub mod project_path;
+pub mod readback;
 pub mod shader;
+pub mod storage_buffer;
+pub mod sweep;
+#[cfg(test)]
+pub mod sweep_test;
+#[cfg(test)]
+pub mod test_util;
 pub mod texture;
+pub mod texture_pool;
+#[cfg(test)]
+pub mod texture_test;
+pub mod trace;
+#[cfg(test)]
+pub mod trace_test;
+pub mod volume;
+#[cfg(test)]
+pub mod volume_test;
+
+const FRAME_TIME: Duration = Duration::from_millis(33);
+const ERROR_FRAME_TIME: Duration = Duration::from_millis(500);
 
 fn main() {
     let path = ProjectPath::new("examples", "project.tw.yaml");
     let mut pipeline = Expirable::now(Pipeline::load_from_file(&path).unwrap());
+    let sweep = Sweep::from_args(pipeline.data().sweep.as_ref(), std::env::args().skip(1)).unwrap();
     let previews = pipeline.data().number_of_previews();
 
-    let width = 200 * previews;
-    let height = 200_usize;
+    let columns = previews.clamp(1, MAX_COLUMNS);
+    let rows = previews.div_ceil(columns).max(1);
+    let width = PREVIEW_SIZE as usize * columns;
+    let height = (PREVIEW_SIZE + LABEL_HEIGHT) as usize * rows;
 
     let sdl = sdl2::init().unwrap();
     let video_subsystem = sdl.video().unwrap();
-    let mut window = video_subsystem
-        .window("Texture Wizard", width as u32, height as u32)
-        .opengl()
-        .resizable()
-        .build()
-        .unwrap();
+    let mut window = video_subsystem.window("Texture Wizard", width as u32, height as u32);
+    window.opengl().resizable();
+    if sweep.is_some() {
+        window.hidden();
+    }
+    let window = window.build().unwrap();
 
     let _gl_context = window.gl_create_context().unwrap();
 
@@ -45,21 +92,38 @@ fn main() {
         gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
     }
 
-    let mut ctx = Ctx::load(path, &pipeline).unwrap();
+    let mut ctx = Ctx::load(path, &pipeline, (width as u32, height as u32)).unwrap();
 
-    executor::execute_pipeline(&mut ctx, &mut pipeline, true, |_| ()).unwrap();
+    if let Some(sweep) = sweep {
+        executor::run_sweep(&mut ctx, pipeline.data(), &sweep).unwrap();
+        return;
+    }
 
     if previews == 0 {
+        executor::execute_pipeline(&mut ctx, &mut pipeline, true).unwrap();
         return;
     }
 
     let mut event_pump = sdl.event_pump().unwrap();
     let mut err: Option<anyhow::Error> = None;
+    let mut force = true;
     loop {
         for event in event_pump.poll_iter() {
             match event {
                 sdl2::event::Event::Quit { .. } => return,
-                _ => (),
+                _ => {
+                    let variables = &mut ctx.variables;
+                    let changed = &mut ctx.dirty_variables;
+                    if !ctx.panel.handle_event(&event, variables, changed) {
+                        ctx.view.handle_event(&event);
+                    }
+                }
+            }
+        }
+
+        if ctx.panel.take_save_request() {
+            if let Err(e) = ctx.save_variables(pipeline.data()) {
+                println!("Failed to save variables: {e:?}");
             }
         }
 
@@ -67,35 +131,50 @@ fn main() {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
-        let new_err = executor::execute_pipeline(&mut ctx, &mut pipeline, false, |p| {
-            let width = 200 * p;
-            let height = 200_usize;
-            window.set_size(width as u32, height as u32).unwrap();
-        });
+        // While failing, the whole pipeline is reexecuted after every change,
+        // so an error is resolved only when the whole pipeline succeeds again.
+        let new_err = match err {
+            Some(_) => executor::retry_pipeline(&mut ctx, &mut pipeline),
+            None => executor::execute_pipeline(&mut ctx, &mut pipeline, force).map(|_| true),
+        };
+        force = false;
 
         match (&err, new_err) {
-            (None, Ok(_)) => (),
-            (Some(_), Ok(_)) => {
+            (None, Ok(_)) | (Some(_), Ok(false)) => (),
+            (Some(_), Ok(true)) => {
                 err = None;
                 ctx.logs_enabled = true;
+                ctx.view.error = None;
                 println!("Error resolved");
             }
             (None, Err(e)) => {
                 println!("Error: {e:?}");
+                ctx.view.error = Some(ErrorOverlay::new(&e));
                 err = Some(e);
                 ctx.logs_enabled = false;
-                thread::sleep(Duration::from_secs(1));
             }
             (Some(e0), Err(e1)) => {
                 if format!("{e0:?}") != format!("{e1:?}") {
                     println!("Error: {e1:?}");
+                    ctx.view.error = Some(ErrorOverlay::new(&e1));
                     err = Some(e1);
                 }
-                thread::sleep(Duration::from_secs(1));
+            }
+        }
+
+        if err.is_some() {
+            unsafe {
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
+            if let Err(e) = executor::draw_previews(&mut ctx, pipeline.data()) {
+                println!("Failed to draw previews: {e:?}");
             }
         }
 
         window.gl_swap_window();
-        thread::sleep(Duration::from_secs_f32(0.1));
+        match err {
+            Some(_) => thread::sleep(ERROR_FRAME_TIME),
+            None => thread::sleep(FRAME_TIME),
+        }
     }
 }
@@ -0,0 +1,178 @@
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Geometry {
+    pub positions: Vec<f32>,
+    pub uvs: Vec<f32>,
+    pub normals: Vec<f32>,
+}
+
+impl Geometry {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    pub fn push_vertex(&mut self, position: [f32; 3], uv: [f32; 2], normal: [f32; 3]) {
+        self.positions.extend(position);
+        self.uvs.extend(uv);
+        self.normals.extend(normal);
+    }
+
+    /// `a b c d` are counter-clockwise.
+    fn push_quad(&mut self, v: [([f32; 3], [f32; 2], [f32; 3]); 4]) {
+        for i in [0, 1, 2, 0, 2, 3] {
+            self.push_vertex(v[i].0, v[i].1, v[i].2);
+        }
+    }
+
+    fn grid<F>(columns: u32, rows: u32, f: F) -> Self
+    where
+        F: Fn(f32, f32) -> ([f32; 3], [f32; 3]),
+    {
+        let mut res = Self::default();
+        let vertex = |i: u32, j: u32| {
+            let uv = [i as f32 / columns as f32, j as f32 / rows as f32];
+            let (position, normal) = f(uv[0], uv[1]);
+            (position, uv, normal)
+        };
+
+        for j in 0..rows {
+            for i in 0..columns {
+                res.push_quad([
+                    vertex(i, j + 1),
+                    vertex(i + 1, j + 1),
+                    vertex(i + 1, j),
+                    vertex(i, j),
+                ]);
+            }
+        }
+
+        res
+    }
+
+    /// Square in the XY plane from -1 to 1.
+    pub fn plane(subdivisions: u32) -> Self {
+        let n = subdivisions.max(1);
+        Self::grid(n, n, |u, v| {
+            ([u * 2.0 - 1.0, 1.0 - v * 2.0, 0.0], [0.0, 0.0, 1.0])
+        })
+    }
+
+    pub fn sphere(segments: u32, rings: u32) -> Self {
+        Self::grid(segments.max(3), rings.max(2), |u, v| {
+            let (phi, theta) = (u * 2.0 * PI, v * PI);
+            let n = [
+                theta.sin() * phi.sin(),
+                theta.cos(),
+                theta.sin() * phi.cos(),
+            ];
+            (n, n)
+        })
+    }
+
+    /// Radius 1 and height 2 with flat caps.
+    pub fn cylinder(segments: u32) -> Self {
+        let segments = segments.max(3);
+        let mut res = Self::grid(segments, 1, |u, v| {
+            let phi = u * 2.0 * PI;
+            let n = [phi.sin(), 0.0, phi.cos()];
+            ([n[0], 1.0 - v * 2.0, n[2]], n)
+        });
+
+        for (y, ny) in [(1.0, 1.0), (-1.0, -1.0)] {
+            for i in 0..segments {
+                let mut ring = [i, i + 1].map(|i| {
+                    let phi = i as f32 / segments as f32 * 2.0 * PI;
+                    (phi.sin(), phi.cos())
+                });
+                if ny < 0.0 {
+                    ring.reverse();
+                }
+
+                res.push_vertex([0.0, y, 0.0], [0.5, 0.5], [0.0, ny, 0.0]);
+                for (x, z) in ring {
+                    let uv = [x * 0.5 + 0.5, z * 0.5 + 0.5];
+                    res.push_vertex([x, y, z], uv, [0.0, ny, 0.0]);
+                }
+            }
+        }
+
+        res
+    }
+
+    pub fn cube() -> Self {
+        let mut res = Self::default();
+
+        for axis in 0..3 {
+            for sign in [1.0, -1.0] {
+                let mut n = [0.0; 3];
+                n[axis] = sign;
+
+                let mut u = [0.0; 3];
+                u[(axis + 1) % 3] = sign;
+                let mut v = [0.0; 3];
+                v[(axis + 2) % 3] = 1.0;
+
+                let corner = |a: f32, b: f32| {
+                    let p = [0, 1, 2].map(|i| n[i] + u[i] * a + v[i] * b);
+                    (p, [a * 0.5 + 0.5, 0.5 - b * 0.5], n)
+                };
+
+                res.push_quad([
+                    corner(-1.0, -1.0),
+                    corner(1.0, -1.0),
+                    corner(1.0, 1.0),
+                    corner(-1.0, 1.0),
+                ]);
+            }
+        }
+
+        res
+    }
+
+    /// Fits the geometry into the `[-1, 1]` cube.
+    pub fn normalize(&mut self) {
+        if self.positions.is_empty() {
+            return;
+        }
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in self.positions.chunks(3) {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+
+        let center = [0, 1, 2].map(|i| (min[i] + max[i]) / 2.0);
+        let extent = (0..3).map(|i| max[i] - min[i]).fold(0.0, f32::max) / 2.0;
+        let scale = if extent > 0.0 { 1.0 / extent } else { 1.0 };
+
+        for p in self.positions.chunks_mut(3) {
+            for i in 0..3 {
+                p[i] = (p[i] - center[i]) * scale;
+            }
+        }
+    }
+
+    pub fn generate_normals(&mut self) {
+        self.normals.clear();
+
+        for t in self.positions.chunks(9) {
+            let a = [t[3] - t[0], t[4] - t[1], t[5] - t[2]];
+            let b = [t[6] - t[0], t[7] - t[1], t[8] - t[2]];
+            let n = [
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ];
+            let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2])
+                .sqrt()
+                .max(f32::EPSILON);
+            for _ in 0..3 {
+                self.normals.extend(n.map(|it| it / len));
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use super::geometry::Geometry;
+
+fn assert_consistent(geometry: &Geometry) {
+    assert_eq!(geometry.positions.len() % 9, 0);
+    assert_eq!(geometry.uvs.len(), geometry.vertex_count() * 2);
+    assert_eq!(geometry.normals.len(), geometry.vertex_count() * 3);
+}
+
+#[test]
+fn test_geometry_plane() {
+    let plane = Geometry::plane(4);
+
+    assert_consistent(&plane);
+    assert_eq!(plane.vertex_count(), 4 * 4 * 6);
+    assert!(plane.positions.chunks(3).all(|p| p[2] == 0.0));
+}
+
+#[test]
+fn test_geometry_sphere() {
+    let sphere = Geometry::sphere(16, 8);
+
+    assert_consistent(&sphere);
+    for p in sphere.positions.chunks(3) {
+        let len = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        assert!((len - 1.0).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_geometry_cube_and_cylinder() {
+    let cube = Geometry::cube();
+    assert_consistent(&cube);
+    assert_eq!(cube.vertex_count(), 36);
+
+    let cylinder = Geometry::cylinder(8);
+    assert_consistent(&cylinder);
+    assert_eq!(cylinder.vertex_count(), 8 * 6 + 2 * 8 * 3);
+}
+
+#[test]
+fn test_geometry_normalize() {
+    let mut geometry = Geometry::default();
+    geometry.push_vertex([2.0, 2.0, 2.0], [0.0, 0.0], [0.0, 0.0, 1.0]);
+    geometry.push_vertex([6.0, 2.0, 2.0], [0.0, 0.0], [0.0, 0.0, 1.0]);
+    geometry.push_vertex([2.0, 4.0, 2.0], [0.0, 0.0], [0.0, 0.0, 1.0]);
+    geometry.normalize();
+
+    assert_eq!(
+        geometry.positions,
+        vec![-1.0, -0.5, 0.0, 1.0, -0.5, 0.0, -1.0, 0.5, 0.0]
+    );
+
+    geometry.generate_normals();
+    assert_eq!(geometry.normals, [0.0, 0.0, 1.0].repeat(3));
+}
@@ -0,0 +1,128 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+
+use super::geometry::Geometry;
+
+pub fn load_geometry(fname: &str) -> Result<Geometry> {
+    let extension = Path::new(fname)
+        .extension()
+        .and_then(|it| it.to_str())
+        .map(|it| it.to_lowercase());
+
+    let mut geometry = match extension.as_deref() {
+        Some("obj") => load_obj(fname),
+        Some("gltf") | Some("glb") => load_gltf(fname),
+        _ => Err(anyhow!("Unsupported mesh format")),
+    }
+    .with_context(|| format!("Failed to load mesh from '{fname}'"))?;
+
+    if geometry.normals.len() != geometry.positions.len() {
+        geometry.generate_normals();
+    }
+    geometry.normalize();
+
+    Ok(geometry)
+}
+
+fn load_obj(fname: &str) -> Result<Geometry> {
+    let (models, _) = tobj::load_obj(fname, &tobj::GPU_LOAD_OPTIONS)?;
+
+    let mut geometry = Geometry::default();
+    let mut has_normals = true;
+
+    for model in models.iter() {
+        let mesh = &model.mesh;
+        has_normals &= !mesh.normals.is_empty();
+
+        for idx in mesh.indices.iter().map(|it| *it as usize) {
+            let position = [0, 1, 2].map(|i| mesh.positions[idx * 3 + i]);
+            let uv = match mesh.texcoords.is_empty() {
+                true => [0.0, 0.0],
+                false => [mesh.texcoords[idx * 2], 1.0 - mesh.texcoords[idx * 2 + 1]],
+            };
+            let normal = match mesh.normals.is_empty() {
+                true => [0.0, 0.0, 0.0],
+                false => [0, 1, 2].map(|i| mesh.normals[idx * 3 + i]),
+            };
+            geometry.push_vertex(position, uv, normal);
+        }
+    }
+
+    if !has_normals {
+        geometry.normals.clear();
+    }
+
+    Ok(geometry)
+}
+
+fn load_gltf(fname: &str) -> Result<Geometry> {
+    let gltf = gltf::Gltf::open(fname)?;
+    let dir = Path::new(fname).parent().unwrap_or(Path::new("."));
+
+    let mut buffers = Vec::new();
+    for buffer in gltf.document.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => gltf
+                .blob
+                .clone()
+                .ok_or_else(|| anyhow!("Missing binary chunk"))?,
+            gltf::buffer::Source::Uri(uri) => {
+                fs::read(dir.join(uri)).with_context(|| format!("Failed to read buffer '{uri}'"))?
+            }
+        };
+        buffers.push(data);
+    }
+
+    let mut geometry = Geometry::default();
+    let mut has_normals = true;
+
+    for mesh in gltf.document.meshes() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|it| &it[..]));
+
+            let positions: Vec<[f32; 3]> = match reader.read_positions() {
+                Some(it) => it.collect(),
+                None => continue,
+            };
+            let uvs: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+                Some(it) => it.into_f32().collect(),
+                None => vec![[0.0, 0.0]; positions.len()],
+            };
+            let normals: Vec<[f32; 3]> = match reader.read_normals() {
+                Some(it) => it.collect(),
+                None => {
+                    has_normals = false;
+                    vec![[0.0, 0.0, 0.0]; positions.len()]
+                }
+            };
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(it) => it.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            for idx in indices.iter().map(|it| *it as usize) {
+                let (Some(position), Some(uv), Some(normal)) =
+                    (positions.get(idx), uvs.get(idx), normals.get(idx))
+                else {
+                    return Err(anyhow!(
+                        "Index {idx} is out of range of the {} vertices of the mesh {}",
+                        positions.len(),
+                        mesh.index()
+                    ));
+                };
+                geometry.push_vertex(*position, *uv, *normal);
+            }
+        }
+    }
+
+    if !has_normals {
+        geometry.normals.clear();
+    }
+
+    Ok(geometry)
+}
@@ -0,0 +1,51 @@
+use crate::{mesh::load_geometry, test_util::TempDir};
+
+/// Writes a glTF triangle whose indices are `indices`.
+fn triangle(dir: &TempDir, indices: [u16; 3]) -> String {
+    let mut bin = vec![];
+    for position in [[0.0_f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+        bin.extend(position.iter().flat_map(|it| it.to_le_bytes()));
+    }
+    bin.extend(indices.iter().flat_map(|it| it.to_le_bytes()));
+    bin.extend([0, 0]);
+    std::fs::write(dir.file("triangle.bin"), &bin).unwrap();
+
+    let gltf = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{ "uri": "triangle.bin", "byteLength": 44 }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+              "min": [0, 0, 0], "max": [1, 1, 0] },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ],
+        "meshes": [{ "name": "triangle", "primitives": [
+            { "attributes": { "POSITION": 0 }, "indices": 1 }
+        ] }]
+    }"#;
+    let fname = dir.file("triangle.gltf");
+    std::fs::write(&fname, gltf).unwrap();
+    fname
+}
+
+#[test]
+fn test_load_gltf() {
+    let dir = TempDir::new("gltf");
+
+    let geometry = load_geometry(&triangle(&dir, [0, 1, 2])).unwrap();
+
+    assert_eq!(geometry.positions.len(), 3 * 3);
+    assert_eq!(geometry.normals.len(), 3 * 3);
+}
+
+#[test]
+fn test_load_gltf_index_out_of_range() {
+    let dir = TempDir::new("gltf");
+
+    let err = load_geometry(&triangle(&dir, [0, 1, 7])).unwrap_err();
+
+    assert!(format!("{err:#}").contains("Index 7 is out of range of the 3 vertices"));
+}
@@ -1,5 +1,15 @@
+mod geometry;
+#[cfg(test)]
+pub mod geometry_test;
+mod loader;
+#[cfg(test)]
+pub mod loader_test;
+
 use gl::types::{GLint, GLsizeiptr, GLuint, GLvoid};
 
+pub use geometry::Geometry;
+pub use loader::load_geometry;
+
 pub struct Mesh {
     _vbo: Vec<Vbo>,
     vao: Vao,
@@ -48,6 +58,26 @@ impl Mesh {
         }
     }
 
+    /// Normals are bound to the attribute `3`.
+    pub fn from_geometry(geometry: &Geometry) -> Self {
+        let vao = Vao::new();
+
+        let real_positions = Vbo::new(&geometry.positions);
+        let fake_positions = Vbo::new(&geometry.positions);
+        let uvs = Vbo::new(&geometry.uvs);
+        let normals = Vbo::new(&geometry.normals);
+
+        vao.attach_vbo(&real_positions, 0, 3);
+        vao.attach_vbo(&fake_positions, 1, 3);
+        vao.attach_vbo(&uvs, 2, 2);
+        vao.attach_vbo(&normals, 3, 3);
+        Self {
+            _vbo: vec![fake_positions, real_positions, uvs, normals],
+            vao,
+            vertex_count: geometry.vertex_count(),
+        }
+    }
+
     pub fn draw(&self) {
         self.vao.bind();
 
@@ -62,7 +92,7 @@ struct Vbo {
 }
 
 impl Vbo {
-    pub fn new(data: &Vec<f32>) -> Self {
+    pub fn new(data: &[f32]) -> Self {
         let mut vbo = 0;
         unsafe {
             gl::CreateBuffers(1, &mut vbo);
@@ -70,7 +100,7 @@ impl Vbo {
 
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (data.len() * std::mem::size_of::<f32>()) as GLsizeiptr,
+                std::mem::size_of_val(data) as GLsizeiptr,
                 data.as_ptr() as *const GLvoid,
                 gl::STATIC_DRAW,
             );
@@ -0,0 +1,244 @@
+use std::f32::consts::PI;
+
+use anyhow::{anyhow, Result};
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    color_space::{linear_to_srgb, srgb_to_linear},
+    formats,
+    pipeline::{suffixed_name, ColorSpace, Compression, Edges},
+    texture::write_image,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Downsample {
+    /// Texels shared by two pixels of odd sizes are weighted by coverage.
+    Box,
+    /// Kaiser-windowed sinc over 8 pixels.
+    Kaiser,
+}
+
+#[derive(Debug)]
+pub enum MipChain {
+    None,
+    Generate {
+        filter: Downsample,
+        edges: Edges,
+        levels: Option<u32>,
+        srgb: bool,
+    },
+    /// Levels below the base image, rendered on the GPU.
+    Levels(Vec<RgbaImage>),
+}
+
+const KAISER_TAPS: i64 = 8;
+const KAISER_ALPHA: f32 = 4.0;
+
+pub fn level_count((w, h): (u32, u32), max: Option<u32>) -> u32 {
+    let full = 32 - w.max(h).max(1).leading_zeros();
+    max.map_or(full, |max| max.clamp(1, full))
+}
+
+pub fn level_size((w, h): (u32, u32), level: u32) -> (u32, u32) {
+    ((w >> level).max(1), (h >> level).max(1))
+}
+
+/// Filters in linear space for sRGB images, starting with the image itself.
+pub fn generate(
+    image: RgbaImage,
+    filter: Downsample,
+    edges: Edges,
+    max: Option<u32>,
+    srgb: bool,
+) -> Vec<RgbaImage> {
+    let base = image.dimensions();
+    let count = level_count(base, max);
+
+    let decode = |v: u8| match srgb {
+        true => srgb_to_linear(v as f32 / 255.0) * 255.0,
+        false => v as f32,
+    };
+    let encode = |v: f32| match srgb {
+        true => linear_to_srgb(v / 255.0) * 255.0,
+        false => v,
+    };
+
+    let mut size = base;
+    let mut pixels: Vec<[f32; 4]> = image
+        .pixels()
+        .map(|it| [decode(it[0]), decode(it[1]), decode(it[2]), it[3] as f32])
+        .collect();
+    let mut levels = vec![image];
+
+    for level in 1..count {
+        let next = level_size(base, level);
+        pixels = match filter {
+            Downsample::Box => downsample_box(&pixels, size, next),
+            Downsample::Kaiser => downsample_kaiser(&pixels, size, next, edges),
+        };
+        size = next;
+
+        levels.push(RgbaImage::from_fn(size.0, size.1, |x, y| {
+            let [r, g, b, a] = pixels[(y * size.0 + x) as usize];
+            Rgba([encode(r), encode(g), encode(b), a].map(|v| v.round().clamp(0.0, 255.0) as u8))
+        }));
+    }
+
+    levels
+}
+
+fn wrap(v: i64, size: u32, edges: Edges) -> u32 {
+    match edges {
+        Edges::Wrap => v.rem_euclid(size as i64) as u32,
+        Edges::Clamp => v.clamp(0, size as i64 - 1) as u32,
+    }
+}
+
+/// Source texels covered by every destination pixel, with their coverage.
+fn box_taps(src: u32, dst: u32) -> Vec<Vec<(u32, f32)>> {
+    let scale = src as f32 / dst as f32;
+    (0..dst)
+        .map(|i| {
+            let (start, end) = (i as f32 * scale, (i + 1) as f32 * scale);
+            (start.floor() as u32..(end.ceil() as u32).min(src))
+                .map(|s| {
+                    let covered = end.min(s as f32 + 1.0) - start.max(s as f32);
+                    (s, covered / scale)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn downsample_box(pixels: &[[f32; 4]], (sw, sh): (u32, u32), (w, h): (u32, u32)) -> Vec<[f32; 4]> {
+    let (columns, rows) = (box_taps(sw, w), box_taps(sh, h));
+
+    let mut res = Vec::with_capacity((w * h) as usize);
+    for row in rows.iter() {
+        for column in columns.iter() {
+            let mut sum = [0.0; 4];
+            for &(sy, wy) in row.iter() {
+                for &(sx, wx) in column.iter() {
+                    let pixel = pixels[(sy * sw + sx) as usize];
+                    for c in 0..4 {
+                        sum[c] += pixel[c] * wx * wy;
+                    }
+                }
+            }
+            res.push(sum);
+        }
+    }
+    res
+}
+
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    for k in 1..16 {
+        let t = x / (2.0 * k as f32);
+        term *= t * t;
+        sum += term;
+    }
+    sum
+}
+
+/// `d` is in source pixels from the destination pixel center.
+fn kaiser_weight(d: f32) -> f32 {
+    let half = KAISER_TAPS as f32 / 2.0;
+    let t = d / half;
+    if t.abs() >= 1.0 {
+        return 0.0;
+    }
+
+    let x = d / 2.0;
+    let sinc = if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    };
+    let window = bessel_i0(KAISER_ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(KAISER_ALPHA);
+    sinc * window
+}
+
+fn kaiser_pass(
+    image: &[[f32; 4]],
+    (sw, sh): (u32, u32),
+    len: u32,
+    horizontal: bool,
+    edges: Edges,
+) -> Vec<[f32; 4]> {
+    let (w, h) = if horizontal { (len, sh) } else { (sw, len) };
+    let src_len = if horizontal { sw } else { sh };
+    let scale = src_len as f32 / len as f32;
+
+    let mut res = vec![[0.0; 4]; (w * h) as usize];
+    for y in 0..h {
+        for x in 0..w {
+            let (pos, other) = if horizontal { (x, y) } else { (y, x) };
+            let center = (pos as f32 + 0.5) * scale;
+            let first = (center - KAISER_TAPS as f32 / 2.0).floor() as i64;
+
+            let mut sum = [0.0; 4];
+            let mut total = 0.0;
+            for i in first..=first + KAISER_TAPS {
+                let weight = kaiser_weight((i as f32 + 0.5 - center) / scale * 2.0);
+                if weight == 0.0 {
+                    continue;
+                }
+                let s = wrap(i, src_len, edges);
+                let idx = if horizontal {
+                    other * sw + s
+                } else {
+                    s * sw + other
+                };
+                let pixel = image[idx as usize];
+                for c in 0..4 {
+                    sum[c] += pixel[c] * weight;
+                }
+                total += weight;
+            }
+
+            res[(y * w + x) as usize] = sum.map(|it| it / total);
+        }
+    }
+    res
+}
+
+fn downsample_kaiser(
+    pixels: &[[f32; 4]],
+    (sw, sh): (u32, u32),
+    (w, h): (u32, u32),
+    edges: Edges,
+) -> Vec<[f32; 4]> {
+    let pixels = kaiser_pass(pixels, (sw, sh), w, true, edges);
+    kaiser_pass(&pixels, (w, sh), h, false, edges)
+}
+
+/// `out/albedo.png` becomes `out/albedo_mip2.png` for the level 2.
+pub fn level_file_name(fname: &str, level: u32) -> String {
+    if level == 0 {
+        return fname.to_string();
+    }
+
+    suffixed_name(fname, &format!("_mip{level}"))
+}
+
+/// Formats without mips get a file per level.
+pub fn write_chain(
+    levels: &[RgbaImage],
+    fname: &str,
+    compression: Option<Compression>,
+    color_space: ColorSpace,
+) -> Result<()> {
+    if formats::is_container(fname) {
+        return formats::write(levels, fname, compression, color_space);
+    }
+    if compression.is_some() {
+        return Err(anyhow!("Compression needs a .dds or .ktx2 output: {fname}"));
+    }
+
+    for (level, image) in levels.iter().enumerate() {
+        write_image(image, &level_file_name(fname, level as u32))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    mips::{generate, level_count, level_file_name, level_size, Downsample},
+    pipeline::Edges,
+};
+
+#[test]
+fn test_level_count() {
+    assert_eq!(level_count((256, 256), None), 9);
+    assert_eq!(level_count((256, 64), None), 9);
+    assert_eq!(level_count((1, 1), None), 1);
+    assert_eq!(level_count((300, 200), None), 9);
+    assert_eq!(level_count((256, 256), Some(4)), 4);
+    assert_eq!(level_count((4, 4), Some(10)), 3);
+}
+
+#[test]
+fn test_level_size() {
+    assert_eq!(level_size((256, 64), 0), (256, 64));
+    assert_eq!(level_size((256, 64), 3), (32, 8));
+    assert_eq!(level_size((256, 64), 7), (2, 1));
+    assert_eq!(level_size((256, 64), 8), (1, 1));
+}
+
+#[test]
+fn test_box_averages_blocks() {
+    let image = RgbaImage::from_fn(4, 2, |x, _| {
+        if x % 2 == 0 {
+            Rgba([0, 0, 0, 255])
+        } else {
+            Rgba([200, 100, 50, 255])
+        }
+    });
+
+    let levels = generate(image, Downsample::Box, Edges::Wrap, None, false);
+
+    assert_eq!(levels.len(), 3);
+    assert_eq!(levels[1].dimensions(), (2, 1));
+    assert_eq!(levels[1].get_pixel(0, 0), &Rgba([100, 50, 25, 255]));
+    assert_eq!(levels[2].dimensions(), (1, 1));
+    assert_eq!(levels[2].get_pixel(0, 0), &Rgba([100, 50, 25, 255]));
+}
+
+#[test]
+fn test_box_covers_odd_sizes() {
+    // Only the last column is red and the last row green, both are lost
+    // when odd sizes drop the texels left over by the 2x2 blocks.
+    let image = RgbaImage::from_fn(5, 5, |x, y| {
+        Rgba([(x == 4) as u8 * 250, (y == 4) as u8 * 250, 0, 255])
+    });
+
+    let levels = generate(image, Downsample::Box, Edges::Clamp, None, false);
+
+    assert_eq!(levels[1].dimensions(), (2, 2));
+    // The right pixel covers half of the middle column and the last two.
+    assert_eq!(levels[1].get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+    assert_eq!(levels[1].get_pixel(1, 0), &Rgba([100, 0, 0, 255]));
+    assert_eq!(levels[1].get_pixel(0, 1), &Rgba([0, 100, 0, 255]));
+    assert_eq!(levels[1].get_pixel(1, 1), &Rgba([100, 100, 0, 255]));
+    // The average of the whole image is kept.
+    assert_eq!(levels[2].get_pixel(0, 0), &Rgba([50, 50, 0, 255]));
+}
+
+#[test]
+fn test_kaiser_keeps_constant_image() {
+    let image = RgbaImage::from_pixel(16, 8, Rgba([10, 128, 250, 77]));
+
+    for edges in [Edges::Wrap, Edges::Clamp] {
+        let levels = generate(image.clone(), Downsample::Kaiser, edges, Some(3), false);
+
+        assert_eq!(levels.len(), 3);
+        assert_eq!(levels[2].dimensions(), (4, 2));
+        assert!(levels[2].pixels().all(|it| it == &Rgba([10, 128, 250, 77])));
+    }
+}
+
+#[test]
+fn test_kaiser_edges() {
+    // A bright column on the left edge bleeds into the right side only when wrapping.
+    let image = RgbaImage::from_fn(16, 16, |x, _| {
+        if x == 0 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    });
+
+    let wrapped = generate(
+        image.clone(),
+        Downsample::Kaiser,
+        Edges::Wrap,
+        Some(2),
+        false,
+    );
+    let clamped = generate(image, Downsample::Kaiser, Edges::Clamp, Some(2), false);
+
+    assert!(wrapped[1].get_pixel(7, 4)[0] > 0);
+    assert_eq!(clamped[1].get_pixel(7, 4)[0], 0);
+}
+
+#[test]
+fn test_srgb_filtered_in_linear_space() {
+    let image = RgbaImage::from_fn(2, 2, |x, _| Rgba([x as u8 * 255, 0, 0, x as u8 * 255]));
+
+    let linear = generate(image.clone(), Downsample::Box, Edges::Wrap, None, false);
+    let srgb = generate(image, Downsample::Box, Edges::Wrap, None, true);
+
+    assert_eq!(linear[1].get_pixel(0, 0), &Rgba([128, 0, 0, 128]));
+    // Half the light is 0.735 in sRGB, alpha is always linear.
+    assert_eq!(srgb[1].get_pixel(0, 0), &Rgba([188, 0, 0, 128]));
+}
+
+#[test]
+fn test_level_file_name() {
+    assert_eq!(level_file_name("out/albedo.png", 0), "out/albedo.png");
+    assert_eq!(level_file_name("out/albedo.png", 2), "out/albedo_mip2.png");
+    assert_eq!(level_file_name("out.dir/albedo", 1), "out.dir/albedo_mip1");
+}
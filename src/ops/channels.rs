@@ -0,0 +1,38 @@
+use anyhow::{anyhow, Result};
+use image::{Rgba, Rgba32FImage};
+
+use crate::pipeline::Channel;
+
+pub fn unpack(image: &Rgba32FImage, channel: Channel) -> Rgba32FImage {
+    let idx = channel.index();
+    Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+        let v = image.get_pixel(x, y)[idx];
+        Rgba([v, v, v, 1.0])
+    })
+}
+
+pub enum PackSource<'a> {
+    Constant(f32),
+    Image(&'a Rgba32FImage, Channel),
+}
+
+pub fn pack(sources: [PackSource; 4], (w, h): (u32, u32)) -> Result<Rgba32FImage> {
+    for source in sources.iter() {
+        if let PackSource::Image(image, _) = source {
+            if image.dimensions() != (w, h) {
+                return Err(anyhow!(
+                    "Packed input is {}x{}, but the output is {w}x{h}",
+                    image.width(),
+                    image.height()
+                ));
+            }
+        }
+    }
+
+    Ok(Rgba32FImage::from_fn(w, h, |x, y| {
+        Rgba([0, 1, 2, 3].map(|idx| match &sources[idx] {
+            PackSource::Constant(v) => *v,
+            PackSource::Image(image, channel) => image.get_pixel(x, y)[channel.index()],
+        }))
+    }))
+}
@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use image::{Rgba, Rgba32FImage};
+
+use crate::pipeline::{BlendMode, Levels};
+
+fn map_colors<F: Fn(f32) -> f32>(image: &Rgba32FImage, f: F) -> Rgba32FImage {
+    let mut res = image.clone();
+    for pixel in res.pixels_mut() {
+        for v in pixel.0.iter_mut().take(3) {
+            *v = f(*v);
+        }
+    }
+    res
+}
+
+pub fn levels(image: &Rgba32FImage, levels: &Levels) -> Rgba32FImage {
+    let range = (levels.in_white - levels.in_black).max(f32::EPSILON);
+    let gamma = levels.gamma.max(f32::EPSILON);
+
+    map_colors(image, |v| {
+        let v = ((v - levels.in_black) / range)
+            .clamp(0.0, 1.0)
+            .powf(1.0 / gamma);
+        levels.out_black + v * (levels.out_white - levels.out_black)
+    })
+}
+
+pub fn invert(image: &Rgba32FImage) -> Rgba32FImage {
+    map_colors(image, |v| 1.0 - v)
+}
+
+fn blend_channel(mode: BlendMode, b: f32, l: f32) -> f32 {
+    match mode {
+        BlendMode::Normal => l,
+        BlendMode::Multiply => b * l,
+        BlendMode::Screen => 1.0 - (1.0 - b) * (1.0 - l),
+        BlendMode::Overlay if b < 0.5 => 2.0 * b * l,
+        BlendMode::Overlay => 1.0 - 2.0 * (1.0 - b) * (1.0 - l),
+        BlendMode::Add => (b + l).min(1.0),
+        BlendMode::Subtract => (b - l).max(0.0),
+        BlendMode::Darken => b.min(l),
+        BlendMode::Lighten => b.max(l),
+        BlendMode::Difference => (b - l).abs(),
+    }
+}
+
+pub fn blend(
+    base: &Rgba32FImage,
+    layer: &Rgba32FImage,
+    mode: BlendMode,
+    opacity: f32,
+) -> Result<Rgba32FImage> {
+    if base.dimensions() != layer.dimensions() {
+        return Err(anyhow!(
+            "Blended layer is {}x{}, but the base is {}x{}",
+            layer.width(),
+            layer.height(),
+            base.width(),
+            base.height()
+        ));
+    }
+
+    Ok(Rgba32FImage::from_fn(
+        base.width(),
+        base.height(),
+        |x, y| {
+            let b = base.get_pixel(x, y).0;
+            let l = layer.get_pixel(x, y).0;
+            let t = l[3] * opacity.clamp(0.0, 1.0);
+
+            let mut res = [0.0; 4];
+            for i in 0..3 {
+                res[i] = b[i] + (blend_channel(mode, b[i], l[i]) - b[i]) * t;
+            }
+            res[3] = t + b[3] * (1.0 - t);
+
+            Rgba(res)
+        },
+    ))
+}
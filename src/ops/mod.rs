@@ -0,0 +1,76 @@
+mod channels;
+mod color;
+mod normal;
+#[cfg(test)]
+pub mod ops_test;
+mod transform;
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use image::Rgba32FImage;
+
+use crate::pipeline::{ChannelSource, Op};
+
+use channels::PackSource;
+
+/// `size` is the size of the stage output.
+pub fn apply(
+    op: &Op,
+    size: (u32, u32),
+    images: &HashMap<String, Rgba32FImage>,
+) -> Result<Rgba32FImage> {
+    let image = |name: &String| {
+        images
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown resource in input: {}", name))
+    };
+
+    let res = match op {
+        Op::Resize { input, filter } => transform::resize(image(input)?, size, *filter),
+        Op::Crop { input, x, y } => transform::crop(image(input)?, (*x, *y), size)?,
+        Op::Flip { input, axis } => transform::flip(image(input)?, *axis),
+        Op::Rotate90 { input, turns } => transform::rotate90(image(input)?, *turns),
+        Op::Unpack { input, channel } => channels::unpack(image(input)?, *channel),
+        Op::Pack { r, g, b, a } => {
+            let source = |src: &Option<ChannelSource>, missing: f32| -> Result<PackSource> {
+                Ok(match src {
+                    None => PackSource::Constant(missing),
+                    Some(ChannelSource::Constant(v)) => PackSource::Constant(*v),
+                    Some(ChannelSource::Texture { name, channel }) => {
+                        PackSource::Image(image(name)?, *channel)
+                    }
+                })
+            };
+            let sources = [
+                source(r, 0.0)?,
+                source(g, 0.0)?,
+                source(b, 0.0)?,
+                source(a, 1.0)?,
+            ];
+            channels::pack(sources, size)?
+        }
+        Op::Levels(levels) => color::levels(image(&levels.input)?, levels),
+        Op::Invert { input } => color::invert(image(input)?),
+        Op::Normal(params) => normal::from_height(image(&params.input)?, params),
+        Op::Blend {
+            base,
+            layer,
+            mode,
+            opacity,
+        } => color::blend(image(base)?, image(layer)?, *mode, *opacity)?,
+    };
+
+    if res.dimensions() != size {
+        return Err(anyhow!(
+            "Operation `{}` produces {}x{} image, but the output is {}x{}",
+            op.name(),
+            res.width(),
+            res.height(),
+            size.0,
+            size.1
+        ));
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,51 @@
+use image::{Rgba, Rgba32FImage};
+
+use crate::pipeline::{Edges, Kernel, NormalConvention, NormalFromHeight};
+
+/// Normalized so a unit slope gives a unit gradient.
+fn weights(kernel: Kernel) -> [f32; 3] {
+    match kernel {
+        Kernel::Sobel => [1.0 / 8.0, 2.0 / 8.0, 1.0 / 8.0],
+        Kernel::Scharr => [3.0 / 32.0, 10.0 / 32.0, 3.0 / 32.0],
+        Kernel::CentralDifference => [0.0, 0.5, 0.0],
+    }
+}
+
+fn sample(image: &Rgba32FImage, x: i64, y: i64, edges: Edges, channel: usize) -> f32 {
+    let (w, h) = (image.width() as i64, image.height() as i64);
+    let (x, y) = match edges {
+        Edges::Wrap => (x.rem_euclid(w), y.rem_euclid(h)),
+        Edges::Clamp => (x.clamp(0, w - 1), y.clamp(0, h - 1)),
+    };
+    image.get_pixel(x as u32, y as u32)[channel]
+}
+
+pub fn from_height(image: &Rgba32FImage, params: &NormalFromHeight) -> Rgba32FImage {
+    let weights = weights(params.kernel);
+    let channel = params.channel.index();
+
+    Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+        let h =
+            |dx: i64, dy: i64| sample(image, x as i64 + dx, y as i64 + dy, params.edges, channel);
+
+        let mut gx = 0.0;
+        let mut gy = 0.0;
+        for (i, weight) in weights.iter().enumerate() {
+            let offset = i as i64 - 1;
+            gx += weight * (h(1, offset) - h(-1, offset));
+            gy += weight * (h(offset, 1) - h(offset, -1));
+        }
+
+        // The rows go down the image, so a height rising down the image
+        // tilts the normal up, which is +Y in OpenGL and -Y in DirectX.
+        let ny = match params.convention {
+            NormalConvention::OpenGl => gy,
+            NormalConvention::DirectX => -gy,
+        };
+        let n = [-gx * params.strength, ny * params.strength, 1.0];
+        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+
+        let [r, g, b] = n.map(|it| it / len * 0.5 + 0.5);
+        Rgba([r, g, b, 1.0])
+    })
+}
@@ -0,0 +1,463 @@
+use std::collections::HashMap;
+
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage};
+
+use crate::pipeline::{Channel, ChannelSource, Edges, Kernel, NormalConvention, Op, Stage};
+
+use super::apply;
+
+/// Red, green in the first row and blue, white in the second one.
+fn quad() -> RgbaImage {
+    let pixels = [
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 255, 255],
+    ];
+    RgbaImage::from_fn(2, 2, |x, y| Rgba(pixels[(y * 2 + x) as usize]))
+}
+
+fn image(pixels: &[[u8; 4]], w: u32) -> RgbaImage {
+    RgbaImage::from_fn(w, pixels.len() as u32 / w, |x, y| {
+        Rgba(pixels[(y * w + x) as usize])
+    })
+}
+
+fn parse(op: &str) -> Op {
+    let stage = format!(
+        r#"
+        op: {op}
+        output:
+          dst: memory
+          name: out
+          width: 2
+          height: 2
+    "#
+    );
+    let stage: Stage = serde_yaml::from_str(&stage).unwrap();
+    stage.op.unwrap()
+}
+
+fn to_f32(image: &RgbaImage) -> Rgba32FImage {
+    DynamicImage::ImageRgba8(image.clone()).to_rgba32f()
+}
+
+fn run(op: &str, size: (u32, u32), inputs: &[(&str, RgbaImage)]) -> RgbaImage {
+    let images: HashMap<String, Rgba32FImage> = inputs
+        .iter()
+        .map(|(name, image)| (name.to_string(), to_f32(image)))
+        .collect();
+    DynamicImage::ImageRgba32F(apply(&parse(op), size, &images).unwrap()).to_rgba8()
+}
+
+const R: [u8; 4] = [255, 0, 0, 255];
+const G: [u8; 4] = [0, 255, 0, 255];
+const B: [u8; 4] = [0, 0, 255, 255];
+const W: [u8; 4] = [255, 255, 255, 255];
+
+#[test]
+fn test_op_parse() {
+    assert_eq!(
+        parse("{ flip: { input: a, axis: vertical } }").inputs(),
+        vec!["a"]
+    );
+    assert_eq!(parse("{ rotate90: { input: a } }").name(), "rotate90");
+    assert_eq!(parse("{ pack: { r: a, a: b } }").inputs(), vec!["a", "b"]);
+    assert_eq!(
+        parse("{ blend: { base: a, layer: b, mode: multiply } }").inputs(),
+        vec!["a", "b"]
+    );
+}
+
+#[test]
+fn test_stage_without_op() {
+    let stage = r#"
+        shader: noise.frag
+        inputs: []
+        output:
+          dst: memory
+          name: out
+          width: 2
+          height: 2
+    "#;
+    let stage: Stage = serde_yaml::from_str(stage).unwrap();
+
+    assert_eq!(stage.op, None);
+    assert_eq!(stage.name(), "noise.frag");
+}
+
+#[test]
+fn test_op_flip() {
+    let res = run(
+        "{ flip: { input: a, axis: horizontal } }",
+        (2, 2),
+        &[("a", quad())],
+    );
+    assert_eq!(res, image(&[G, R, W, B], 2));
+
+    let res = run(
+        "{ flip: { input: a, axis: vertical } }",
+        (2, 2),
+        &[("a", quad())],
+    );
+    assert_eq!(res, image(&[B, W, R, G], 2));
+}
+
+#[test]
+fn test_op_rotate90() {
+    let res = run("{ rotate90: { input: a } }", (2, 2), &[("a", quad())]);
+    assert_eq!(res, image(&[B, R, W, G], 2));
+
+    let res = run(
+        "{ rotate90: { input: a, turns: 2 } }",
+        (2, 2),
+        &[("a", quad())],
+    );
+    assert_eq!(res, image(&[W, B, G, R], 2));
+
+    let res = run(
+        "{ rotate90: { input: a, turns: 4 } }",
+        (2, 2),
+        &[("a", quad())],
+    );
+    assert_eq!(res, quad());
+}
+
+#[test]
+fn test_op_crop_and_resize() {
+    let res = run(
+        "{ crop: { input: a, x: 1, y: 1 } }",
+        (1, 1),
+        &[("a", quad())],
+    );
+    assert_eq!(res, image(&[W], 1));
+
+    let res = run(
+        "{ resize: { input: a, filter: nearest } }",
+        (4, 4),
+        &[("a", quad())],
+    );
+    assert_eq!(res.get_pixel(1, 1), &Rgba(R));
+    assert_eq!(res.get_pixel(3, 0), &Rgba(G));
+    assert_eq!(res.get_pixel(0, 3), &Rgba(B));
+}
+
+#[test]
+fn test_op_crop_out_of_bounds() {
+    let images = HashMap::from([("a".to_string(), to_f32(&quad()))]);
+    let op = parse("{ crop: { input: a, x: 1, y: 0 } }");
+
+    assert!(apply(&op, (2, 2), &images).is_err());
+}
+
+#[test]
+fn test_op_crop_overflow() {
+    let images = HashMap::from([("a".to_string(), to_f32(&quad()))]);
+    let op = parse("{ crop: { input: a, x: 4294967295, y: 0 } }");
+
+    assert!(apply(&op, (2, 2), &images).is_err());
+}
+
+#[test]
+fn test_op_size_mismatch() {
+    let images = HashMap::from([("a".to_string(), to_f32(&quad()))]);
+    let op = parse("{ invert: { input: a } }");
+
+    let err = apply(&op, (4, 4), &images).unwrap_err();
+    assert!(err.to_string().contains("2x2"));
+}
+
+#[test]
+fn test_op_unpack_and_pack() {
+    let res = run(
+        "{ unpack: { input: a, channel: g } }",
+        (2, 2),
+        &[("a", quad())],
+    );
+    assert_eq!(
+        res,
+        image(
+            &[
+                [0, 0, 0, 255],
+                [255, 255, 255, 255],
+                [0, 0, 0, 255],
+                [255, 255, 255, 255]
+            ],
+            2
+        )
+    );
+
+    let res = run("{ pack: { g: a, b: a } }", (2, 2), &[("a", quad())]);
+    assert_eq!(
+        res,
+        image(
+            &[
+                [0, 255, 255, 255],
+                [0, 0, 0, 255],
+                [0, 0, 0, 255],
+                [0, 255, 255, 255]
+            ],
+            2
+        )
+    );
+}
+
+#[test]
+fn test_op_invert_and_levels() {
+    let gray = image(&[[0, 64, 128, 10], [255, 255, 255, 20]], 2);
+
+    let res = run("{ invert: { input: a } }", (2, 1), &[("a", gray.clone())]);
+    assert_eq!(res, image(&[[255, 191, 127, 10], [0, 0, 0, 20]], 2));
+
+    let res = run(
+        "{ levels: { input: a, in_white: 0.5, out_black: 0.2 } }",
+        (2, 1),
+        &[("a", gray)],
+    );
+    assert_eq!(res, image(&[[51, 153, 255, 10], [255, 255, 255, 20]], 2));
+}
+
+#[test]
+fn test_op_blend() {
+    let base = image(&[[128, 128, 128, 255], [200, 100, 0, 255]], 2);
+    let layer = image(&[[128, 255, 0, 255], [100, 100, 100, 0]], 2);
+    let inputs = [("a", base.clone()), ("b", layer)];
+
+    let res = run("{ blend: { base: a, layer: b } }", (2, 1), &inputs);
+    assert_eq!(res, image(&[[128, 255, 0, 255], [200, 100, 0, 255]], 2));
+
+    let res = run(
+        "{ blend: { base: a, layer: b, mode: multiply } }",
+        (2, 1),
+        &inputs,
+    );
+    assert_eq!(res.get_pixel(0, 0), &Rgba([64, 128, 0, 255]));
+
+    let res = run(
+        "{ blend: { base: a, layer: b, mode: screen, opacity: 0.0 } }",
+        (2, 1),
+        &inputs,
+    );
+    assert_eq!(res, base);
+}
+
+#[test]
+fn test_channel_source_parse() {
+    let op = parse("{ pack: { r: ao.r, g: out/rough.png.g, b: metal, a: 1.0 } }");
+
+    let Op::Pack { r, g, b, a } = &op else {
+        panic!("Expected pack, got {op:?}");
+    };
+    assert_eq!(
+        r,
+        &Some(ChannelSource::Texture {
+            name: "ao".to_string(),
+            channel: Channel::R
+        })
+    );
+    assert_eq!(
+        g,
+        &Some(ChannelSource::Texture {
+            name: "out/rough.png".to_string(),
+            channel: Channel::G
+        })
+    );
+    assert_eq!(
+        b,
+        &Some(ChannelSource::Texture {
+            name: "metal".to_string(),
+            channel: Channel::R
+        })
+    );
+    assert_eq!(a, &Some(ChannelSource::Constant(1.0)));
+    assert_eq!(op.inputs(), vec!["ao", "out/rough.png", "metal"]);
+}
+
+#[test]
+fn test_channel_source_roundtrip() {
+    let op = parse("{ pack: { r: ao.b, a: 0.5 } }");
+    let yaml = serde_yaml::to_string(&op).unwrap();
+
+    assert!(yaml.contains("ao.b"));
+    assert_eq!(serde_yaml::from_str::<Op>(&yaml).unwrap(), op);
+}
+
+#[test]
+fn test_op_pack_orm() {
+    let ao = image(&[[10, 0, 0, 255], [20, 0, 0, 255]], 2);
+    let rough = image(&[[0, 30, 0, 255], [0, 40, 0, 255]], 2);
+    let metal = image(&[[0, 50, 0, 255], [0, 60, 0, 255]], 2);
+
+    let res = run(
+        "{ pack: { r: ao.r, g: rough.g, b: metal.g, a: 1.0 } }",
+        (2, 1),
+        &[("ao", ao), ("rough", rough), ("metal", metal)],
+    );
+    assert_eq!(res, image(&[[10, 30, 50, 255], [20, 40, 60, 255]], 2));
+
+    let res = run("{ pack: { g: a.b, a: 0.0 } }", (2, 2), &[("a", quad())]);
+    assert_eq!(
+        res,
+        image(
+            &[[0, 0, 0, 0], [0, 0, 0, 0], [0, 255, 0, 0], [0, 255, 0, 0]],
+            2
+        )
+    );
+}
+
+fn ramp(w: u32, h: u32, f: impl Fn(u32, u32) -> u8) -> RgbaImage {
+    RgbaImage::from_fn(w, h, |x, y| {
+        let v = f(x, y);
+        Rgba([v, v, v, 255])
+    })
+}
+
+#[test]
+fn test_op_normal_parse() {
+    let op = parse("{ normal: { input: height } }");
+    let Op::Normal(params) = &op else {
+        panic!("Expected normal, got {op:?}");
+    };
+
+    assert_eq!(params.strength, 1.0);
+    assert_eq!(params.kernel, Kernel::Sobel);
+    assert_eq!(params.edges, Edges::Wrap);
+    assert_eq!(params.convention, NormalConvention::OpenGl);
+    assert_eq!(params.channel, Channel::R);
+    assert_eq!(op.inputs(), vec!["height"]);
+}
+
+#[test]
+fn test_op_normal_flat() {
+    let res = run(
+        "{ normal: { input: h, strength: 10 } }",
+        (4, 4),
+        &[("h", ramp(4, 4, |_, _| 100))],
+    );
+
+    for pixel in res.pixels() {
+        assert_eq!(pixel, &Rgba([128, 128, 255, 255]));
+    }
+}
+
+#[test]
+fn test_op_normal_slopes() {
+    let right = ramp(8, 8, |x, _| x as u8 * 16);
+    let res = run(
+        "{ normal: { input: h, strength: 8, edges: clamp } }",
+        (8, 8),
+        &[("h", right)],
+    );
+    let pixel = res.get_pixel(4, 4);
+    assert!(pixel[0] < 128);
+    assert_eq!(pixel[1], 128);
+
+    let down = ramp(8, 8, |_, y| y as u8 * 16);
+    let opengl = run(
+        "{ normal: { input: h, strength: 8, edges: clamp } }",
+        (8, 8),
+        &[("h", down.clone())],
+    );
+    let directx = run(
+        "{ normal: { input: h, strength: 8, edges: clamp, convention: directx } }",
+        (8, 8),
+        &[("h", down)],
+    );
+    assert!(opengl.get_pixel(4, 4)[1] > 128);
+    assert!(directx.get_pixel(4, 4)[1] < 128);
+    assert_eq!(opengl.get_pixel(4, 4)[1], 255 - directx.get_pixel(4, 4)[1]);
+}
+
+#[test]
+fn test_op_normal_kernels_agree_on_linear_slope() {
+    let slope = ramp(8, 8, |x, y| (x * 8 + y * 4) as u8);
+    let normals: Vec<RgbaImage> = ["sobel", "scharr", "central"]
+        .iter()
+        .map(|kernel| {
+            run(
+                &format!(
+                    "{{ normal: {{ input: h, strength: 4, kernel: {kernel}, edges: clamp }} }}"
+                ),
+                (8, 8),
+                &[("h", slope.clone())],
+            )
+        })
+        .collect();
+
+    assert_eq!(normals[0].get_pixel(3, 3), normals[1].get_pixel(3, 3));
+    assert_eq!(normals[0].get_pixel(3, 3), normals[2].get_pixel(3, 3));
+}
+
+#[test]
+fn test_op_normal_edges() {
+    let right = ramp(4, 4, |x, _| x as u8 * 60);
+
+    let wrap = run("{ normal: { input: h } }", (4, 4), &[("h", right.clone())]);
+    let clamp = run(
+        "{ normal: { input: h, edges: clamp } }",
+        (4, 4),
+        &[("h", right)],
+    );
+
+    // Wrapping sees the drop from the last column back to the first one.
+    assert!(wrap.get_pixel(0, 0)[0] > 128);
+    assert!(clamp.get_pixel(0, 0)[0] < 128);
+    assert_eq!(wrap.get_pixel(1, 1), clamp.get_pixel(1, 1));
+}
+
+#[test]
+fn test_ops_keep_precision() {
+    // Heights closer than an 8-bit step stay apart.
+    let height = Rgba32FImage::from_fn(3, 1, |x, _| {
+        let v = 0.5 + x as f32 * 0.001;
+        Rgba([v, v, v, 1.0])
+    });
+    let images = HashMap::from([("h".to_string(), height)]);
+
+    let inverted = apply(&parse("{ invert: { input: h } }"), (3, 1), &images).unwrap();
+    assert!((inverted.get_pixel(0, 0)[0] - 0.5).abs() < 1e-6);
+    assert!((inverted.get_pixel(1, 0)[0] - 0.499).abs() < 1e-6);
+
+    let op = "{ normal: { input: h, strength: 100 } }";
+    let normal = apply(&parse(op), (3, 1), &images).unwrap();
+    assert!(normal.get_pixel(1, 0)[0] < 0.49);
+}
+
+/// The expected outputs were computed independently of the operations.
+fn fixture(name: &str) -> RgbaImage {
+    let path = format!("{}/src/ops/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    image::open(path).unwrap().into_rgba8()
+}
+
+/// Allows the rounding of 8-bit values.
+fn assert_matches_reference(res: &RgbaImage, reference: &str) {
+    let expected = fixture(reference);
+    assert_eq!(res.dimensions(), expected.dimensions());
+    for ((x, y, a), b) in res.enumerate_pixels().zip(expected.pixels()) {
+        let close = a.0.iter().zip(b.0.iter()).all(|(a, b)| a.abs_diff(*b) <= 1);
+        assert!(close, "{reference} differs at ({x}, {y}): {a:?} != {b:?}");
+    }
+}
+
+#[test]
+fn test_op_normal_reference() {
+    let res = run(
+        "{ normal: { input: h, strength: 4 } }",
+        (8, 8),
+        &[("h", fixture("bump.png"))],
+    );
+    assert_matches_reference(&res, "bump_normal.png");
+}
+
+#[test]
+fn test_op_blend_reference() {
+    let res = run(
+        "{ blend: { base: a, layer: b, mode: overlay, opacity: 0.75 } }",
+        (8, 8),
+        &[
+            ("a", fixture("overlay_base.png")),
+            ("b", fixture("overlay_layer.png")),
+        ],
+    );
+    assert_matches_reference(&res, "overlay.png");
+}
@@ -0,0 +1,41 @@
+use anyhow::{anyhow, Result};
+use image::{imageops, imageops::FilterType, Rgba32FImage};
+
+use crate::pipeline::{Axis, Filter};
+
+pub fn resize(image: &Rgba32FImage, (w, h): (u32, u32), filter: Filter) -> Rgba32FImage {
+    let filter = match filter {
+        Filter::Nearest => FilterType::Nearest,
+        Filter::Linear => FilterType::Triangle,
+    };
+    imageops::resize(image, w, h, filter)
+}
+
+pub fn crop(image: &Rgba32FImage, (x, y): (u32, u32), (w, h): (u32, u32)) -> Result<Rgba32FImage> {
+    let fits =
+        |start: u32, len: u32, size: u32| start.checked_add(len).is_some_and(|it| it <= size);
+    if !fits(x, w, image.width()) || !fits(y, h, image.height()) {
+        return Err(anyhow!(
+            "Crop area {w}x{h} at ({x}, {y}) is out of the {}x{} input",
+            image.width(),
+            image.height()
+        ));
+    }
+    Ok(imageops::crop_imm(image, x, y, w, h).to_image())
+}
+
+pub fn flip(image: &Rgba32FImage, axis: Axis) -> Rgba32FImage {
+    match axis {
+        Axis::Horizontal => imageops::flip_horizontal(image),
+        Axis::Vertical => imageops::flip_vertical(image),
+    }
+}
+
+pub fn rotate90(image: &Rgba32FImage, turns: u32) -> Rgba32FImage {
+    match turns % 4 {
+        1 => imageops::rotate90(image),
+        2 => imageops::rotate180(image),
+        3 => imageops::rotate270(image),
+        _ => image.clone(),
+    }
+}
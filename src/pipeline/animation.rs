@@ -0,0 +1,56 @@
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Animation {
+    #[serde(default)]
+    pub start: u32,
+    /// Exclusive.
+    pub end: u32,
+    #[serde(default = "default_fps")]
+    pub fps: f32,
+}
+
+pub const DEFAULT_FPS: f32 = 30.0;
+
+fn default_fps() -> f32 {
+    DEFAULT_FPS
+}
+
+impl Animation {
+    pub fn frames(&self) -> Range<u32> {
+        self.start..self.end
+    }
+
+    pub fn time(&self, frame: u32) -> f32 {
+        frame as f32 / self.fps
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !self.fps.is_finite() || self.fps <= 0.0 {
+            return Err(anyhow!("Animation fps must be positive, got {}", self.fps));
+        }
+        if self.end <= self.start {
+            return Err(anyhow!(
+                "Animation ends at frame {} before rendering its start {}",
+                self.end,
+                self.start
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimatedOutput {
+    #[serde(rename = "sequence")]
+    Sequence,
+    #[serde(rename = "flipbook")]
+    Flipbook(u32),
+    #[serde(rename = "gif")]
+    Gif,
+    #[serde(rename = "apng")]
+    Apng,
+}
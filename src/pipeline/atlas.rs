@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ColorSpace, Compression};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Atlas {
+    /// A `.dds` or `.ktx2` container for arrays.
+    pub name: String,
+    /// A stage with variations adds all of them.
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub layout: AtlasLayout,
+    #[serde(default)]
+    pub columns: Option<u32>,
+    #[serde(default)]
+    pub padding: u32,
+    /// Pads with the edge pixels instead of transparent black.
+    #[serde(default)]
+    pub bleed: bool,
+    /// `<name>.json` by default.
+    #[serde(default)]
+    pub manifest: Option<String>,
+    #[serde(default)]
+    pub compression: Option<Compression>,
+    #[serde(default)]
+    pub color_space: ColorSpace,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AtlasLayout {
+    #[serde(rename = "grid")]
+    #[default]
+    Grid,
+    /// All the sources need the same size.
+    #[serde(rename = "array")]
+    Array,
+}
+
+impl Atlas {
+    pub fn manifest_name(&self) -> String {
+        match &self.manifest {
+            Some(manifest) => manifest.clone(),
+            None => std::path::Path::new(&self.name)
+                .with_extension("json")
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+}
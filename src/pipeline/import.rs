@@ -0,0 +1,273 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{Expr, Input, MeshShape, MipFilter, Output, Pipeline, Preview, Source, Stage};
+use crate::project_path::ProjectPath;
+
+/// Resources of the subgraph are renamed to `<name>.<resource>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Import {
+    pub name: String,
+    /// Relative to the importing file.
+    pub file: String,
+    #[serde(default)]
+    pub inputs: HashMap<String, String>,
+    #[serde(default)]
+    pub parameters: HashMap<String, Expr>,
+}
+
+impl Pipeline {
+    /// `stack` holds the files being imported, to detect cycles.
+    pub(super) fn resolve_imports(
+        &mut self,
+        project: &ProjectPath,
+        dir: &str,
+        stack: &mut Vec<String>,
+    ) -> Result<()> {
+        for import in std::mem::take(&mut self.imports) {
+            let file = join(dir, &import.file);
+            let path = project.path(&file);
+            if stack.contains(&path) {
+                return Err(anyhow!("Import `{}` imports itself: {file}", import.name));
+            }
+
+            let mut subgraph = Pipeline::parse_file(&path)
+                .with_context(|| format!("Failed to import `{}` from {file}", import.name))?;
+            stack.push(path.clone());
+            subgraph.resolve_imports(project, parent_dir(&file), stack)?;
+            stack.pop();
+
+            self.imported_files.push(path);
+            self.imported_files.append(&mut subgraph.imported_files);
+            // Paths of the subgraph are relative to this file, even the nested ones.
+            self.instantiate(&import, subgraph, parent_dir(&import.file))
+                .with_context(|| format!("Failed to import `{}` from {file}", import.name))?;
+        }
+        Ok(())
+    }
+
+    /// A missing import counts as just modified, so fixing `imports` reloads.
+    pub fn modified(&self, project: &ProjectPath) -> Result<SystemTime> {
+        let mut modified = fs::metadata(project.main())?.modified()?;
+        for file in self.imported_files.iter() {
+            let time = fs::metadata(file)
+                .and_then(|it| it.modified())
+                .unwrap_or_else(|_| SystemTime::now());
+            modified = modified.max(time);
+        }
+        Ok(modified)
+    }
+
+    fn instantiate(&mut self, import: &Import, mut subgraph: Pipeline, dir: &str) -> Result<()> {
+        if let Some(input) = subgraph
+            .inputs
+            .iter()
+            .find(|it| !import.inputs.contains_key(*it))
+        {
+            return Err(anyhow!("Input `{input}` is not bound"));
+        }
+        if let Some(input) = import
+            .inputs
+            .keys()
+            .find(|it| !subgraph.inputs.contains(it))
+        {
+            return Err(anyhow!("There is no input `{input}`"));
+        }
+        if let Some(name) = import
+            .parameters
+            .keys()
+            .find(|it| !subgraph.variables.contains_key(*it))
+        {
+            return Err(anyhow!("There is no parameter `{name}`"));
+        }
+        if let Some(output) = subgraph.outputs.iter().find(|it| {
+            !subgraph
+                .pipeline
+                .iter()
+                .any(|stage| &stage.output.name == *it)
+        }) {
+            return Err(anyhow!("No stage writes the output `{output}`"));
+        }
+
+        let mut namespace = Namespace {
+            name: &import.name,
+            dir,
+            inputs: &import.inputs,
+            outputs: HashMap::new(),
+        };
+        for stage in subgraph.pipeline.iter() {
+            let name = namespace.output(&stage.output);
+            namespace.outputs.insert(stage.output.name.clone(), name);
+        }
+
+        for (name, expr) in subgraph.variables {
+            let value = import.parameters.get(&name).cloned().unwrap_or(expr);
+            let name = namespace.resource(&name);
+            if self.variables.contains_key(&name) {
+                return Err(anyhow!("Variable `{name}` is already defined"));
+            }
+            self.imported_variables.insert(name.clone());
+            self.variables.insert(name, value);
+        }
+        for (name, parameter) in subgraph.parameters {
+            self.parameters.insert(namespace.resource(&name), parameter);
+        }
+
+        for stage in subgraph.pipeline.iter_mut() {
+            namespace.rename_stage(stage);
+        }
+        for atlas in subgraph.atlases.iter_mut() {
+            atlas.name = namespace.file_output(&atlas.name);
+            atlas.manifest = atlas.manifest.as_ref().map(|it| namespace.file_output(it));
+            for source in atlas.sources.iter_mut() {
+                *source = namespace.resource(source);
+            }
+        }
+
+        let bound: HashSet<&String> = import.inputs.values().collect();
+        let at = self
+            .pipeline
+            .iter()
+            .rposition(|stage| stage.output_names().iter().any(|it| bound.contains(it)))
+            .map_or(0, |it| it + 1);
+        let produced: HashSet<&String> = namespace.outputs.values().collect();
+        for (idx, stage) in self.pipeline[..at].iter().enumerate() {
+            if let Some(name) = stage
+                .input_names()
+                .into_iter()
+                .find(|it| produced.contains(it))
+            {
+                return Err(anyhow!(
+                    "`{name}` is used by the stage {idx} ({}) before the import runs",
+                    stage.name()
+                ));
+            }
+        }
+        self.pipeline.splice(at..at, subgraph.pipeline);
+        self.atlases.append(&mut subgraph.atlases);
+
+        Ok(())
+    }
+}
+
+struct Namespace<'a> {
+    name: &'a str,
+    dir: &'a str,
+    inputs: &'a HashMap<String, String>,
+    outputs: HashMap<String, String>,
+}
+
+impl Namespace<'_> {
+    fn resource(&self, name: &str) -> String {
+        match self.inputs.get(name).or(self.outputs.get(name)) {
+            Some(renamed) => renamed.clone(),
+            None => format!("{}.{name}", self.name),
+        }
+    }
+
+    fn file(&self, name: &str) -> String {
+        match self.inputs.get(name).or(self.outputs.get(name)) {
+            Some(renamed) => renamed.clone(),
+            None => join(self.dir, name),
+        }
+    }
+
+    /// `out/normal.png` becomes `out/<name>.normal.png`.
+    fn file_output(&self, fname: &str) -> String {
+        let dir_end = fname.rfind('/').map_or(0, |it| it + 1);
+        format!("{}{}.{}", &fname[..dir_end], self.name, &fname[dir_end..])
+    }
+
+    fn output(&self, output: &Output) -> String {
+        match output.dst {
+            Source::Memory => format!("{}.{}", self.name, output.name),
+            Source::File => self.file_output(&output.name),
+        }
+    }
+
+    fn rename_stage(&self, stage: &mut Stage) {
+        if stage.op.is_none() {
+            stage.shader = join(self.dir, &stage.shader);
+        }
+        if let Some(debug_shader) = stage.debug_shader.as_mut() {
+            *debug_shader = join(self.dir, debug_shader);
+        }
+        for input in stage.inputs.iter_mut() {
+            match input {
+                Input::File { name, .. } => *name = self.file(name),
+                Input::Memory { name, .. } => *name = self.resource(name),
+                Input::Expr { .. } => (),
+            }
+        }
+        if let Some(op) = stage.op.as_mut() {
+            for name in op.inputs_mut() {
+                *name = self.resource(name);
+            }
+        }
+        for binding in stage.buffers.iter_mut() {
+            binding.name = self.resource(&binding.name);
+        }
+
+        let output = &mut stage.output;
+        output.name = self.outputs[&output.name].clone();
+        if let Some(MipFilter::Shader(shader)) = output.mips.as_mut().map(|it| &mut it.filter) {
+            *shader = join(self.dir, shader);
+        }
+        match &mut output.preview {
+            Preview::Material(material) => {
+                for name in [
+                    &mut material.albedo,
+                    &mut material.normal,
+                    &mut material.roughness,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    *name = self.resource(name);
+                }
+            }
+            Preview::Mesh(mesh) => {
+                if let MeshShape::File(fname) = &mut mesh.mesh {
+                    *fname = join(self.dir, fname);
+                }
+                for name in [
+                    &mut mesh.albedo,
+                    &mut mesh.normal,
+                    &mut mesh.roughness,
+                    &mut mesh.displacement,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    *name = self.resource(name);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Resolves `.` and `..`.
+pub fn join(dir: &str, fname: &str) -> String {
+    let mut parts: Vec<&str> = vec![];
+    for part in dir.split('/').chain(fname.split('/')) {
+        match part {
+            "" | "." => (),
+            ".." if parts.last().is_some_and(|it| *it != "..") => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn parent_dir(fname: &str) -> &str {
+    fname.rfind('/').map_or("", |it| &fname[..it])
+}
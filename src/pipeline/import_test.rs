@@ -0,0 +1,189 @@
+use std::{collections::HashSet, path::Path, time::SystemTime};
+
+use super::{Expr, Input, Op, Pipeline};
+use crate::{project_path::ProjectPath, test_util::TempDir};
+
+const SUBGRAPH: &str = r#"
+inputs: [height]
+outputs: [normal, out/ao.png]
+variables:
+  strength: 1.0
+pipeline:
+  - op: { normal: { input: height } }
+    output: { dst: memory, name: normal, width: 4, height: 4 }
+  - shader: shaders/ao.glsl
+    inputs:
+      - { src: memory, name: normal, uniform: normal }
+      - { src: memory, name: strength, uniform: strength }
+      - { src: file, name: noise.png, uniform: noise }
+    output: { dst: file, name: out/ao.png, width: 4, height: 4 }
+"#;
+
+const MAIN: &str = r#"
+variables:
+  seed: 1.0
+pipeline:
+  - shader: rock.glsl
+    output: { dst: memory, name: rock_height, width: 4, height: 4 }
+  - shader: sand.glsl
+    output: { dst: memory, name: sand_height, width: 4, height: 4 }
+imports:
+  - name: rock
+    file: lib/maps.tw.yaml
+    inputs: { height: rock_height }
+    parameters: { strength: 2.0 }
+  - name: sand
+    file: lib/maps.tw.yaml
+    inputs: { height: sand_height }
+"#;
+
+/// Writes the files into the project directory.
+fn project(dir: &TempDir, files: &[(&str, &str)]) -> ProjectPath {
+    for (fname, src) in files {
+        let path = dir.file(fname);
+        std::fs::create_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+        std::fs::write(path, src).unwrap();
+    }
+    ProjectPath::new(&dir.path(), "main.tw.yaml")
+}
+
+fn outputs(pipeline: &Pipeline) -> Vec<&str> {
+    pipeline
+        .pipeline
+        .iter()
+        .map(|it| it.output.name.as_str())
+        .collect()
+}
+
+#[test]
+fn test_import_instances() {
+    let dir = TempDir::new("import");
+    let path = project(
+        &dir,
+        &[("main.tw.yaml", MAIN), ("lib/maps.tw.yaml", SUBGRAPH)],
+    );
+
+    let pipeline = Pipeline::load_from_file(&path).unwrap();
+
+    assert_eq!(
+        outputs(&pipeline),
+        vec![
+            "rock_height",
+            "rock.normal",
+            "out/rock.ao.png",
+            "sand_height",
+            "sand.normal",
+            "out/sand.ao.png",
+        ]
+    );
+    assert!(matches!(
+        &pipeline.pipeline[1].op,
+        Some(Op::Normal(normal)) if normal.input == "rock_height"
+    ));
+
+    let ao = &pipeline.pipeline[2];
+    assert_eq!(ao.shader, "lib/shaders/ao.glsl");
+    let names: Vec<&String> = ao.input_names();
+    assert_eq!(names, vec!["rock.normal", "rock.strength", "lib/noise.png"]);
+    assert!(matches!(&ao.inputs[1], Input::Memory { uniform, .. } if uniform == "strength"));
+
+    assert_eq!(pipeline.variables["rock.strength"], Expr::Float(2.0));
+    assert_eq!(pipeline.variables["sand.strength"], Expr::Float(1.0));
+    assert_eq!(
+        pipeline.imported_variables,
+        HashSet::from(["rock.strength".to_string(), "sand.strength".to_string()])
+    );
+    assert_eq!(pipeline.imported_files.len(), 2);
+    assert!(pipeline.imports.is_empty());
+}
+
+#[test]
+fn test_nested_import() {
+    let outer = r#"
+inputs: [height]
+variables: {}
+pipeline: []
+imports:
+  - name: maps
+    file: maps.tw.yaml
+    inputs: { height: height }
+"#;
+    let main = r#"
+variables: {}
+pipeline: []
+imports:
+  - name: rock
+    file: lib/outer.tw.yaml
+    inputs: { height: rock_height }
+"#;
+    let dir = TempDir::new("import");
+    let path = project(
+        &dir,
+        &[
+            ("main.tw.yaml", main),
+            ("lib/outer.tw.yaml", outer),
+            ("lib/maps.tw.yaml", SUBGRAPH),
+        ],
+    );
+
+    let pipeline = Pipeline::load_from_file(&path).unwrap();
+
+    assert_eq!(
+        outputs(&pipeline),
+        vec!["rock.maps.normal", "out/rock.maps.ao.png"]
+    );
+    assert_eq!(pipeline.pipeline[1].shader, "lib/shaders/ao.glsl");
+    assert!(pipeline.variables.contains_key("rock.maps.strength"));
+    assert_eq!(pipeline.imported_files.len(), 2);
+}
+
+#[test]
+fn test_import_errors() {
+    let load = |name: &str, main: &str| {
+        let dir = TempDir::new(name);
+        let path = project(
+            &dir,
+            &[("main.tw.yaml", main), ("lib/maps.tw.yaml", SUBGRAPH)],
+        );
+        format!("{:#}", Pipeline::load_from_file(&path).unwrap_err())
+    };
+
+    let unbound = "{ variables: {}, pipeline: [], imports: [{ name: a, file: lib/maps.tw.yaml }] }";
+    assert!(load("unbound", unbound).contains("Input `height` is not bound"));
+
+    let parameter = r#"{ variables: {}, pipeline: [], imports: [
+        { name: a, file: lib/maps.tw.yaml, inputs: { height: h }, parameters: { size: 1 } }
+    ] }"#;
+    assert!(load("parameter", parameter).contains("There is no parameter `size`"));
+
+    let early = r#"{ variables: {}, imports: [{ name: a, file: lib/maps.tw.yaml, inputs: { height: h } }],
+      pipeline: [
+        { op: { invert: { input: a.normal } }, output: { dst: memory, name: n, width: 4, height: 4 } },
+        { shader: h.glsl, output: { dst: memory, name: h, width: 4, height: 4 } },
+      ] }"#;
+    assert!(load("early", early)
+        .contains("`a.normal` is used by the stage 0 (invert) before the import runs"));
+
+    let cycle = "{ variables: {}, pipeline: [], imports: [{ name: a, file: main.tw.yaml }] }";
+    assert!(load("cycle", cycle).contains("imports itself"));
+    let dotted = "{ variables: {}, pipeline: [], imports: [{ name: a, file: ./main.tw.yaml }] }";
+    assert!(load("dotted", dotted).contains("imports itself"));
+    let parent =
+        "{ variables: {}, pipeline: [], imports: [{ name: a, file: lib/../main.tw.yaml }] }";
+    assert!(load("parent", parent).contains("imports itself"));
+}
+
+#[test]
+fn test_missing_import_reloads() {
+    let dir = TempDir::new("import");
+    let path = project(
+        &dir,
+        &[("main.tw.yaml", MAIN), ("lib/maps.tw.yaml", SUBGRAPH)],
+    );
+    let pipeline = Pipeline::load_from_file(&path).unwrap();
+    let loaded = SystemTime::now();
+    assert!(pipeline.modified(&path).unwrap() <= loaded);
+
+    std::fs::remove_file(path.path("lib/maps.tw.yaml")).unwrap();
+    assert!(pipeline.modified(&path).unwrap() >= loaded);
+}
@@ -1,16 +1,50 @@
 use serde::{Deserialize, Serialize};
 
+use super::{ColorSpace, Depth};
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "src")]
 pub enum Input {
+    /// Keeps the precision of the file unless `depth` is set.
     #[serde(rename = "file")]
-    File { name: String, uniform: String },
+    File {
+        name: String,
+        uniform: String,
+        #[serde(default)]
+        depth: Option<Depth>,
+        #[serde(default)]
+        color_space: ColorSpace,
+    },
     #[serde(rename = "memory")]
     Memory { name: String, uniform: String },
     #[serde(rename = "expr")]
     Expr { uniform: String, expr: Expr },
 }
 
+/// A unit per texture input and the first free one, samplers of different
+/// types cannot share a unit.
+pub fn texture_units(
+    inputs: &[Input],
+    is_texture: impl Fn(&str) -> bool,
+) -> (Vec<Option<u32>>, u32) {
+    let mut next = 0;
+    let units = inputs
+        .iter()
+        .map(|input| {
+            let reads_texture = match input {
+                Input::File { .. } => true,
+                Input::Memory { name, .. } => is_texture(name),
+                Input::Expr { .. } => false,
+            };
+            reads_texture.then(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect();
+    (units, next)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum Expr {
@@ -1,6 +1,6 @@
-use crate::pipeline::input::Expr;
+use crate::pipeline::{input::Expr, ColorSpace, Depth};
 
-use super::input::Input;
+use super::input::{texture_units, Input};
 
 #[test]
 fn test_input_parse_file() {
@@ -14,6 +14,29 @@ fn test_input_parse_file() {
     let expected = Input::File {
         name: "foo".into(),
         uniform: "bar".into(),
+        depth: None,
+        color_space: ColorSpace::Data,
+    };
+
+    assert_eq!(input, expected);
+}
+
+#[test]
+fn test_input_parse_file_depth() {
+    let input = r#"
+        src: file
+        name: height.png
+        uniform: height
+        depth: u16
+        color_space: srgb
+    "#;
+    let input: Input = serde_yaml::from_str(input).unwrap();
+
+    let expected = Input::File {
+        name: "height.png".into(),
+        uniform: "height".into(),
+        depth: Some(Depth::U16),
+        color_space: ColorSpace::Srgb,
     };
 
     assert_eq!(input, expected);
@@ -52,3 +75,25 @@ fn test_input_parse_expr() {
 
     assert_eq!(input, expected);
 }
+
+#[test]
+fn test_texture_units() {
+    let inputs: Vec<Input> = serde_yaml::from_str(
+        r#"
+        - { src: file, name: albedo.png, uniform: albedo }
+        - { src: memory, name: strength, uniform: strength }
+        - { src: memory, name: height, uniform: height }
+        - { src: expr, uniform: scale, expr: 2.0 }
+        "#,
+    )
+    .unwrap();
+
+    let (units, free_unit) = texture_units(&inputs, |name| name == "height");
+    assert_eq!(units, vec![Some(0), None, Some(1), None]);
+    // `tw_previous` does not share a unit with the inputs.
+    assert_eq!(free_unit, 2);
+
+    let (units, free_unit) = texture_units(&[], |_| true);
+    assert!(units.is_empty());
+    assert_eq!(free_unit, 0);
+}
@@ -1,38 +1,150 @@
+mod animation;
+mod atlas;
+mod import;
+#[cfg(test)]
+pub mod import_test;
 mod input;
 #[cfg(test)]
 pub mod input_test;
+mod op;
+mod parameter;
+#[cfg(test)]
+pub mod parameter_test;
 mod stage;
+#[cfg(test)]
+pub mod stage_test;
+mod sweep;
+#[cfg(test)]
+pub mod sweep_test;
 
-use std::{collections::HashMap, fs};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::Range,
+};
 
-pub use input::{Expr, Input};
+pub use animation::*;
+pub use atlas::*;
+pub use import::Import;
+pub use input::{texture_units, Expr, Input};
+pub use op::*;
+pub use parameter::*;
 
 use serde::{Deserialize, Serialize};
 pub use stage::*;
+pub use sweep::{Sweep, SweepValues};
 
-use crate::project_path::ProjectPath;
+use crate::{profiler::ProfileSettings, project_path::ProjectPath};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Pipeline {
     pub pipeline: Vec<Stage>,
     pub variables: HashMap<String, Expr>,
+    #[serde(default)]
+    pub parameters: HashMap<String, Parameter>,
+    #[serde(default)]
+    pub profile: Option<ProfileSettings>,
+    #[serde(default)]
+    pub atlases: Vec<Atlas>,
+    #[serde(default)]
+    pub animation: Option<Animation>,
+    #[serde(default)]
+    pub sweep: Option<Sweep>,
+    #[serde(default)]
+    pub imports: Vec<Import>,
+    /// Resources bound by the importing pipeline.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    #[serde(skip)]
+    pub imported_files: Vec<String>,
+    /// Set by the `parameters` of the imports, not saved to the project file.
+    #[serde(skip)]
+    pub imported_variables: HashSet<String>,
 }
 
 impl Pipeline {
     pub fn load_from_file(path: &ProjectPath) -> anyhow::Result<Self> {
-        let pipeline = fs::read_to_string(path.main())?;
+        let main = path.main();
+        let mut pipeline = Self::parse_file(&main)?;
+        pipeline.resolve_imports(path, "", &mut vec![main])?;
+        Ok(pipeline)
+    }
+
+    fn parse_file(path: &str) -> anyhow::Result<Self> {
+        let pipeline = fs::read_to_string(path)?;
         let pipeline = serde_yaml::from_str(&pipeline)?;
         Ok(pipeline)
     }
 
+    pub fn trace_file(&self) -> Option<&String> {
+        self.profile.as_ref().and_then(|it| it.trace.as_ref())
+    }
+
+    /// A single frame without an animation.
+    pub fn frames(&self) -> Range<u32> {
+        self.animation.as_ref().map_or(0..1, |it| it.frames())
+    }
+
+    pub fn time(&self, frame: u32) -> f32 {
+        self.animation.as_ref().map_or(0.0, |it| it.time(frame))
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.animation.as_ref().map_or(DEFAULT_FPS, |it| it.fps)
+    }
+
     pub fn number_of_previews(&self) -> usize {
-        let mut res = 0;
-        for stage in self.pipeline.iter() {
-            if let Preview::Disabled = stage.output.preview {
-                continue;
+        self.previews().count()
+    }
+
+    /// Includes the stages depending on the resources indirectly.
+    pub fn affected_stages(&self, changed: &HashSet<String>) -> Vec<usize> {
+        let mut dirty = changed.clone();
+        let mut res = vec![];
+
+        for (idx, stage) in self.pipeline.iter().enumerate() {
+            let affected = stage
+                .input_names()
+                .into_iter()
+                .any(|name| dirty.contains(name));
+
+            if affected {
+                dirty.extend(stage.output_names());
+                res.push(idx);
+            }
+        }
+
+        res
+    }
+
+    /// Variations of the sources are expanded.
+    pub fn atlas_sources(&self, atlas: &Atlas) -> Vec<String> {
+        let mut res = vec![];
+        for source in atlas.sources.iter() {
+            match self.pipeline.iter().find(|it| &it.output.name == source) {
+                Some(stage) => res.extend(stage.output_names()),
+                None => res.push(source.clone()),
             }
-            res += 1;
         }
         res
     }
+
+    pub fn affected_atlases<'a>(
+        &'a self,
+        outputs: &'a HashSet<String>,
+    ) -> impl Iterator<Item = &'a Atlas> {
+        self.atlases.iter().filter(|atlas| {
+            self.atlas_sources(atlas)
+                .iter()
+                .any(|it| outputs.contains(it))
+        })
+    }
+
+    pub fn previews(&self) -> impl Iterator<Item = &Stage> {
+        self.pipeline
+            .iter()
+            .filter(|stage| !matches!(stage.output.preview, Preview::Disabled))
+    }
 }
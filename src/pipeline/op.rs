@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+
+use super::Channel;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Op {
+    #[serde(rename = "resize")]
+    Resize {
+        input: String,
+        #[serde(default)]
+        filter: Filter,
+    },
+    #[serde(rename = "crop")]
+    Crop {
+        input: String,
+        #[serde(default)]
+        x: u32,
+        #[serde(default)]
+        y: u32,
+    },
+    #[serde(rename = "flip")]
+    Flip { input: String, axis: Axis },
+    /// Clockwise quarter turns.
+    #[serde(rename = "rotate90")]
+    Rotate90 {
+        input: String,
+        #[serde(default = "default_turns")]
+        turns: u32,
+    },
+    #[serde(rename = "unpack")]
+    Unpack { input: String, channel: Channel },
+    /// Missing color channels are black and missing alpha is opaque.
+    #[serde(rename = "pack")]
+    Pack {
+        #[serde(default)]
+        r: Option<ChannelSource>,
+        #[serde(default)]
+        g: Option<ChannelSource>,
+        #[serde(default)]
+        b: Option<ChannelSource>,
+        #[serde(default)]
+        a: Option<ChannelSource>,
+    },
+    #[serde(rename = "levels")]
+    Levels(Levels),
+    #[serde(rename = "invert")]
+    Invert { input: String },
+    #[serde(rename = "normal")]
+    Normal(NormalFromHeight),
+    #[serde(rename = "blend")]
+    Blend {
+        base: String,
+        layer: String,
+        #[serde(default)]
+        mode: BlendMode,
+        #[serde(default = "default_one")]
+        opacity: f32,
+    },
+}
+
+fn default_turns() -> u32 {
+    1
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+impl Op {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Resize { .. } => "resize",
+            Op::Crop { .. } => "crop",
+            Op::Flip { .. } => "flip",
+            Op::Rotate90 { .. } => "rotate90",
+            Op::Unpack { .. } => "unpack",
+            Op::Pack { .. } => "pack",
+            Op::Levels(_) => "levels",
+            Op::Invert { .. } => "invert",
+            Op::Normal(_) => "normal",
+            Op::Blend { .. } => "blend",
+        }
+    }
+
+    pub fn inputs(&self) -> Vec<&String> {
+        match self {
+            Op::Resize { input, .. }
+            | Op::Crop { input, .. }
+            | Op::Flip { input, .. }
+            | Op::Rotate90 { input, .. }
+            | Op::Unpack { input, .. }
+            | Op::Levels(Levels { input, .. })
+            | Op::Invert { input }
+            | Op::Normal(NormalFromHeight { input, .. }) => vec![input],
+            Op::Pack { r, g, b, a } => [r, g, b, a]
+                .into_iter()
+                .flatten()
+                .filter_map(|it| it.texture())
+                .collect(),
+            Op::Blend { base, layer, .. } => vec![base, layer],
+        }
+    }
+
+    pub fn inputs_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Op::Resize { input, .. }
+            | Op::Crop { input, .. }
+            | Op::Flip { input, .. }
+            | Op::Rotate90 { input, .. }
+            | Op::Unpack { input, .. }
+            | Op::Levels(Levels { input, .. })
+            | Op::Invert { input }
+            | Op::Normal(NormalFromHeight { input, .. }) => vec![input],
+            Op::Pack { r, g, b, a } => [r, g, b, a]
+                .into_iter()
+                .flatten()
+                .filter_map(|it| it.texture_mut())
+                .collect(),
+            Op::Blend { base, layer, .. } => vec![base, layer],
+        }
+    }
+}
+
+/// A constant, `name.r` or just `name` for the red channel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "RawChannelSource", into = "RawChannelSource")]
+pub enum ChannelSource {
+    Constant(f32),
+    Texture { name: String, channel: Channel },
+}
+
+impl ChannelSource {
+    pub fn texture(&self) -> Option<&String> {
+        match self {
+            ChannelSource::Constant(_) => None,
+            ChannelSource::Texture { name, .. } => Some(name),
+        }
+    }
+
+    pub fn texture_mut(&mut self) -> Option<&mut String> {
+        match self {
+            ChannelSource::Constant(_) => None,
+            ChannelSource::Texture { name, .. } => Some(name),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum RawChannelSource {
+    Constant(f32),
+    Texture(String),
+}
+
+impl TryFrom<RawChannelSource> for ChannelSource {
+    type Error = String;
+
+    fn try_from(raw: RawChannelSource) -> Result<Self, Self::Error> {
+        let src = match raw {
+            RawChannelSource::Constant(v) => return Ok(ChannelSource::Constant(v)),
+            RawChannelSource::Texture(src) => src,
+        };
+
+        let (name, channel) = match src.rsplit_once('.') {
+            Some((name, "r")) => (name, Channel::R),
+            Some((name, "g")) => (name, Channel::G),
+            Some((name, "b")) => (name, Channel::B),
+            Some((name, "a")) => (name, Channel::A),
+            _ => (src.as_str(), Channel::R),
+        };
+        if name.is_empty() {
+            return Err(format!("Missing texture name in channel source `{src}`"));
+        }
+
+        Ok(ChannelSource::Texture {
+            name: name.to_string(),
+            channel,
+        })
+    }
+}
+
+impl From<ChannelSource> for RawChannelSource {
+    fn from(src: ChannelSource) -> Self {
+        match src {
+            ChannelSource::Constant(v) => RawChannelSource::Constant(v),
+            ChannelSource::Texture { name, channel } => {
+                let channel = match channel {
+                    Channel::R => "r",
+                    Channel::G => "g",
+                    Channel::B => "b",
+                    Channel::A => "a",
+                };
+                RawChannelSource::Texture(format!("{name}.{channel}"))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Filter {
+    #[serde(rename = "nearest")]
+    Nearest,
+    #[serde(rename = "linear")]
+    #[default]
+    Linear,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    #[serde(rename = "horizontal")]
+    Horizontal,
+    #[serde(rename = "vertical")]
+    Vertical,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Levels {
+    pub input: String,
+    #[serde(default)]
+    pub in_black: f32,
+    #[serde(default = "default_one")]
+    pub in_white: f32,
+    #[serde(default = "default_one")]
+    pub gamma: f32,
+    #[serde(default)]
+    pub out_black: f32,
+    #[serde(default = "default_one")]
+    pub out_white: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NormalFromHeight {
+    pub input: String,
+    #[serde(default = "default_one")]
+    pub strength: f32,
+    #[serde(default)]
+    pub kernel: Kernel,
+    #[serde(default)]
+    pub edges: Edges,
+    #[serde(default)]
+    pub convention: NormalConvention,
+    #[serde(default = "default_height_channel")]
+    pub channel: Channel,
+}
+
+fn default_height_channel() -> Channel {
+    Channel::R
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Kernel {
+    #[serde(rename = "sobel")]
+    #[default]
+    Sobel,
+    #[serde(rename = "scharr")]
+    Scharr,
+    #[serde(rename = "central")]
+    CentralDifference,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Edges {
+    #[serde(rename = "wrap")]
+    #[default]
+    Wrap,
+    #[serde(rename = "clamp")]
+    Clamp,
+}
+
+/// Green is up in OpenGL and down in DirectX.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NormalConvention {
+    #[serde(rename = "opengl")]
+    #[default]
+    OpenGl,
+    #[serde(rename = "directx")]
+    DirectX,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[serde(rename = "normal")]
+    #[default]
+    Normal,
+    #[serde(rename = "multiply")]
+    Multiply,
+    #[serde(rename = "screen")]
+    Screen,
+    #[serde(rename = "overlay")]
+    Overlay,
+    #[serde(rename = "add")]
+    Add,
+    #[serde(rename = "subtract")]
+    Subtract,
+    #[serde(rename = "darken")]
+    Darken,
+    #[serde(rename = "lighten")]
+    Lighten,
+    #[serde(rename = "difference")]
+    Difference,
+}
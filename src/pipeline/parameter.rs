@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::Expr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Parameter {
+    #[serde(default)]
+    pub min: Option<f32>,
+    #[serde(default)]
+    pub max: Option<f32>,
+}
+
+impl Parameter {
+    /// Wide enough to hold `v` when it is not set.
+    pub fn range(&self, v: f32) -> (f32, f32) {
+        let min = self.min.unwrap_or(v.min(0.0) * 2.0);
+        let max = self.max.unwrap_or(v.max(1.0) * 2.0);
+        (min, max.max(min + f32::EPSILON))
+    }
+}
+
+impl Expr {
+    /// Colors are split into RGB.
+    pub fn components(&self) -> Option<Vec<f32>> {
+        match self {
+            Expr::Float(v) => Some(vec![*v]),
+            Expr::Vec2(v) => Some(v.to_vec()),
+            Expr::Vec3(v) => Some(v.to_vec()),
+            Expr::Vec4(v) => Some(v.to_vec()),
+            Expr::String(v) => parse_color(v).map(|it| it.to_vec()),
+        }
+    }
+
+    pub fn set_component(&mut self, idx: usize, value: f32) {
+        match self {
+            Expr::Float(v) => *v = value,
+            Expr::Vec2(v) => v[idx] = value,
+            Expr::Vec3(v) => v[idx] = value,
+            Expr::Vec4(v) => v[idx] = value,
+            Expr::String(v) => {
+                let Some(mut color) = parse_color(v) else {
+                    return;
+                };
+                color[idx] = value.clamp(0.0, 1.0);
+                let [r, g, b] = color.map(|it| (it * 255.0).round() as u8);
+                *v = format!("#{r:02x}{g:02x}{b:02x}{}", &v[7..]);
+            }
+        }
+    }
+
+    pub fn is_color(&self) -> bool {
+        matches!(self, Expr::String(v) if parse_color(v).is_some())
+    }
+
+    /// Colors are bound as `vec3`.
+    pub fn glsl_type(&self) -> &'static str {
+        match self {
+            Expr::Float(_) => "float",
+            Expr::Vec2(_) => "vec2",
+            Expr::Vec3(_) => "vec3",
+            Expr::Vec4(_) => "vec4",
+            Expr::String(_) if self.is_color() => "vec3",
+            Expr::String(_) => "string",
+        }
+    }
+
+    pub fn to_yaml(&self) -> String {
+        let list = |v: &[f32]| {
+            let items: Vec<String> = v.iter().map(|it| it.to_string()).collect();
+            format!("[{}]", items.join(", "))
+        };
+
+        match self {
+            Expr::Float(v) => v.to_string(),
+            Expr::Vec2(v) => list(v),
+            Expr::Vec3(v) => list(v),
+            Expr::Vec4(v) => list(v),
+            Expr::String(v) => format!("'{v}'"),
+        }
+    }
+}
+
+/// `#rrggbb`, possibly followed by more digits.
+pub fn parse_color(s: &str) -> Option<[f32; 3]> {
+    if !s.starts_with('#') || s.len() < 7 || !s.is_char_boundary(7) {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&s[i..i + 2], 16).ok();
+    Some([channel(1)? as f32, channel(3)? as f32, channel(5)? as f32].map(|it| it / 255.0))
+}
+
+/// Rewrites the values in place, keeping the comments. Only block mappings
+/// with a variable per line are supported.
+pub fn update_variables_yaml(src: &str, variables: &[(&String, &Expr)]) -> Result<String> {
+    let mut lines: Vec<String> = src.lines().map(|it| it.to_string()).collect();
+
+    let start = lines
+        .iter()
+        .position(|l| l.starts_with("variables:"))
+        .ok_or_else(|| anyhow!("No `variables` section in the project file"))?;
+    if !without_comment(&lines[start])
+        .trim_end()
+        .ends_with("variables:")
+    {
+        return Err(anyhow!(
+            "The `variables` section must have a variable per line to be saved"
+        ));
+    }
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| !l.is_empty() && !l.starts_with([' ', '#']))
+        .map_or(lines.len(), |it| it + start + 1);
+
+    // Lines of the section holding a value, comments and blank lines aside.
+    let entries: Vec<usize> = (start + 1..end)
+        .filter(|&i| !without_comment(&lines[i]).trim().is_empty())
+        .collect();
+    let indent = entries.first().map_or(0, |&i| indentation(&lines[i]));
+
+    for (name, expr) in variables {
+        let idx = entries.iter().position(|&i| {
+            let line = &lines[i];
+            indentation(line) == indent
+                && line[indent..]
+                    .strip_prefix(name.as_str())
+                    .is_some_and(|rest| rest.trim_start().starts_with(':'))
+        });
+        let multiline = idx
+            .and_then(|it| entries.get(it + 1))
+            .is_some_and(|&next| indentation(&lines[next]) > indent);
+        let (Some(idx), false) = (idx, multiline) else {
+            return Err(anyhow!("Variable `{name}` is not a single line"));
+        };
+
+        let line = &mut lines[entries[idx]];
+        let comment = match line[without_comment(line).len()..].trim_start() {
+            "" => String::new(),
+            comment => format!(" {comment}"),
+        };
+        *line = format!("{}{name}: {}{comment}", &line[..indent], expr.to_yaml());
+    }
+
+    let mut res = lines.join("\n");
+    if src.ends_with('\n') {
+        res.push('\n');
+    }
+
+    check_variables(&res, variables)?;
+    Ok(res)
+}
+
+fn check_variables(src: &str, variables: &[(&String, &Expr)]) -> Result<()> {
+    let value: serde_yaml::Value = serde_yaml::from_str(src)
+        .map_err(|e| anyhow!("Saving the variables would break the project file: {e}"))?;
+    for (name, expr) in variables {
+        let saved = value
+            .get("variables")
+            .and_then(|it| it.get(name.as_str()))
+            .and_then(|it| serde_yaml::from_value::<Expr>(it.clone()).ok());
+        if saved.as_ref() != Some(*expr) {
+            return Err(anyhow!("Variable `{name}` cannot be saved in this form"));
+        }
+    }
+    Ok(())
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// `#` starts a comment only outside quotes and after a space.
+fn without_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '#') if previous == ' ' => return line[..i].trim_end(),
+            _ => (),
+        }
+        previous = c;
+    }
+    line
+}
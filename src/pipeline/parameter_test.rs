@@ -0,0 +1,98 @@
+use super::{
+    input::Expr,
+    parameter::{parse_color, update_variables_yaml, Parameter},
+};
+
+#[test]
+fn test_parameter_range() {
+    let parameter = Parameter::default();
+    assert_eq!(parameter.range(0.5), (0.0, 2.0));
+    assert_eq!(parameter.range(5.0), (0.0, 10.0));
+    assert_eq!(parameter.range(-1.0), (-2.0, 2.0));
+
+    let parameter = Parameter {
+        min: Some(-1.0),
+        max: Some(3.0),
+    };
+    assert_eq!(parameter.range(100.0), (-1.0, 3.0));
+}
+
+#[test]
+fn test_parameter_color_components() {
+    assert_eq!(parse_color("#ff0000"), Some([1.0, 0.0, 0.0]));
+    assert_eq!(parse_color("ff0000"), None);
+
+    let mut color = Expr::String("#b11b00dc".into());
+    assert!(color.is_color());
+
+    color.set_component(1, 1.0);
+    assert_eq!(color, Expr::String("#b1ff00dc".into()));
+}
+
+#[test]
+fn test_parameter_vector_components() {
+    let mut v = Expr::Vec2([3.0, 8.0]);
+    v.set_component(1, 4.0);
+
+    assert_eq!(v.components(), Some(vec![3.0, 4.0]));
+    assert_eq!(v.to_yaml(), "[3, 4]");
+}
+
+#[test]
+fn test_parameter_update_yaml() {
+    let src =
+        "variables:\n  scale: [3, 8]\n  # comment\n  color: '#000000'\npipeline:\n  - scale: 1\n";
+    let scale = "scale".to_string();
+    let color = "color".to_string();
+
+    let res = update_variables_yaml(
+        src,
+        &[
+            (&scale, &Expr::Vec2([4.0, 8.5])),
+            (&color, &Expr::String("#ffffff".into())),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(
+        res,
+        "variables:\n  scale: [4, 8.5]\n  # comment\n  color: '#ffffff'\npipeline:\n  - scale: 1\n"
+    );
+
+    let missing = "missing".to_string();
+    assert!(update_variables_yaml(src, &[(&missing, &Expr::Float(1.0))]).is_err());
+}
+
+#[test]
+fn test_parameter_update_yaml_keeps_comments() {
+    let src = "variables: # tweakables\n  scale: 2 # in meters\n  color: '#000000' # ground\n";
+    let scale = "scale".to_string();
+    let color = "color".to_string();
+
+    let res = update_variables_yaml(
+        src,
+        &[
+            (&scale, &Expr::Float(0.5)),
+            (&color, &Expr::String("#ff8000".into())),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(
+        res,
+        "variables: # tweakables\n  scale: 0.5 # in meters\n  color: '#ff8000' # ground\n"
+    );
+}
+
+#[test]
+fn test_parameter_update_yaml_unsupported_shapes() {
+    let scale = "scale".to_string();
+    let update = |src: &str| update_variables_yaml(src, &[(&scale, &Expr::Vec2([1.0, 2.0]))]);
+
+    // Flow mappings and values over several lines are left untouched.
+    assert!(update("variables: { scale: [3, 8] }\npipeline: []\n").is_err());
+    assert!(update("variables:\n  scale:\n    - 3\n    - 8\npipeline: []\n").is_err());
+    // A key of a nested mapping is not a variable.
+    assert!(update("variables:\n  other:\n    scale: 1\npipeline: []\n").is_err());
+    assert!(update("pipeline: []\n").is_err());
+}
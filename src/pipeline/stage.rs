@@ -1,30 +1,163 @@
+use std::fmt::Display;
+
 use serde::{Deserialize, Serialize};
 
-use super::Input;
+use super::{AnimatedOutput, Edges, Input, Op};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Stage {
+    #[serde(default)]
+    pub kind: StageKind,
+    #[serde(default)]
     pub shader: String,
+    /// Built-in operation run instead of the shader.
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub op: Option<Op>,
+    #[serde(default)]
     pub inputs: Vec<Input>,
     pub output: Output,
     #[serde(default)]
     pub debug_shader: Option<String>,
     #[serde(default)]
     pub profiling: Profiling,
+    #[serde(default)]
+    pub iterations: Option<Iterations>,
+    #[serde(default)]
+    pub buffers: Vec<BufferBinding>,
+    /// Renders after the first one are stored under `<name>_v<variation>`.
+    #[serde(default)]
+    pub variations: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StageKind {
+    #[serde(rename = "fragment")]
+    #[default]
+    Fragment,
+    /// The output is bound to the `tw_output` image.
+    #[serde(rename = "compute")]
+    Compute,
+}
+
+/// Storage buffer, shared by the stages binding the same name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BufferBinding {
+    pub name: String,
+    pub binding: u32,
+    /// Number of 32-bit elements.
+    pub size: u32,
+    /// Fill the buffer with zeros before the stage is executed.
+    #[serde(default)]
+    pub clear: bool,
+}
+
+/// Number of ping-pong iterations of a stage.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Iterations {
+    Fixed(u32),
+    /// Iterates until no texel changes by more than `epsilon`.
+    Converge {
+        max: u32,
+        epsilon: f32,
+        #[serde(default = "default_check_every")]
+        check_every: u32,
+    },
+}
+
+fn default_check_every() -> u32 {
+    1
+}
+
+impl Iterations {
+    pub fn max(&self) -> u32 {
+        match self {
+            Iterations::Fixed(n) => *n,
+            Iterations::Converge { max, .. } => *max,
+        }
+    }
+
+    /// Epsilon of the convergence check after `iteration`, if there is one.
+    pub fn should_check(&self, iteration: u32) -> Option<f32> {
+        match self {
+            Iterations::Fixed(_) => None,
+            Iterations::Converge {
+                epsilon,
+                check_every,
+                ..
+            } => (iteration > 0 && (iteration + 1).is_multiple_of((*check_every).max(1)))
+                .then_some(*epsilon),
+        }
+    }
+}
+
+impl Stage {
+    /// The shader of the stage, or the name of its operation.
+    pub fn name(&self) -> &str {
+        match &self.op {
+            Some(op) => op.name(),
+            None => &self.shader,
+        }
+    }
+
+    pub fn variations(&self) -> u32 {
+        self.variations.unwrap_or(1).max(1)
+    }
+
+    /// Names of the textures written by the stage, one for every variation.
+    pub fn output_names(&self) -> Vec<String> {
+        (0..self.variations())
+            .map(|it| self.output.variation_name(it))
+            .collect()
+    }
+
+    pub fn input_names(&self) -> Vec<&String> {
+        let mut res: Vec<&String> = self
+            .inputs
+            .iter()
+            .filter_map(|input| match input {
+                Input::File { name, .. } | Input::Memory { name, .. } => Some(name),
+                Input::Expr { .. } => None,
+            })
+            .collect();
+        if let Some(op) = &self.op {
+            res.extend(op.inputs());
+        }
+        res
+    }
+}
+
+/// Error context naming the failing stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageError {
+    pub idx: usize,
+    pub shader: String,
+}
+
+impl StageError {
+    pub fn new(idx: usize, stage: &Stage) -> Self {
+        Self {
+            idx,
+            shader: stage.name().to_string(),
+        }
+    }
+}
+
+impl Display for StageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stage {} (`{}`) failed", self.idx, self.shader)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub enum Profiling {
     #[serde(rename = "disabled")]
+    #[default]
     Disabled,
     #[serde(rename = "clock")]
     Clock,
-}
-
-impl Default for Profiling {
-    fn default() -> Self {
-        Self::Disabled
-    }
+    #[serde(rename = "gpu")]
+    Gpu,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -33,8 +166,158 @@ pub struct Output {
     pub name: String,
     pub width: u32,
     pub height: u32,
-    #[serde(default)]
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
     pub preview: Preview,
+    #[serde(default)]
+    pub mips: Option<Mips>,
+    #[serde(default)]
+    pub compression: Option<Compression>,
+    /// `f32` for `.exr` files and `u8` otherwise by default.
+    #[serde(default)]
+    pub depth: Option<Depth>,
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    /// Only the first frame is written without it.
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub animation: Option<AnimatedOutput>,
+    #[serde(default)]
+    pub volume: Option<Volume>,
+    #[serde(default)]
+    pub cubemap: Option<CubemapLayout>,
+}
+
+impl Output {
+    /// `out/rock.png` becomes `out/rock_v2.png` for the variation 2.
+    pub fn variation_name(&self, variation: u32) -> String {
+        match variation {
+            0 => self.name.clone(),
+            _ => suffixed_name(&self.name, &format!("_v{variation}")),
+        }
+    }
+
+    pub fn depth(&self) -> Depth {
+        let exr = std::path::Path::new(&self.name)
+            .extension()
+            .is_some_and(|it| it.eq_ignore_ascii_case("exr"));
+        match self.depth {
+            Some(depth) => depth,
+            None if exr => Depth::F32,
+            None => Depth::U8,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Volume {
+    pub slices: u32,
+    /// Columns of the slice sheet, square by default.
+    #[serde(default)]
+    pub columns: Option<u32>,
+}
+
+/// Ignored by `.dds` and `.ktx2` outputs, which store cubemaps as they are.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CubemapLayout {
+    /// A file per face, `sky.png` becomes `sky_px.png`, `sky_nx.png`, ... `sky_nz.png`.
+    #[serde(rename = "faces")]
+    #[default]
+    Faces,
+    /// Horizontal cross, 4 faces wide and 3 high.
+    #[serde(rename = "cross")]
+    Cross,
+    /// Equirectangular panorama, 4 faces wide and 2 high.
+    #[serde(rename = "equirect")]
+    Equirect,
+}
+
+/// Inserts the suffix before the extension of the file name, if any.
+pub fn suffixed_name(fname: &str, suffix: &str) -> String {
+    let dir_end = fname.rfind('/').map_or(0, |it| it + 1);
+    match fname[dir_end..].rfind('.') {
+        Some(dot) => {
+            let dot = dir_end + dot;
+            format!("{}{}{}", &fname[..dot], suffix, &fname[dot..])
+        }
+        None => format!("{fname}{suffix}"),
+    }
+}
+
+/// `data` marks non-color maps like normals, never converted like `linear` ones.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    #[serde(rename = "srgb")]
+    Srgb,
+    #[serde(rename = "linear")]
+    Linear,
+    #[serde(rename = "data")]
+    #[default]
+    Data,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    #[serde(rename = "u8")]
+    U8,
+    /// For PNG and TIFF height maps.
+    #[serde(rename = "u16")]
+    U16,
+    /// Half floats, written as 32-bit floats.
+    #[serde(rename = "f16")]
+    F16,
+    #[serde(rename = "f32")]
+    F32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Mips {
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub filter: MipFilter,
+    /// Wrap for tileable textures.
+    #[serde(default)]
+    pub edges: Edges,
+    /// Down to 1x1 by default.
+    #[serde(default)]
+    pub levels: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub enum MipFilter {
+    #[serde(rename = "box")]
+    #[default]
+    Box,
+    #[serde(rename = "kaiser")]
+    Kaiser,
+    /// Samples the previous level from `tw_source`.
+    #[serde(rename = "shader")]
+    Shader(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// RGB with 1-bit alpha, 8 bytes per block.
+    #[serde(rename = "bc1")]
+    Bc1,
+    /// RGBA with interpolated alpha, 16 bytes per block.
+    #[serde(rename = "bc3")]
+    Bc3,
+    /// Single channel taken from red, 8 bytes per block.
+    #[serde(rename = "bc4")]
+    Bc4,
+    /// Two channels taken from red and green, for normal maps. 16 bytes per block.
+    #[serde(rename = "bc5")]
+    Bc5,
+    /// High quality RGBA, 16 bytes per block.
+    #[serde(rename = "bc7")]
+    Bc7,
+}
+
+impl Compression {
+    pub fn block_bytes(&self) -> usize {
+        match self {
+            Compression::Bc1 | Compression::Bc4 => 8,
+            Compression::Bc3 | Compression::Bc5 | Compression::Bc7 => 16,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -45,16 +328,136 @@ pub enum Source {
     Memory,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub enum Preview {
     #[serde(rename = "disabled")]
+    #[default]
     Disabled,
     #[serde(rename = "simple")]
     Simple,
+    #[serde(rename = "alpha")]
+    Alpha,
+    #[serde(rename = "channel")]
+    Channel(Channel),
+    #[serde(rename = "heightmap")]
+    Heightmap,
+    #[serde(rename = "normal")]
+    Normal,
+    #[serde(rename = "material")]
+    Material(MaterialPreview),
+    #[serde(rename = "mesh")]
+    Mesh(MeshPreview),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    #[serde(rename = "r")]
+    R,
+    #[serde(rename = "g")]
+    G,
+    #[serde(rename = "b")]
+    B,
+    #[serde(rename = "a")]
+    A,
+}
+
+impl Channel {
+    pub fn index(&self) -> usize {
+        match self {
+            Self::R => 0,
+            Self::G => 1,
+            Self::B => 2,
+            Self::A => 3,
+        }
+    }
+}
+
+/// The output of the stage is the `albedo` by default.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MaterialPreview {
+    #[serde(default)]
+    pub albedo: Option<String>,
+    #[serde(default)]
+    pub normal: Option<String>,
+    #[serde(default)]
+    pub roughness: Option<String>,
+    #[serde(default)]
+    pub shape: MaterialShape,
+}
+
+impl MaterialPreview {
+    pub fn textures(&self) -> impl Iterator<Item = &String> {
+        [&self.albedo, &self.normal, &self.roughness]
+            .into_iter()
+            .flatten()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaterialShape {
+    #[serde(rename = "plane")]
+    #[default]
+    Plane,
+    #[serde(rename = "sphere")]
+    Sphere,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MeshPreview {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub mesh: MeshShape,
+    #[serde(default)]
+    pub albedo: Option<String>,
+    #[serde(default)]
+    pub normal: Option<String>,
+    #[serde(default)]
+    pub roughness: Option<String>,
+    #[serde(default)]
+    pub displacement: Option<String>,
+    #[serde(default = "default_displacement_scale")]
+    pub displacement_scale: f32,
+}
+
+fn default_displacement_scale() -> f32 {
+    0.1
+}
+
+impl MeshPreview {
+    pub fn textures(&self) -> impl Iterator<Item = &String> {
+        [
+            &self.albedo,
+            &self.normal,
+            &self.roughness,
+            &self.displacement,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum MeshShape {
+    #[serde(rename = "plane")]
+    Plane,
+    #[serde(rename = "sphere")]
+    Sphere,
+    #[serde(rename = "cube")]
+    Cube,
+    #[serde(rename = "cylinder")]
+    Cylinder,
+    #[serde(rename = "file")]
+    File(String),
 }
 
-impl Default for Preview {
-    fn default() -> Self {
-        Self::Disabled
+impl MeshShape {
+    /// Name under which the mesh is stored in the context.
+    pub fn key(&self) -> String {
+        match self {
+            Self::Plane => "@plane".into(),
+            Self::Sphere => "@sphere".into(),
+            Self::Cube => "@cube".into(),
+            Self::Cylinder => "@cylinder".into(),
+            Self::File(name) => name.clone(),
+        }
     }
 }
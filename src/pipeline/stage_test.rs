@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+
+use super::{
+    stage::{
+        suffixed_name, BufferBinding, Channel, Depth, Iterations, MaterialPreview, MaterialShape,
+        MeshPreview, MeshShape, Output, Preview, Stage, StageKind,
+    },
+    AtlasLayout, Pipeline,
+};
+
+fn parse_preview(preview: &str) -> Preview {
+    let output = format!(
+        r#"
+        dst: memory
+        name: foo
+        width: 16
+        height: 16
+        preview: {preview}
+    "#
+    );
+    let output: Output = serde_yaml::from_str(&output).unwrap();
+    output.preview
+}
+
+#[test]
+fn test_preview_parse_default() {
+    let output = r#"
+        dst: memory
+        name: foo
+        width: 16
+        height: 16
+    "#;
+    let output: Output = serde_yaml::from_str(output).unwrap();
+
+    assert_eq!(output.preview, Preview::Disabled);
+}
+
+#[test]
+fn test_preview_parse_unit() {
+    assert_eq!(parse_preview("simple"), Preview::Simple);
+    assert_eq!(parse_preview("alpha"), Preview::Alpha);
+    assert_eq!(parse_preview("heightmap"), Preview::Heightmap);
+    assert_eq!(parse_preview("normal"), Preview::Normal);
+}
+
+#[test]
+fn test_preview_parse_channel() {
+    assert_eq!(
+        parse_preview("{ channel: g }"),
+        Preview::Channel(Channel::G)
+    );
+}
+
+#[test]
+fn test_preview_parse_material() {
+    let preview = parse_preview("{ material: { normal: bar, shape: sphere } }");
+
+    let expected = Preview::Material(MaterialPreview {
+        albedo: None,
+        normal: Some("bar".into()),
+        roughness: None,
+        shape: MaterialShape::Sphere,
+    });
+
+    assert_eq!(preview, expected);
+}
+
+#[test]
+fn test_preview_parse_mesh() {
+    let preview = parse_preview("{ mesh: { mesh: { file: bar.obj }, displacement: baz } }");
+
+    let expected = Preview::Mesh(MeshPreview {
+        mesh: MeshShape::File("bar.obj".into()),
+        albedo: None,
+        normal: None,
+        roughness: None,
+        displacement: Some("baz".into()),
+        displacement_scale: 0.1,
+    });
+
+    assert_eq!(preview, expected);
+
+    let preview = parse_preview("{ mesh: { mesh: cube } }");
+    let Preview::Mesh(preview) = preview else {
+        panic!("Expected mesh preview, got {preview:?}");
+    };
+
+    assert_eq!(preview.mesh, MeshShape::Cube);
+}
+
+#[test]
+fn test_pipeline_affected_stages() {
+    let pipeline = r#"
+        variables:
+          a: 1
+          b: 2
+        pipeline:
+          - shader: first.glsl
+            inputs:
+              - { src: memory, name: a, uniform: a }
+            output: { dst: memory, name: first, width: 1, height: 1 }
+          - shader: second.glsl
+            inputs:
+              - { src: memory, name: b, uniform: b }
+            output: { dst: memory, name: second, width: 1, height: 1 }
+          - shader: third.glsl
+            inputs:
+              - { src: memory, name: first, uniform: first }
+            output: { dst: file, name: third.png, width: 1, height: 1 }
+    "#;
+    let pipeline: Pipeline = serde_yaml::from_str(pipeline).unwrap();
+
+    let changed =
+        |names: &[&str]| -> HashSet<String> { names.iter().map(|it| it.to_string()).collect() };
+
+    assert_eq!(pipeline.affected_stages(&changed(&["a"])), vec![0, 2]);
+    assert_eq!(pipeline.affected_stages(&changed(&["b"])), vec![1]);
+    assert_eq!(pipeline.affected_stages(&changed(&[])), Vec::<usize>::new());
+}
+
+#[test]
+fn test_iterations_parse() {
+    let fixed: Iterations = serde_yaml::from_str("16").unwrap();
+    assert_eq!(fixed, Iterations::Fixed(16));
+    assert_eq!(fixed.max(), 16);
+    assert_eq!(fixed.should_check(5), None);
+
+    let converge: Iterations = serde_yaml::from_str("{ max: 100, epsilon: 0.01 }").unwrap();
+    assert_eq!(
+        converge,
+        Iterations::Converge {
+            max: 100,
+            epsilon: 0.01,
+            check_every: 1
+        }
+    );
+    assert_eq!(converge.should_check(0), None);
+    assert_eq!(converge.should_check(1), Some(0.01));
+}
+
+#[test]
+fn test_iterations_check_every() {
+    let converge: Iterations =
+        serde_yaml::from_str("{ max: 100, epsilon: 0.5, check_every: 4 }").unwrap();
+
+    let checked: Vec<u32> = (0..12)
+        .filter(|it| converge.should_check(*it).is_some())
+        .collect();
+    assert_eq!(checked, vec![3, 7, 11]);
+}
+
+#[test]
+fn test_stage_parse_compute() {
+    let stage = r#"
+        kind: compute
+        shader: histogram.comp
+        inputs: []
+        buffers:
+          - name: histogram
+            binding: 1
+            size: 256
+            clear: true
+        output:
+          dst: memory
+          name: foo
+          width: 16
+          height: 16
+    "#;
+    let stage: Stage = serde_yaml::from_str(stage).unwrap();
+
+    assert_eq!(stage.kind, StageKind::Compute);
+    assert_eq!(
+        stage.buffers,
+        vec![BufferBinding {
+            name: "histogram".to_string(),
+            binding: 1,
+            size: 256,
+            clear: true,
+        }]
+    );
+}
+
+#[test]
+fn test_stage_parse_default_kind() {
+    let stage = r#"
+        shader: noise.frag
+        inputs: []
+        output:
+          dst: memory
+          name: foo
+          width: 16
+          height: 16
+    "#;
+    let stage: Stage = serde_yaml::from_str(stage).unwrap();
+
+    assert_eq!(stage.kind, StageKind::Fragment);
+    assert!(stage.buffers.is_empty());
+}
+
+#[test]
+fn test_output_depth() {
+    let parse = |name: &str, depth: &str| {
+        let output = format!(
+            r#"
+            dst: file
+            name: {name}
+            width: 16
+            height: 16
+            {depth}
+        "#
+        );
+        serde_yaml::from_str::<Output>(&output).unwrap().depth()
+    };
+
+    assert_eq!(parse("out/albedo.png", ""), Depth::U8);
+    assert_eq!(parse("out/sky.exr", ""), Depth::F32);
+    assert_eq!(parse("out/sky.EXR", ""), Depth::F32);
+    assert_eq!(parse("out/height.png", "depth: u16"), Depth::U16);
+    assert_eq!(parse("out/sky.exr", "depth: f16"), Depth::F16);
+}
+
+#[test]
+fn test_variation_names() {
+    let stage = r#"
+        shader: rock.glsl
+        variations: 3
+        output: { dst: file, name: out/rock.png, width: 1, height: 1 }
+    "#;
+    let stage: Stage = serde_yaml::from_str(stage).unwrap();
+
+    assert_eq!(
+        stage.output_names(),
+        vec!["out/rock.png", "out/rock_v1.png", "out/rock_v2.png"]
+    );
+    assert_eq!(suffixed_name("rock", "_v1"), "rock_v1");
+    assert_eq!(suffixed_name("out.d/rock", "_v1"), "out.d/rock_v1");
+}
+
+#[test]
+fn test_atlas_sources() {
+    let pipeline = r#"
+        variables:
+          seed: 1
+        pipeline:
+          - shader: rock.glsl
+            variations: 2
+            inputs:
+              - { src: memory, name: seed, uniform: seed }
+            output: { dst: memory, name: rock, width: 1, height: 1 }
+          - shader: sand.glsl
+            output: { dst: memory, name: sand, width: 1, height: 1 }
+          - shader: grass.glsl
+            inputs:
+              - { src: memory, name: rock_v1, uniform: rock }
+            output: { dst: memory, name: grass, width: 1, height: 1 }
+        atlases:
+          - name: out/ground.png
+            sources: [rock, sand]
+          - name: out/ground.ktx2
+            sources: [grass]
+            layout: array
+    "#;
+    let pipeline: Pipeline = serde_yaml::from_str(pipeline).unwrap();
+
+    let ground = &pipeline.atlases[0];
+    assert_eq!(ground.layout, AtlasLayout::Grid);
+    assert_eq!(ground.manifest_name(), "out/ground.json");
+    assert_eq!(
+        pipeline.atlas_sources(ground),
+        vec!["rock", "rock_v1", "sand"]
+    );
+
+    // Variations other than the first one mark their readers as affected too.
+    let changed: HashSet<String> = ["seed".to_string()].into();
+    assert_eq!(pipeline.affected_stages(&changed), vec![0, 2]);
+
+    let outputs: HashSet<String> = ["rock_v1".to_string()].into();
+    let affected: Vec<&String> = pipeline
+        .affected_atlases(&outputs)
+        .map(|it| &it.name)
+        .collect();
+    assert_eq!(affected, vec!["out/ground.png"]);
+}
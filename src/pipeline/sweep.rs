@@ -0,0 +1,201 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::Expr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Sweep {
+    /// Combined in the order of their names.
+    #[serde(default)]
+    pub variables: BTreeMap<String, SweepValues>,
+    #[serde(default)]
+    pub samples: Option<u32>,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub sheet: Option<String>,
+    /// The first previewed output by default.
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default = "default_cell_size")]
+    pub cell_size: u32,
+    #[serde(default)]
+    pub columns: Option<u32>,
+}
+
+fn default_cell_size() -> u32 {
+    128
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SweepValues {
+    Range { from: f32, to: f32, steps: u32 },
+    List(Vec<Expr>),
+}
+
+impl SweepValues {
+    /// `0.1..0.9:5` for a range, `1,2,4` or `"#ff0000","#00ff00"` for a list.
+    pub fn parse(src: &str) -> Result<Self> {
+        if let Some((range, steps)) = src.split_once(':') {
+            if let Some((from, to)) = range.split_once("..") {
+                return Ok(Self::Range {
+                    from: from.trim().parse()?,
+                    to: to.trim().parse()?,
+                    steps: steps.trim().parse()?,
+                });
+            }
+        }
+        let values = serde_yaml::from_str(&format!("[{src}]"))
+            .map_err(|e| anyhow!("Invalid sweep values `{src}`: {e}"))?;
+        Ok(Self::List(values))
+    }
+
+    pub fn values(&self) -> Vec<Expr> {
+        match self {
+            Self::Range { from, to, steps } => {
+                let steps = (*steps).max(1);
+                (0..steps)
+                    .map(|i| match steps {
+                        1 => *from,
+                        _ => from + (to - from) * i as f32 / (steps - 1) as f32,
+                    })
+                    .map(Expr::Float)
+                    .collect()
+            }
+            Self::List(values) => values.clone(),
+        }
+    }
+}
+
+impl Sweep {
+    pub fn set_variable(&mut self, arg: &str) -> Result<()> {
+        let (name, values) = arg
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected `name=values` for a swept variable: {arg}"))?;
+        self.variables
+            .insert(name.trim().to_string(), SweepValues::parse(values)?);
+        Ok(())
+    }
+
+    /// `--sweep-var` and `--samples` imply `--sweep`.
+    pub fn from_args(
+        pipeline: Option<&Sweep>,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Option<Sweep>> {
+        let mut sweep = pipeline.cloned().unwrap_or_default();
+        let mut enabled = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("Expected a value after `{arg}`"))
+            };
+            match arg.as_str() {
+                "--sweep" => (),
+                "--sweep-var" => sweep.set_variable(&value()?)?,
+                "--samples" => {
+                    let samples = value()?;
+                    sweep.samples = Some(
+                        samples
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid number of samples: {samples}"))?,
+                    );
+                }
+                _ => return Err(anyhow!("Unknown argument `{arg}`")),
+            }
+            enabled = true;
+        }
+
+        if !enabled {
+            return Ok(None);
+        }
+        if sweep.variables.is_empty() {
+            return Err(anyhow!(
+                "Nothing to sweep, add a `sweep` to the pipeline or use `--sweep-var`"
+            ));
+        }
+        Ok(Some(sweep))
+    }
+
+    pub fn check(&self, variables: &HashMap<String, Expr>) -> Result<()> {
+        for (name, values) in self.variables.iter() {
+            let declared = variables
+                .get(name)
+                .ok_or_else(|| anyhow!("Swept variable `{name}` is not a pipeline variable"))?;
+            if let Some(value) = values
+                .values()
+                .into_iter()
+                .find(|it| it.glsl_type() != declared.glsl_type())
+            {
+                return Err(anyhow!(
+                    "Swept value {} of `{name}` is a {} but the variable is a {}",
+                    value.to_yaml(),
+                    value.glsl_type(),
+                    declared.glsl_type()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn combinations(&self) -> u64 {
+        self.variables
+            .values()
+            .map(|it| it.values().len() as u64)
+            .fold(1, u64::saturating_mul)
+    }
+
+    /// The last variable changes the fastest, samples keep that order.
+    pub fn variants(&self) -> Vec<Vec<(String, Expr)>> {
+        let values: Vec<(&String, Vec<Expr>)> = self
+            .variables
+            .iter()
+            .map(|(name, values)| (name, values.values()))
+            .collect();
+        let total = self.combinations();
+
+        let indices: Vec<u64> = match self.samples {
+            Some(samples) if (samples as u64) < total => {
+                let mut random = SplitMix64(self.seed);
+                let mut picked = HashSet::new();
+                while picked.len() < samples as usize {
+                    picked.insert(random.next() % total);
+                }
+                let mut picked: Vec<u64> = picked.into_iter().collect();
+                picked.sort();
+                picked
+            }
+            _ => (0..total).collect(),
+        };
+
+        indices
+            .into_iter()
+            .map(|mut idx| {
+                let mut variant = vec![];
+                for (name, values) in values.iter().rev() {
+                    let len = values.len() as u64;
+                    variant.push(((*name).clone(), values[(idx % len) as usize].clone()));
+                    idx /= len;
+                }
+                variant.reverse();
+                variant
+            })
+            .collect()
+    }
+}
+
+/// A seed always samples the same variants.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
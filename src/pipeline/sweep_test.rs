@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use super::{Expr, Pipeline, Sweep, SweepValues};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|it| it.to_string()).collect()
+}
+
+#[test]
+fn test_sweep_parse() {
+    let pipeline = r#"
+        variables:
+          roughness: 0.5
+          tint: '#ffffff'
+        pipeline: []
+        sweep:
+          variables:
+            roughness: { from: 0.0, to: 1.0, steps: 3 }
+            tint: ['#ff0000', '#00ff00']
+          samples: 4
+          sheet: out/sheet.png
+    "#;
+    let pipeline: Pipeline = serde_yaml::from_str(pipeline).unwrap();
+    let sweep = pipeline.sweep.unwrap();
+
+    assert_eq!(
+        sweep.variables["roughness"],
+        SweepValues::Range {
+            from: 0.0,
+            to: 1.0,
+            steps: 3
+        }
+    );
+    assert_eq!(
+        sweep.variables["tint"],
+        SweepValues::List(vec![
+            Expr::String("#ff0000".to_string()),
+            Expr::String("#00ff00".to_string())
+        ])
+    );
+    assert_eq!(sweep.samples, Some(4));
+    assert_eq!(sweep.sheet.as_deref(), Some("out/sheet.png"));
+    assert_eq!(sweep.cell_size, 128);
+}
+
+#[test]
+fn test_sweep_values() {
+    let range = SweepValues::parse("0.1..0.9:5").unwrap();
+    assert_eq!(
+        range,
+        SweepValues::Range {
+            from: 0.1,
+            to: 0.9,
+            steps: 5
+        }
+    );
+    let values: Vec<f32> = range
+        .values()
+        .into_iter()
+        .map(|it| match it {
+            Expr::Float(v) => v,
+            _ => panic!("Expected floats"),
+        })
+        .collect();
+    assert_eq!(values.len(), 5);
+    assert!((values[2] - 0.5).abs() < 1e-6);
+    assert!((values[4] - 0.9).abs() < 1e-6);
+
+    let single = SweepValues::Range {
+        from: 2.0,
+        to: 3.0,
+        steps: 1,
+    };
+    assert_eq!(single.values(), vec![Expr::Float(2.0)]);
+
+    assert_eq!(
+        SweepValues::parse("1, 2, [0.5, 1]").unwrap().values(),
+        vec![Expr::Float(1.0), Expr::Float(2.0), Expr::Vec2([0.5, 1.0])]
+    );
+    assert!(SweepValues::parse("[1, 2").is_err());
+}
+
+#[test]
+fn test_sweep_variants() {
+    let mut sweep = Sweep::default();
+    sweep.set_variable("b=1, 2, 3").unwrap();
+    sweep.set_variable("a=0..1:2").unwrap();
+    assert_eq!(sweep.combinations(), 6);
+
+    let variants = sweep.variants();
+    assert_eq!(variants.len(), 6);
+    assert_eq!(
+        variants[0],
+        vec![
+            ("a".to_string(), Expr::Float(0.0)),
+            ("b".to_string(), Expr::Float(1.0))
+        ]
+    );
+    assert_eq!(
+        variants[1],
+        vec![
+            ("a".to_string(), Expr::Float(0.0)),
+            ("b".to_string(), Expr::Float(2.0))
+        ]
+    );
+    assert_eq!(
+        variants[5],
+        vec![
+            ("a".to_string(), Expr::Float(1.0)),
+            ("b".to_string(), Expr::Float(3.0))
+        ]
+    );
+
+    assert!(sweep.set_variable("a").is_err());
+}
+
+#[test]
+fn test_sweep_samples() {
+    let mut sweep = Sweep::default();
+    sweep.set_variable("a=0..1:10").unwrap();
+    sweep.set_variable("b=0..1:10").unwrap();
+    sweep.samples = Some(7);
+
+    let variants = sweep.variants();
+    assert_eq!(variants.len(), 7);
+    assert_eq!(variants, sweep.variants());
+    let all = Sweep {
+        samples: None,
+        ..sweep.clone()
+    }
+    .variants();
+    let positions: Vec<usize> = variants
+        .iter()
+        .map(|it| all.iter().position(|v| v == it).unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|it| it[0] < it[1]));
+
+    sweep.seed = 1;
+    assert_ne!(variants, sweep.variants());
+
+    // More samples than combinations renders all of them.
+    sweep.samples = Some(1000);
+    assert_eq!(sweep.variants().len(), 100);
+}
+
+#[test]
+fn test_sweep_from_args() {
+    assert_eq!(Sweep::from_args(None, args(&[])).unwrap(), None);
+    assert!(Sweep::from_args(None, args(&["--verbose"])).is_err());
+    assert!(Sweep::from_args(None, args(&["--sweep-var", "a=1", "--smaples", "2"])).is_err());
+
+    let sweep = Sweep::from_args(None, args(&["--sweep-var", "a=1,2", "--samples", "1"]))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        sweep.variables["a"],
+        SweepValues::List(vec![Expr::Float(1.0), Expr::Float(2.0)])
+    );
+    assert_eq!(sweep.samples, Some(1));
+
+    let mut base = Sweep::default();
+    base.set_variable("a=1").unwrap();
+    base.sheet = Some("sheet.png".to_string());
+    let sweep = Sweep::from_args(Some(&base), args(&["--sweep", "--sweep-var", "b=2"]))
+        .unwrap()
+        .unwrap();
+    assert_eq!(sweep.variables.len(), 2);
+    assert_eq!(sweep.sheet.as_deref(), Some("sheet.png"));
+
+    assert!(Sweep::from_args(None, args(&["--sweep"])).is_err());
+    assert!(Sweep::from_args(Some(&base), args(&["--samples"])).is_err());
+    assert!(Sweep::from_args(Some(&base), args(&["--samples", "x"])).is_err());
+}
+
+#[test]
+fn test_sweep_check() {
+    let variables = HashMap::from([
+        ("roughness".to_string(), Expr::Float(0.5)),
+        ("tint".to_string(), Expr::String("#ffffff".to_string())),
+        ("offset".to_string(), Expr::Vec3([0.0, 0.0, 0.0])),
+    ]);
+
+    let mut sweep = Sweep::default();
+    sweep.set_variable("roughness=0..1:3").unwrap();
+    sweep.set_variable("tint=\"#ff0000\", [0, 1, 0]").unwrap();
+    assert!(sweep.check(&variables).is_ok());
+
+    sweep.set_variable("offset=0..1:3").unwrap();
+    assert!(sweep.check(&variables).is_err());
+    sweep.set_variable("offset=[0, 1, 0], [1, 0]").unwrap();
+    assert!(sweep.check(&variables).is_err());
+    sweep.set_variable("offset=[0, 1, 0], '#00ff00'").unwrap();
+    assert!(sweep.check(&variables).is_ok());
+
+    sweep.set_variable("missing=1").unwrap();
+    assert!(sweep.check(&variables).is_err());
+}
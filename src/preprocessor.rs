@@ -4,7 +4,10 @@ use anyhow::{Context, Result};
 
 use lazy_static::lazy_static;
 
+use crate::trace;
+
 pub fn preprocess_shader(name: &str, debug_shader: &Option<String>) -> Result<String> {
+    let _span = trace::span("preprocess", "preprocess_shader").arg("file", name);
     let mut p = Preproseccor::new();
 
     let src = p.load_sorce(name)?.to_owned();
@@ -0,0 +1,95 @@
+use std::f32::consts::PI;
+
+use super::View;
+
+/// Column-major.
+pub type Mat4 = [f32; 16];
+
+const FOV: f32 = PI / 4.0;
+const DISTANCE: f32 = 4.0;
+
+/// Driven by the pan and zoom of the view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+impl OrbitCamera {
+    pub fn from_view(view: &View) -> Self {
+        let limit = PI / 2.0 - 0.01;
+        Self {
+            yaw: view.pan[0] * 2.0 * PI,
+            pitch: (view.pan[1] * PI).clamp(-limit, limit),
+            distance: DISTANCE / view.zoom,
+        }
+    }
+
+    pub fn model_view(&self) -> Mat4 {
+        let rotate_y = rotation_y(self.yaw);
+        let rotate_x = rotation_x(self.pitch);
+        let translate = translation([0.0, 0.0, -self.distance]);
+
+        mul(&translate, &mul(&rotate_x, &rotate_y))
+    }
+
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        perspective(FOV, aspect, 0.01, 100.0)
+    }
+}
+
+pub fn identity() -> Mat4 {
+    let mut m = [0.0; 16];
+    for i in 0..4 {
+        m[i * 5] = 1.0;
+    }
+    m
+}
+
+pub fn mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut m = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            m[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    m
+}
+
+pub fn translation(v: [f32; 3]) -> Mat4 {
+    let mut m = identity();
+    m[12..15].copy_from_slice(&v);
+    m
+}
+
+pub fn rotation_x(angle: f32) -> Mat4 {
+    let (s, c) = angle.sin_cos();
+    let mut m = identity();
+    m[5] = c;
+    m[6] = s;
+    m[9] = -s;
+    m[10] = c;
+    m
+}
+
+pub fn rotation_y(angle: f32) -> Mat4 {
+    let (s, c) = angle.sin_cos();
+    let mut m = identity();
+    m[0] = c;
+    m[2] = -s;
+    m[8] = s;
+    m[10] = c;
+    m
+}
+
+pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let f = 1.0 / (fov / 2.0).tan();
+    let mut m = [0.0; 16];
+    m[0] = f / aspect;
+    m[5] = f;
+    m[10] = (far + near) / (near - far);
+    m[11] = -1.0;
+    m[14] = 2.0 * far * near / (near - far);
+    m
+}
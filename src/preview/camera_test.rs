@@ -0,0 +1,40 @@
+use super::camera::{identity, mul, perspective, rotation_y, translation, Mat4};
+
+fn transform(m: &Mat4, v: [f32; 4]) -> [f32; 4] {
+    [0, 1, 2, 3].map(|row| (0..4).map(|k| m[k * 4 + row] * v[k]).sum())
+}
+
+fn assert_close(a: [f32; 4], b: [f32; 4]) {
+    for i in 0..4 {
+        assert!((a[i] - b[i]).abs() < 1e-5, "{a:?} != {b:?}");
+    }
+}
+
+#[test]
+fn test_camera_mul_identity() {
+    let m = translation([1.0, 2.0, 3.0]);
+
+    assert_eq!(mul(&identity(), &m), m);
+    assert_eq!(mul(&m, &identity()), m);
+}
+
+#[test]
+fn test_camera_transform() {
+    let m = mul(
+        &translation([0.0, 0.0, -4.0]),
+        &rotation_y(std::f32::consts::FRAC_PI_2),
+    );
+
+    assert_close(transform(&m, [0.0, 0.0, 1.0, 1.0]), [1.0, 0.0, -4.0, 1.0]);
+}
+
+#[test]
+fn test_camera_perspective() {
+    let m = perspective(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 10.0);
+
+    let near = transform(&m, [0.0, 0.0, -1.0, 1.0]);
+    let far = transform(&m, [0.0, 0.0, -10.0, 1.0]);
+
+    assert!((near[2] / near[3] + 1.0).abs() < 1e-5);
+    assert!((far[2] / far[3] - 1.0).abs() < 1e-5);
+}
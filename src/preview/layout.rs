@@ -0,0 +1,101 @@
+use super::Rect;
+
+pub const PREVIEW_SIZE: u32 = 200;
+pub const MAX_COLUMNS: usize = 4;
+
+pub const LABEL_HEIGHT: u32 = 14;
+pub const PADDING: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub area: Rect,
+    pub label: Rect,
+    pub image: Rect,
+}
+
+impl Cell {
+    pub fn new(area: Rect, aspect: f32) -> Self {
+        let label = Rect {
+            x: area.x + PADDING as i32,
+            y: area.y,
+            w: area.w.saturating_sub(PADDING * 2),
+            h: LABEL_HEIGHT.min(area.h),
+        };
+
+        let rest = Rect {
+            x: area.x + PADDING as i32,
+            y: area.y + label.h as i32,
+            w: area.w.saturating_sub(PADDING * 2),
+            h: area.h.saturating_sub(label.h + PADDING),
+        };
+
+        Self {
+            area,
+            label,
+            image: fit(&rest, aspect),
+        }
+    }
+}
+
+/// Empty for an empty `area` or an aspect ratio which is not positive.
+pub fn fit(area: &Rect, aspect: f32) -> Rect {
+    if area.is_empty() || !(aspect.is_finite() && aspect > 0.0) {
+        return Rect {
+            x: area.x,
+            y: area.y,
+            w: 0,
+            h: 0,
+        };
+    }
+
+    let (mut w, mut h) = (area.w as f32, area.w as f32 / aspect);
+    if h > area.h as f32 {
+        (w, h) = (area.h as f32 * aspect, area.h as f32);
+    }
+
+    let (w, h) = (
+        (w.round() as u32).min(area.w),
+        (h.round() as u32).min(area.h),
+    );
+    Rect {
+        x: area.x + (area.w - w) as i32 / 2,
+        y: area.y + (area.h - h) as i32 / 2,
+        w,
+        h,
+    }
+}
+
+/// Picks the number of columns giving the largest images.
+pub fn grid(window: (u32, u32), aspects: &[f32]) -> Vec<Cell> {
+    let count = aspects.len() as u32;
+    if count == 0 {
+        return vec![];
+    }
+
+    let cell_size = |columns: u32| {
+        let rows = count.div_ceil(columns);
+        (window.0 / columns, window.1 / rows)
+    };
+    let image_size = |columns: u32| {
+        let (w, h) = cell_size(columns);
+        w.min(h.saturating_sub(LABEL_HEIGHT))
+    };
+
+    let columns = (1..=count).max_by_key(|c| image_size(*c)).unwrap_or(1);
+    let (w, h) = cell_size(columns);
+
+    aspects
+        .iter()
+        .enumerate()
+        .map(|(idx, aspect)| {
+            let (column, row) = (idx as u32 % columns, idx as u32 / columns);
+            let area = Rect {
+                x: (column * w) as i32,
+                y: (row * h) as i32,
+                w,
+                h,
+            };
+            Cell::new(area, *aspect)
+        })
+        .collect()
+}
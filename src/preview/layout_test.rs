@@ -0,0 +1,86 @@
+use super::{
+    layout::{fit, grid, LABEL_HEIGHT},
+    Rect,
+};
+
+#[test]
+fn test_layout_fit() {
+    let area = Rect {
+        x: 10,
+        y: 20,
+        w: 200,
+        h: 100,
+    };
+
+    let expected = Rect {
+        x: 60,
+        y: 20,
+        w: 100,
+        h: 100,
+    };
+    assert_eq!(fit(&area, 1.0), expected);
+
+    let expected = Rect {
+        x: 10,
+        y: 45,
+        w: 200,
+        h: 50,
+    };
+    assert_eq!(fit(&area, 4.0), expected);
+}
+
+#[test]
+fn test_layout_fit_empty() {
+    let flat = Rect {
+        x: 10,
+        y: 20,
+        w: 200,
+        h: 0,
+    };
+    assert_eq!(
+        fit(&flat, 1.0),
+        Rect {
+            x: 10,
+            y: 20,
+            w: 0,
+            h: 0
+        }
+    );
+
+    let area = Rect { h: 100, ..flat };
+    assert!(fit(&area, 0.0).is_empty());
+    assert!(fit(&area, f32::NAN).is_empty());
+    assert!(fit(&area, f32::INFINITY).is_empty());
+
+    // A minimized window gives empty images instead of NaN sizes.
+    assert!(grid((0, 0), &[1.0, 2.0]).iter().all(|c| c.image.is_empty()));
+    assert!(grid((400, LABEL_HEIGHT), &[1.0])
+        .iter()
+        .all(|c| c.image.is_empty()));
+}
+
+#[test]
+fn test_layout_grid_columns() {
+    let wide = grid((1000, 200 + LABEL_HEIGHT), &[1.0; 4]);
+    assert!(wide.iter().all(|c| c.area.y == 0));
+
+    let square = grid((400, 400 + LABEL_HEIGHT * 2), &[1.0; 4]);
+    assert_eq!(square[3].area.x, 200);
+    assert_eq!(square[3].area.y, (200 + LABEL_HEIGHT) as i32);
+}
+
+#[test]
+fn test_layout_grid_cells() {
+    let cells = grid((300, 300), &[2.0, 0.5, 1.0]);
+
+    assert_eq!(cells.len(), 3);
+    for cell in cells.iter() {
+        assert!(cell.label.y + cell.label.h as i32 <= cell.image.y);
+        assert!(cell.image.x >= cell.area.x && cell.image.y >= cell.area.y);
+        assert!(cell.image.x + cell.image.w as i32 <= cell.area.x + cell.area.w as i32);
+        assert!(cell.image.y + cell.image.h as i32 <= cell.area.y + cell.area.h as i32);
+    }
+
+    let image = cells[0].image;
+    assert_eq!(image.w, image.h * 2);
+}
@@ -0,0 +1,25 @@
+mod camera;
+#[cfg(test)]
+pub mod camera_test;
+mod layout;
+#[cfg(test)]
+pub mod layout_test;
+mod overlay;
+#[cfg(test)]
+pub mod overlay_test;
+mod panel;
+#[cfg(test)]
+pub mod panel_test;
+mod shading;
+mod text;
+mod view;
+#[cfg(test)]
+pub mod view_test;
+
+pub use camera::*;
+pub use layout::*;
+pub use overlay::*;
+pub use panel::*;
+pub use shading::Shading;
+pub use text::*;
+pub use view::*;
@@ -0,0 +1,40 @@
+use anyhow::Error;
+
+use crate::pipeline::StageError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorOverlay {
+    pub message: String,
+    pub stage: Option<usize>,
+}
+
+impl ErrorOverlay {
+    pub fn new(error: &Error) -> Self {
+        Self {
+            message: format!("Error: {error:?}"),
+            stage: error.downcast_ref::<StageError>().map(|it| it.idx),
+        }
+    }
+}
+
+pub fn wrap(text: &str, columns: usize, rows: usize) -> String {
+    let columns = columns.max(1);
+    let mut lines = vec![];
+
+    for line in text.lines() {
+        let chars: Vec<char> = line.replace('\t', "    ").chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        for chunk in chars.chunks(columns) {
+            lines.push(chunk.iter().collect());
+        }
+    }
+
+    if lines.len() > rows {
+        lines.truncate(rows.saturating_sub(1));
+        lines.push("...".into());
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,30 @@
+use anyhow::{anyhow, Context};
+
+use super::overlay::{wrap, ErrorOverlay};
+use crate::pipeline::StageError;
+
+#[test]
+fn test_overlay_wrap() {
+    assert_eq!(wrap("abcdef\n\nxy", 4, 10), "abcd\nef\n\nxy");
+    assert_eq!(wrap("a\nb\nc\nd", 4, 3), "a\nb\n...");
+}
+
+#[test]
+fn test_overlay_stage() {
+    let stage = StageError {
+        idx: 2,
+        shader: "foo.glsl".into(),
+    };
+    let error = Err::<(), _>(anyhow!("Failed to compile shader"))
+        .context(stage)
+        .context("Outer")
+        .unwrap_err();
+
+    let overlay = ErrorOverlay::new(&error);
+
+    assert_eq!(overlay.stage, Some(2));
+    assert!(overlay.message.contains("Stage 2 (`foo.glsl`) failed"));
+    assert!(overlay.message.contains("Failed to compile shader"));
+
+    assert_eq!(ErrorOverlay::new(&anyhow!("Oops")).stage, None);
+}
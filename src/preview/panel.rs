@@ -0,0 +1,423 @@
+use std::collections::{HashMap, HashSet};
+
+use sdl2::{event::Event, keyboard::Keycode, mouse::MouseButton};
+
+use crate::{
+    color_space::{hsv_to_rgb, rgb_to_hsv},
+    pipeline::{Expr, Parameter},
+};
+
+use super::{Rect, CHAR_WIDTH};
+
+pub const PANEL_WIDTH: u32 = 240;
+pub const ROW_HEIGHT: u32 = 16;
+const PICKER_HEIGHT: u32 = 4 * ROW_HEIGHT;
+const HUE_BAR_WIDTH: u32 = 12;
+const LABEL_COLUMNS: u32 = 12;
+const SCROLL_ROWS: i32 = 3;
+const COMPONENT_NAMES: [&str; 4] = ["x", "y", "z", "w"];
+const COLOR_NAMES: [&str; 3] = ["r", "g", "b"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Row {
+    Title {
+        name: String,
+        label: Rect,
+        swatch: Option<([f32; 3], Rect)>,
+        /// The parameters of imports are set by the importing file instead.
+        saved: bool,
+    },
+    Picker(Picker),
+    Slider(Slider),
+}
+
+impl Row {
+    /// Rows are kept only when their full height is in the panel.
+    fn bounds(&self) -> Rect {
+        match self {
+            Row::Title { label, .. } => *label,
+            Row::Picker(p) => Rect {
+                y: p.square.y - 2,
+                h: PICKER_HEIGHT,
+                ..p.square
+            },
+            Row::Slider(s) => s.label,
+        }
+    }
+
+    fn scroll(&mut self, dy: i32) {
+        let rects = match self {
+            Row::Title { label, swatch, .. } => {
+                vec![Some(label), swatch.as_mut().map(|it| &mut it.1)]
+            }
+            Row::Picker(p) => vec![Some(&mut p.square), Some(&mut p.hue_bar)],
+            Row::Slider(s) => vec![Some(&mut s.label), Some(&mut s.bar)],
+        };
+        for rect in rects.into_iter().flatten() {
+            rect.y -= dy;
+        }
+    }
+}
+
+/// Saturation/value square next to a hue bar, editing a color variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Picker {
+    pub name: String,
+    pub square: Rect,
+    pub hue_bar: Rect,
+    pub hsv: [f32; 3],
+}
+
+impl Picker {
+    /// Saturation grows to the right and value to the top of the square.
+    pub fn square_hsv(&self, x: i32, y: i32) -> [f32; 3] {
+        let s = (x - self.square.x) as f32 / self.square.w.saturating_sub(1).max(1) as f32;
+        let v = (y - self.square.y) as f32 / self.square.h.saturating_sub(1).max(1) as f32;
+        [self.hsv[0], s.clamp(0.0, 1.0), 1.0 - v.clamp(0.0, 1.0)]
+    }
+
+    /// Hue grows from the top to the bottom of the bar.
+    pub fn hue_at(&self, y: i32) -> f32 {
+        let h = (y - self.hue_bar.y) as f32 / self.hue_bar.h.saturating_sub(1).max(1) as f32;
+        h.clamp(0.0, 1.0)
+    }
+
+    pub fn square_marker(&self) -> (i32, i32) {
+        let [_, s, v] = self.hsv;
+        let w = self.square.w.saturating_sub(1) as f32;
+        let h = self.square.h.saturating_sub(1) as f32;
+        (
+            self.square.x + (s * w).round() as i32,
+            self.square.y + ((1.0 - v) * h).round() as i32,
+        )
+    }
+
+    pub fn hue_marker(&self) -> i32 {
+        let h = self.hue_bar.h.saturating_sub(1) as f32;
+        self.hue_bar.y + (self.hsv[0] * h).round() as i32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Drag {
+    Slider(String, usize),
+    Square(String),
+    Hue(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slider {
+    pub name: String,
+    pub component: usize,
+    pub text: String,
+    pub label: Rect,
+    pub bar: Rect,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Slider {
+    pub fn fraction(&self) -> f32 {
+        ((self.value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    }
+
+    pub fn value_at(&self, x: i32) -> f32 {
+        let t = ((x - self.bar.x) as f32 / self.bar.w as f32).clamp(0.0, 1.0);
+        self.min + t * (self.max - self.min)
+    }
+}
+
+/// Edits go directly to the variables of the context.
+#[derive(Debug, Default)]
+pub struct Panel {
+    pub visible: bool,
+    pub area: Rect,
+    pub rows: Vec<Row>,
+    /// Rows scrolled out above and below the area.
+    pub hidden: (usize, usize),
+    scroll: i32,
+    mouse: Option<(i32, i32)>,
+    dragging: Option<Drag>,
+    /// Kept for the colors without a hue of their own, like grays.
+    hues: HashMap<String, f32>,
+    save_requested: bool,
+}
+
+impl Panel {
+    pub fn width(&self) -> u32 {
+        if self.visible {
+            PANEL_WIDTH
+        } else {
+            0
+        }
+    }
+
+    pub fn take_save_request(&mut self) -> bool {
+        std::mem::take(&mut self.save_requested)
+    }
+
+    pub fn update_layout(
+        &mut self,
+        window: (u32, u32),
+        variables: &HashMap<String, Expr>,
+        parameters: &HashMap<String, Parameter>,
+        unsaved: &HashSet<String>,
+    ) {
+        self.area = Rect {
+            x: window.0.saturating_sub(PANEL_WIDTH) as i32,
+            y: 0,
+            w: PANEL_WIDTH.min(window.0),
+            h: window.1,
+        };
+        self.rows.clear();
+        self.hidden = (0, 0);
+
+        let mut names: Vec<&String> = variables.keys().collect();
+        names.sort();
+
+        let label_width = LABEL_COLUMNS * CHAR_WIDTH;
+        let area = self.area;
+        let mut y = area.y + ROW_HEIGHT as i32;
+        let row = |y: &mut i32| {
+            let rect = Rect {
+                x: area.x + 4,
+                y: *y,
+                w: area.w.saturating_sub(8),
+                h: ROW_HEIGHT,
+            };
+            *y += ROW_HEIGHT as i32;
+            rect
+        };
+
+        for name in names {
+            let expr = &variables[name];
+            let Some(components) = expr.components() else {
+                continue;
+            };
+            let parameter = parameters.get(name).cloned().unwrap_or_default();
+
+            let title = row(&mut y);
+            let swatch = match expr.is_color() {
+                true => {
+                    let color = [components[0], components[1], components[2]];
+                    let rect = Rect {
+                        x: title.x + title.w as i32 - ROW_HEIGHT as i32,
+                        y: title.y + 2,
+                        w: ROW_HEIGHT - 4,
+                        h: ROW_HEIGHT - 4,
+                    };
+                    Some((color, rect))
+                }
+                false => None,
+            };
+            self.rows.push(Row::Title {
+                name: name.clone(),
+                label: title,
+                swatch,
+                saved: !unsaved.contains(name),
+            });
+
+            if expr.is_color() {
+                let rect = Rect {
+                    h: PICKER_HEIGHT,
+                    ..row(&mut y)
+                };
+                y += (PICKER_HEIGHT - ROW_HEIGHT) as i32;
+                let bars_x = rect.x + label_width as i32;
+                let bars_w = rect.w.saturating_sub(label_width);
+
+                let mut hsv = rgb_to_hsv([components[0], components[1], components[2]]);
+                if hsv[1] == 0.0 || hsv[2] == 0.0 {
+                    hsv[0] = self.hues.get(name).copied().unwrap_or(hsv[0]);
+                }
+                self.rows.push(Row::Picker(Picker {
+                    name: name.clone(),
+                    square: Rect {
+                        x: bars_x,
+                        y: rect.y + 2,
+                        w: bars_w.saturating_sub(HUE_BAR_WIDTH + 4).max(1),
+                        h: PICKER_HEIGHT - 4,
+                    },
+                    hue_bar: Rect {
+                        x: bars_x + bars_w.saturating_sub(HUE_BAR_WIDTH) as i32,
+                        y: rect.y + 2,
+                        w: HUE_BAR_WIDTH.min(bars_w).max(1),
+                        h: PICKER_HEIGHT - 4,
+                    },
+                    hsv,
+                }));
+            }
+
+            for (component, value) in components.iter().enumerate() {
+                let rect = row(&mut y);
+                let (min, max) = match expr.is_color() {
+                    true => (0.0, 1.0),
+                    false => parameter.range(*value),
+                };
+                let prefix = match (expr.is_color(), components.len()) {
+                    (true, _) => COLOR_NAMES[component],
+                    (false, 1) => "",
+                    (false, _) => COMPONENT_NAMES[component],
+                };
+
+                self.rows.push(Row::Slider(Slider {
+                    name: name.clone(),
+                    component,
+                    text: format!("{prefix} {value:.3}"),
+                    label: Rect {
+                        w: label_width,
+                        ..rect
+                    },
+                    bar: Rect {
+                        x: rect.x + label_width as i32,
+                        y: rect.y + 3,
+                        w: rect.w.saturating_sub(label_width).max(1),
+                        h: ROW_HEIGHT - 6,
+                    },
+                    value: *value,
+                    min,
+                    max,
+                }));
+            }
+        }
+
+        // The header stays in place, the rows scroll below it.
+        let top = area.y + ROW_HEIGHT as i32;
+        let bottom = area.y + area.h as i32;
+        self.scroll = self.scroll.min(y - bottom).max(0);
+        let rows = std::mem::take(&mut self.rows);
+        for mut row in rows {
+            row.scroll(self.scroll);
+            let bounds = row.bounds();
+            if bounds.y < top {
+                self.hidden.0 += 1;
+            } else if bounds.y + bounds.h as i32 > bottom {
+                self.hidden.1 += 1;
+            } else {
+                self.rows.push(row);
+            }
+        }
+    }
+
+    /// Returns `false` when the event is for the previews instead.
+    pub fn handle_event(
+        &mut self,
+        event: &Event,
+        variables: &mut HashMap<String, Expr>,
+        changed: &mut HashSet<String>,
+    ) -> bool {
+        if let Event::MouseMotion { x, y, .. } = event {
+            self.mouse = Some((*x, *y));
+        }
+
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab),
+                ..
+            } => {
+                self.visible = !self.visible;
+                true
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::S),
+                ..
+            } if self.visible => {
+                self.save_requested = true;
+                true
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if self.visible && self.area.contains(*x, *y) => {
+                self.dragging = self.rows.iter().find_map(|row| match row {
+                    Row::Slider(s) if s.bar.contains(*x, *y) || s.label.contains(*x, *y) => {
+                        Some(Drag::Slider(s.name.clone(), s.component))
+                    }
+                    Row::Picker(p) if p.square.contains(*x, *y) => {
+                        Some(Drag::Square(p.name.clone()))
+                    }
+                    Row::Picker(p) if p.hue_bar.contains(*x, *y) => Some(Drag::Hue(p.name.clone())),
+                    _ => None,
+                });
+                self.drag_to(*x, *y, variables, changed);
+                true
+            }
+            Event::MouseButtonDown { x, y, .. } => self.visible && self.area.contains(*x, *y),
+            Event::MouseWheel { y, .. }
+                if self.visible && self.mouse.is_some_and(|(x, y)| self.area.contains(x, y)) =>
+            {
+                self.scroll -= y * SCROLL_ROWS * ROW_HEIGHT as i32;
+                true
+            }
+            Event::MouseMotion { x, y, .. } if self.dragging.is_some() => {
+                self.drag_to(*x, *y, variables, changed);
+                true
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } if self.dragging.is_some() => {
+                self.dragging = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn drag_to(
+        &mut self,
+        x: i32,
+        y: i32,
+        variables: &mut HashMap<String, Expr>,
+        changed: &mut HashSet<String>,
+    ) {
+        let Some(drag) = &self.dragging else {
+            return;
+        };
+        let name = match drag {
+            Drag::Slider(name, _) | Drag::Square(name) | Drag::Hue(name) => name,
+        };
+        let Some(expr) = variables.get_mut(name) else {
+            return;
+        };
+
+        // The picker keeps the new color until the next layout, so that
+        // a hue set for a gray is used by the following drags.
+        let picker = self.rows.iter_mut().find_map(|row| match row {
+            Row::Picker(p) if &p.name == name => Some(p),
+            _ => None,
+        });
+        let hsv = match (drag, picker) {
+            (Drag::Square(_), Some(picker)) => {
+                picker.hsv = picker.square_hsv(x, y);
+                picker.hsv
+            }
+            (Drag::Hue(_), Some(picker)) => {
+                picker.hsv[0] = picker.hue_at(y);
+                picker.hsv
+            }
+            (Drag::Slider(_, component), _) => {
+                let slider = self.rows.iter().find_map(|row| match row {
+                    Row::Slider(s) if &s.name == name && s.component == *component => Some(s),
+                    _ => None,
+                });
+                let Some(slider) = slider else {
+                    return;
+                };
+                expr.set_component(*component, slider.value_at(x));
+                changed.insert(name.clone());
+                return;
+            }
+            _ => return,
+        };
+
+        for (component, value) in hsv_to_rgb(hsv).into_iter().enumerate() {
+            expr.set_component(component, value);
+        }
+        self.hues.insert(name.clone(), hsv[0]);
+        changed.insert(name.clone());
+    }
+}
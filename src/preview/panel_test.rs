@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use sdl2::{
+    event::Event,
+    mouse::{MouseButton, MouseWheelDirection},
+};
+
+use super::panel::{Panel, Row, PANEL_WIDTH};
+use super::Rect;
+use crate::pipeline::{Expr, Parameter};
+
+fn parse_color_expr(expr: &Expr) -> [f32; 3] {
+    let components = expr.components().unwrap();
+    [components[0], components[1], components[2]]
+}
+
+fn mouse_down(x: i32, y: i32) -> Event {
+    Event::MouseButtonDown {
+        timestamp: 0,
+        window_id: 0,
+        which: 0,
+        mouse_btn: MouseButton::Left,
+        clicks: 1,
+        x,
+        y,
+    }
+}
+
+fn motion(x: i32, y: i32) -> Event {
+    Event::MouseMotion {
+        timestamp: 0,
+        window_id: 0,
+        which: 0,
+        mousestate: sdl2::mouse::MouseState::from_sdl_state(1),
+        x,
+        y,
+        xrel: 0,
+        yrel: 0,
+    }
+}
+
+fn wheel(y: i32) -> Event {
+    Event::MouseWheel {
+        timestamp: 0,
+        window_id: 0,
+        which: 0,
+        x: 0,
+        y,
+        direction: MouseWheelDirection::Normal,
+    }
+}
+
+fn variables() -> HashMap<String, Expr> {
+    let mut variables = HashMap::new();
+    variables.insert("scale".into(), Expr::Vec2([3.0, 8.0]));
+    variables.insert("color".into(), Expr::String("#000000".into()));
+    variables
+}
+
+#[test]
+fn test_panel_layout() {
+    let mut panel = Panel::default();
+    panel.visible = true;
+    panel.update_layout((800, 600), &variables(), &HashMap::new(), &HashSet::new());
+
+    assert_eq!(panel.area.x, 800 - PANEL_WIDTH as i32);
+    // One title and a slider per component, sorted by name,
+    // and a picker above the sliders of a color.
+    assert_eq!(panel.rows.len(), 1 + 1 + 3 + 1 + 2);
+    assert!(matches!(&panel.rows[0], Row::Title { name, swatch: Some(_), .. } if name == "color"));
+    assert!(matches!(&panel.rows[1], Row::Picker(picker) if picker.name == "color"));
+    assert!(matches!(&panel.rows[5], Row::Title { name, swatch: None, .. } if name == "scale"));
+}
+
+#[test]
+fn test_panel_unsaved_variables() {
+    let mut panel = Panel::default();
+    panel.visible = true;
+    let unsaved = HashSet::from(["scale".to_string()]);
+    panel.update_layout((800, 600), &variables(), &HashMap::new(), &unsaved);
+
+    assert!(matches!(&panel.rows[0], Row::Title { saved: true, .. }));
+    assert!(matches!(&panel.rows[5], Row::Title { saved: false, .. }));
+}
+
+#[test]
+fn test_panel_drag() {
+    let mut variables = variables();
+    let mut parameters = HashMap::new();
+    parameters.insert(
+        "scale".to_string(),
+        Parameter {
+            min: Some(0.0),
+            max: Some(10.0),
+        },
+    );
+
+    let mut panel = Panel::default();
+    panel.visible = true;
+    panel.update_layout((800, 600), &variables, &parameters, &HashSet::new());
+
+    let Row::Slider(slider) = &panel.rows[7] else {
+        panic!("Expected slider");
+    };
+    let x = slider.bar.x + slider.bar.w as i32 / 2;
+    let y = slider.bar.y + 1;
+
+    let mut changed = HashSet::new();
+    assert!(panel.handle_event(&mouse_down(x, y), &mut variables, &mut changed));
+
+    assert!(changed.contains("scale"));
+    let Expr::Vec2([a, b]) = variables["scale"] else {
+        panic!("Expected vec2");
+    };
+    assert_eq!(a, 3.0);
+    assert!((b - 5.0).abs() < 0.1);
+
+    assert!(!panel.handle_event(&mouse_down(10, 10), &mut variables, &mut changed));
+}
+
+#[test]
+fn test_panel_picker() {
+    let mut variables = variables();
+    let mut panel = Panel::default();
+    panel.visible = true;
+    panel.update_layout((800, 600), &variables, &HashMap::new(), &HashSet::new());
+
+    let Row::Picker(picker) = panel.rows[1].clone() else {
+        panic!("Expected picker");
+    };
+    let center = |rect: &Rect| (rect.x + rect.w as i32 / 2, rect.y + rect.h as i32 / 2);
+
+    // The hue of black is kept from the hue bar until the square gives it a color.
+    let mut changed = HashSet::new();
+    let (x, y) = center(&picker.hue_bar);
+    assert!(panel.handle_event(&mouse_down(x, y), &mut variables, &mut changed));
+    assert!(changed.contains("color"));
+    assert_eq!(variables["color"], Expr::String("#000000".into()));
+
+    // Only the height along the bar matters while dragging the hue.
+    let right = picker.square.x + picker.square.w as i32 - 1;
+    assert!(panel.handle_event(&motion(right, y), &mut variables, &mut changed));
+    assert_eq!(variables["color"], Expr::String("#000000".into()));
+
+    let up = Event::MouseButtonUp {
+        timestamp: 0,
+        window_id: 0,
+        which: 0,
+        mouse_btn: MouseButton::Left,
+        clicks: 1,
+        x,
+        y,
+    };
+    assert!(panel.handle_event(&up, &mut variables, &mut changed));
+    assert!(panel.handle_event(
+        &mouse_down(right, picker.square.y),
+        &mut variables,
+        &mut changed
+    ));
+    let [r, g, b] = parse_color_expr(&variables["color"]);
+    // Cyan, the hue at the middle of the bar.
+    assert_eq!(r, 0.0);
+    assert!(g > 0.9 && b > 0.9);
+
+    panel.update_layout((800, 600), &variables, &HashMap::new(), &HashSet::new());
+    let Row::Picker(picker) = &panel.rows[1] else {
+        panic!("Expected picker");
+    };
+    assert!((picker.hsv[0] - 0.5).abs() < 0.01);
+    assert_eq!(picker.square_marker(), (right, picker.square.y));
+}
+
+#[test]
+fn test_panel_scroll() {
+    let mut variables = variables();
+    let mut changed = HashSet::new();
+    let mut panel = Panel::default();
+    panel.visible = true;
+    let layout = |panel: &mut Panel, variables: &HashMap<String, Expr>| {
+        panel.update_layout((800, 100), variables, &HashMap::new(), &HashSet::new());
+    };
+
+    // Only the title and the picker of the color fit below the header.
+    layout(&mut panel, &variables);
+    assert_eq!(panel.rows.len(), 2);
+    assert_eq!(panel.hidden, (0, 6));
+    let bottom = panel.area.y + panel.area.h as i32;
+    assert!(panel.rows.iter().all(|row| match row {
+        Row::Title { label, .. } => label.y + label.h as i32 <= bottom,
+        Row::Picker(p) => p.square.y + p.square.h as i32 <= bottom,
+        Row::Slider(s) => s.label.y + s.label.h as i32 <= bottom,
+    }));
+
+    // The wheel zooms the previews unless the mouse is over the panel.
+    assert!(!panel.handle_event(&wheel(-1), &mut variables, &mut changed));
+    panel.handle_event(&motion(790, 50), &mut variables, &mut changed);
+    assert!(panel.handle_event(&wheel(-1), &mut variables, &mut changed));
+    layout(&mut panel, &variables);
+    assert_eq!(panel.hidden, (2, 3));
+    assert!(matches!(&panel.rows[0], Row::Slider(s) if s.name == "color" && s.component == 0));
+
+    // Scrolling stops at the last row.
+    for _ in 0..10 {
+        panel.handle_event(&wheel(-1), &mut variables, &mut changed);
+    }
+    layout(&mut panel, &variables);
+    assert_eq!(panel.hidden, (3, 0));
+    assert!(
+        matches!(panel.rows.last(), Some(Row::Slider(s)) if s.name == "scale" && s.component == 1)
+    );
+
+    panel.handle_event(&wheel(1), &mut variables, &mut changed);
+    layout(&mut panel, &variables);
+    assert!(panel.hidden.0 < 3);
+    panel.handle_event(&wheel(100), &mut variables, &mut changed);
+    layout(&mut panel, &variables);
+    assert_eq!(panel.hidden, (0, 6));
+    assert!(changed.is_empty());
+}
@@ -0,0 +1,39 @@
+use crate::pipeline::{Channel, Preview};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shading {
+    Color,
+    Channel(Channel),
+    Heightmap,
+    Normal,
+}
+
+impl Shading {
+    /// `None` for previews not drawn by the preview shader.
+    pub fn of(preview: &Preview) -> Option<Self> {
+        match preview {
+            Preview::Disabled | Preview::Material(_) | Preview::Mesh(_) => None,
+            Preview::Simple => Some(Self::Color),
+            Preview::Alpha => Some(Self::Channel(Channel::A)),
+            Preview::Channel(c) => Some(Self::Channel(*c)),
+            Preview::Heightmap => Some(Self::Heightmap),
+            Preview::Normal => Some(Self::Normal),
+        }
+    }
+
+    pub fn mode(&self) -> i32 {
+        match self {
+            Self::Color => 0,
+            Self::Channel(_) => 1,
+            Self::Heightmap => 2,
+            Self::Normal => 3,
+        }
+    }
+
+    pub fn channel(&self) -> i32 {
+        match self {
+            Self::Channel(c) => c.index() as i32,
+            _ => 0,
+        }
+    }
+}
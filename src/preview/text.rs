@@ -0,0 +1,94 @@
+use std::{collections::HashMap, convert::Infallible};
+
+use anyhow::Result;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb888,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use image::{Rgba, RgbaImage};
+
+use crate::texture::Texture;
+
+pub const CHAR_WIDTH: u32 = 6;
+pub const LINE_HEIGHT: u32 = 10;
+
+const MAX_CACHED: usize = 256;
+
+pub fn render_text(text: &str, color: [u8; 4], background: [u8; 4]) -> RgbaImage {
+    let columns = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+    let rows = text.lines().count();
+
+    let mut canvas = Canvas {
+        image: RgbaImage::from_pixel(
+            (columns as u32 * CHAR_WIDTH).max(1),
+            (rows as u32 * LINE_HEIGHT).max(1),
+            Rgba(background),
+        ),
+        alpha: color[3],
+    };
+
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb888::new(color[0], color[1], color[2]));
+    for (idx, line) in text.lines().enumerate() {
+        let position = Point::new(0, idx as i32 * LINE_HEIGHT as i32);
+        // Drawing into an image can not fail.
+        let _ = Text::with_baseline(line, position, style, Baseline::Top).draw(&mut canvas);
+    }
+
+    canvas.image
+}
+
+struct Canvas {
+    image: RgbaImage,
+    alpha: u8,
+}
+
+impl OriginDimensions for Canvas {
+    fn size(&self) -> Size {
+        Size::new(self.image.width(), self.image.height())
+    }
+}
+
+impl DrawTarget for Canvas {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x < 0 || point.y < 0 {
+                continue;
+            }
+            let (x, y) = (point.x as u32, point.y as u32);
+            if x < self.image.width() && y < self.image.height() {
+                let pixel = Rgba([color.r(), color.g(), color.b(), self.alpha]);
+                self.image.put_pixel(x, y, pixel);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TextCache {
+    textures: HashMap<(String, [u8; 4], [u8; 4]), Texture>,
+}
+
+impl TextCache {
+    pub fn get(&mut self, text: &str, color: [u8; 4], background: [u8; 4]) -> Result<&Texture> {
+        let key = (text.to_string(), color, background);
+
+        if !self.textures.contains_key(&key) {
+            if self.textures.len() >= MAX_CACHED {
+                self.textures.clear();
+            }
+            let texture = Texture::from_image(render_text(text, color, background))?;
+            self.textures.insert(key.clone(), texture);
+        }
+
+        Ok(&self.textures[&key])
+    }
+}
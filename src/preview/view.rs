@@ -0,0 +1,229 @@
+use sdl2::{event::Event, event::WindowEvent, keyboard::Keycode, mouse::MouseButton};
+
+use crate::pipeline::Channel;
+
+use super::{
+    layout::{self, Cell},
+    ErrorOverlay,
+};
+
+const ZOOM_STEP: f32 = 1.1;
+const TILES: i32 = 3;
+
+/// Window coordinates, with the origin in the top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.w as i32 && y < self.y + self.h as i32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w == 0 || self.h == 0
+    }
+
+    pub fn set_viewport(&self, window_height: u32) {
+        let y = window_height as i32 - self.y - self.h as i32;
+        unsafe {
+            gl::Viewport(self.x, y, self.w as i32, self.h as i32);
+        }
+    }
+
+    pub fn set_scissor(&self, window_height: u32) {
+        let y = window_height as i32 - self.y - self.h as i32;
+        unsafe {
+            gl::Scissor(self.x, y, self.w as i32, self.h as i32);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct View {
+    pub focused: Option<usize>,
+    pub zoom: f32,
+    pub pan: [f32; 2],
+    pub tiled: bool,
+    pub channel: Option<Channel>,
+    pub slice: u32,
+    pub window_size: (u32, u32),
+    pub panel_width: u32,
+    pub error: Option<ErrorOverlay>,
+    layout: Vec<Cell>,
+    inspect_at: Option<(i32, i32)>,
+    dragging: Option<usize>,
+}
+
+impl View {
+    pub fn new(window_size: (u32, u32)) -> Self {
+        Self {
+            focused: None,
+            zoom: 1.0,
+            pan: [0.0, 0.0],
+            tiled: false,
+            channel: None,
+            slice: 0,
+            window_size,
+            panel_width: 0,
+            error: None,
+            layout: vec![],
+            inspect_at: None,
+            dragging: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.pan = [0.0, 0.0];
+        self.tiled = false;
+        self.channel = None;
+        self.slice = 0;
+    }
+
+    pub fn slice_index(&self, slices: u32) -> u32 {
+        self.slice.min(slices.saturating_sub(1))
+    }
+
+    pub fn tiles(&self) -> i32 {
+        if self.tiled {
+            TILES
+        } else {
+            1
+        }
+    }
+
+    pub fn update_layout(&mut self, aspects: &[f32]) {
+        if self.focused.is_some_and(|idx| idx >= aspects.len()) {
+            self.focused = None;
+        }
+
+        let window = (
+            self.window_size.0.saturating_sub(self.panel_width),
+            self.window_size.1,
+        );
+
+        self.layout = match self.focused {
+            Some(focused) => {
+                let area = Rect {
+                    x: 0,
+                    y: 0,
+                    w: window.0,
+                    h: window.1,
+                };
+                let mut layout = vec![None; aspects.len()];
+                layout[focused] = Some(Cell::new(area, aspects[focused]));
+                layout.into_iter().flatten().collect()
+            }
+            None => layout::grid(window, aspects),
+        };
+    }
+
+    /// `None` if the preview is hidden by a focused one.
+    pub fn cell(&self, idx: usize) -> Option<Cell> {
+        match self.focused {
+            Some(focused) if focused == idx => self.layout.first().copied(),
+            Some(_) => None,
+            None => self.layout.get(idx).copied(),
+        }
+    }
+
+    pub fn rect(&self, idx: usize) -> Option<Rect> {
+        self.cell(idx).map(|it| it.image)
+    }
+
+    pub fn hit(&self, x: i32, y: i32) -> Option<usize> {
+        let previews = match self.focused {
+            Some(focused) => focused + 1,
+            None => self.layout.len(),
+        };
+        (0..previews).find(|idx| self.cell(*idx).is_some_and(|c| c.area.contains(x, y)))
+    }
+
+    /// `None` if only background is shown at `(x, y)`.
+    pub fn texel_uv(&self, rect: &Rect, x: i32, y: i32) -> Option<[f32; 2]> {
+        let tiles = self.tiles() as f32;
+        let screen = [
+            (x - rect.x) as f32 / rect.w as f32,
+            (y - rect.y) as f32 / rect.h as f32,
+        ];
+
+        let mut uv = [0.0; 2];
+        for i in 0..2 {
+            uv[i] = ((screen[i] - 0.5) / self.zoom + 0.5 - self.pan[i]) * tiles;
+            if uv[i] < 0.0 || uv[i] > tiles {
+                return None;
+            }
+            uv[i] = uv[i].fract();
+        }
+
+        Some(uv)
+    }
+
+    pub fn take_inspect_request(&mut self) -> Option<(i32, i32)> {
+        self.inspect_at.take()
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::Window {
+                win_event: WindowEvent::SizeChanged(w, h),
+                ..
+            } => {
+                self.window_size = (*w as u32, *h as u32);
+            }
+            Event::MouseButtonDown {
+                mouse_btn, x, y, ..
+            } => match mouse_btn {
+                MouseButton::Left => match self.focused {
+                    None if self.hit(*x, *y).is_some() => {
+                        self.focused = self.hit(*x, *y);
+                    }
+                    _ => self.dragging = self.hit(*x, *y),
+                },
+                MouseButton::Right => self.inspect_at = Some((*x, *y)),
+                _ => (),
+            },
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.dragging = None,
+            Event::MouseMotion { xrel, yrel, .. } => {
+                if let Some(rect) = self.dragging.and_then(|idx| self.rect(idx)) {
+                    self.pan[0] += *xrel as f32 / rect.w as f32 / self.zoom;
+                    self.pan[1] += *yrel as f32 / rect.h as f32 / self.zoom;
+                }
+            }
+            Event::MouseWheel { y, .. } => {
+                self.zoom *= ZOOM_STEP.powi(*y);
+            }
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => match key {
+                Keycode::Escape => self.focused = None,
+                Keycode::Space => self.reset(),
+                Keycode::T => self.tiled = !self.tiled,
+                Keycode::R => self.toggle_channel(Channel::R),
+                Keycode::G => self.toggle_channel(Channel::G),
+                Keycode::B => self.toggle_channel(Channel::B),
+                Keycode::A => self.toggle_channel(Channel::A),
+                Keycode::LeftBracket => self.slice = self.slice.saturating_sub(1),
+                Keycode::RightBracket => self.slice += 1,
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    fn toggle_channel(&mut self, channel: Channel) {
+        self.channel = if self.channel == Some(channel) {
+            None
+        } else {
+            Some(channel)
+        };
+    }
+}
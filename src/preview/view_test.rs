@@ -0,0 +1,62 @@
+use super::{
+    layout::LABEL_HEIGHT,
+    view::{Rect, View},
+};
+
+#[test]
+fn test_view_rect_grid() {
+    let mut view = View::new((600, 200 + LABEL_HEIGHT));
+    view.update_layout(&[1.0; 3]);
+
+    let cell = view.cell(1).unwrap();
+
+    assert_eq!(cell.area.x, 200);
+    assert_eq!(view.rect(1), Some(cell.image));
+    assert_eq!(view.hit(210, 10), Some(1));
+    assert_eq!(view.hit(610, 10), None);
+}
+
+#[test]
+fn test_view_rect_focused() {
+    let mut view = View::new((800, 600));
+    view.focused = Some(2);
+    view.update_layout(&[1.0; 3]);
+
+    let expected = Rect {
+        x: 0,
+        y: 0,
+        w: 800,
+        h: 600,
+    };
+
+    assert_eq!(view.rect(0), None);
+    assert_eq!(view.cell(2).unwrap().area, expected);
+    assert_eq!(view.hit(10, 10), Some(2));
+
+    view.update_layout(&[1.0; 2]);
+    assert_eq!(view.focused, None);
+}
+
+#[test]
+fn test_view_texel_uv() {
+    let mut view = View::new((100, 100));
+    let rect = Rect {
+        x: 0,
+        y: 0,
+        w: 100,
+        h: 100,
+    };
+
+    assert_eq!(view.texel_uv(&rect, 25, 75), Some([0.25, 0.75]));
+
+    view.zoom = 2.0;
+    assert_eq!(view.texel_uv(&rect, 50, 50), Some([0.5, 0.5]));
+    assert_eq!(view.texel_uv(&rect, 0, 0), Some([0.25, 0.25]));
+
+    view.zoom = 0.5;
+    assert_eq!(view.texel_uv(&rect, 0, 0), None);
+
+    view.zoom = 1.0;
+    view.tiled = true;
+    assert_eq!(view.texel_uv(&rect, 50, 50), Some([0.5, 0.5]));
+}
@@ -0,0 +1,173 @@
+use std::{collections::VecDeque, fmt::Write, time::Duration};
+
+use gl::types::{GLuint, GLuint64};
+use serde::{Deserialize, Serialize};
+
+/// `GL_TIME_ELAPSED` queries, read later so the CPU does not wait for the GPU.
+#[derive(Default)]
+pub struct GpuTimer {
+    free: Vec<GLuint>,
+    /// Oldest first.
+    pending: VecDeque<(u64, GLuint)>,
+}
+
+impl GpuTimer {
+    pub fn begin(&mut self, execution: u64) {
+        let id = self.free.pop().unwrap_or_else(|| {
+            let mut id = 0;
+            unsafe {
+                gl::GenQueries(1, &mut id);
+            }
+            id
+        });
+        self.pending.push_back((execution, id));
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, id);
+        }
+    }
+
+    pub fn end(&mut self) {
+        unsafe {
+            gl::EndQuery(gl::TIME_ELAPSED);
+        }
+    }
+
+    pub fn take_finished(&mut self) -> Vec<(u64, Duration)> {
+        let mut res = vec![];
+        while let Some(&(_, id)) = self.pending.front() {
+            let mut available = 0;
+            unsafe {
+                gl::GetQueryObjectiv(id, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            }
+            if available == 0 {
+                break;
+            }
+            res.push(self.read());
+        }
+        res
+    }
+
+    pub fn wait(&mut self) -> Vec<(u64, Duration)> {
+        let mut res = vec![];
+        while !self.pending.is_empty() {
+            res.push(self.read());
+        }
+        res
+    }
+
+    fn read(&mut self) -> (u64, Duration) {
+        let (execution, id) = self.pending.pop_front().expect("Query is pending");
+        let mut nanos: GLuint64 = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(id, gl::QUERY_RESULT, &mut nanos);
+        }
+        self.free.push(id);
+        (execution, Duration::from_nanos(nanos))
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        let pending = self.pending.iter().map(|(_, id)| id);
+        for id in self.free.iter().chain(pending) {
+            unsafe {
+                gl::DeleteQueries(1, id);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProfileSettings {
+    #[serde(default)]
+    pub table: bool,
+    #[serde(default)]
+    pub json: Option<String>,
+    #[serde(default)]
+    pub trace: Option<String>,
+}
+
+impl ProfileSettings {
+    pub fn report_enabled(&self) -> bool {
+        self.table || self.json.is_some()
+    }
+}
+
+/// Times in seconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct StageProfile {
+    pub idx: usize,
+    pub shader: String,
+    pub output: String,
+    pub gpu: f64,
+    pub cpu: f64,
+    /// Last load of the file inputs.
+    pub upload: f64,
+    pub readback: f64,
+    pub encode: f64,
+    /// Last compilation of the shader.
+    pub compile: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ProfileReport {
+    pub stages: Vec<StageProfile>,
+}
+
+impl ProfileReport {
+    pub fn total(&self) -> StageProfile {
+        let mut total = StageProfile {
+            shader: "total".to_string(),
+            ..Default::default()
+        };
+        for stage in self.stages.iter() {
+            total.gpu += stage.gpu;
+            total.cpu += stage.cpu;
+            total.upload += stage.upload;
+            total.readback += stage.readback;
+            total.encode += stage.encode;
+            total.compile += stage.compile;
+        }
+        total
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_table(&self) -> String {
+        let name_width = self
+            .stages
+            .iter()
+            .map(|it| it.shader.len())
+            .max()
+            .unwrap_or(0)
+            .max("shader".len());
+
+        let mut res = String::new();
+        let _ = writeln!(
+            res,
+            "{:>3}  {:<name_width$} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "#", "shader", "gpu", "cpu", "upload", "readback", "encode", "compile"
+        );
+
+        let total = self.total();
+        let rows = self.stages.iter().map(|it| (it.idx.to_string(), it));
+        for (idx, stage) in rows.chain(std::iter::once((String::new(), &total))) {
+            let _ = writeln!(
+                res,
+                "{:>3}  {:<name_width$} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.3}",
+                idx,
+                stage.shader,
+                stage.gpu * 1000.0,
+                stage.cpu * 1000.0,
+                stage.upload * 1000.0,
+                stage.readback * 1000.0,
+                stage.encode * 1000.0,
+                stage.compile * 1000.0,
+            );
+        }
+
+        res
+    }
+}
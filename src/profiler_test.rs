@@ -0,0 +1,58 @@
+use crate::profiler::{ProfileReport, StageProfile};
+
+fn report() -> ProfileReport {
+    ProfileReport {
+        stages: vec![
+            StageProfile {
+                idx: 0,
+                shader: "noise.frag".to_string(),
+                output: "noise".to_string(),
+                gpu: 0.002,
+                cpu: 0.0005,
+                compile: 0.01,
+                ..Default::default()
+            },
+            StageProfile {
+                idx: 1,
+                shader: "blur.frag".to_string(),
+                output: "blur.png".to_string(),
+                gpu: 0.004,
+                readback: 0.001,
+                encode: 0.02,
+                ..Default::default()
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_profile_total() {
+    let total = report().total();
+
+    assert!((total.gpu - 0.006).abs() < 1e-9);
+    assert!((total.encode - 0.02).abs() < 1e-9);
+    assert!((total.compile - 0.01).abs() < 1e-9);
+}
+
+#[test]
+fn test_profile_table() {
+    let table = report().to_table();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].contains("shader"));
+    assert!(lines[1].contains("noise.frag"));
+    assert!(lines[1].contains("2.000"));
+    assert!(lines[3].contains("total"));
+    assert!(lines[3].contains("6.000"));
+}
+
+#[test]
+fn test_profile_json_roundtrip() {
+    let report = report();
+    let json = report.to_json().unwrap();
+
+    assert!(json.contains("\"readback\""));
+    let parsed: ProfileReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, report);
+}
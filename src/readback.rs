@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Result};
+use gl::types::{GLint, GLsizeiptr, GLsync, GLuint};
+use image::DynamicImage;
+
+use crate::texture::{image_from_pixels, TextureFormat};
+
+/// In nanoseconds.
+const WAIT_TIMEOUT: u64 = 1_000_000_000;
+
+/// Asynchronous copy of a texture into a pixel buffer object.
+#[derive(Debug)]
+pub struct PendingReadback {
+    pbo: GLuint,
+    fence: GLsync,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+}
+
+impl PendingReadback {
+    pub fn issue(id: GLuint, width: u32, height: u32, format: TextureFormat) -> Self {
+        let size = (width as usize * height as usize * format.bytes_per_pixel()) as GLsizeiptr;
+
+        let mut pbo = 0;
+        let fence;
+        unsafe {
+            gl::GenBuffers(1, &mut pbo);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+            gl::BufferData(
+                gl::PIXEL_PACK_BUFFER,
+                size,
+                std::ptr::null(),
+                gl::STREAM_READ,
+            );
+            gl::GetTextureImage(
+                id,
+                0,
+                gl::RGBA,
+                format.pixel_type(),
+                size as GLint,
+                std::ptr::null_mut(),
+            );
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+
+            fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            gl::Flush();
+        }
+
+        Self {
+            pbo,
+            fence,
+            width,
+            height,
+            format,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        let status = unsafe { gl::ClientWaitSync(self.fence, 0, 0) };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+
+    pub fn wait(self) -> Result<DynamicImage> {
+        unsafe {
+            while gl::ClientWaitSync(self.fence, gl::SYNC_FLUSH_COMMANDS_BIT, WAIT_TIMEOUT)
+                == gl::TIMEOUT_EXPIRED
+            {}
+        }
+
+        let size = self.width as usize * self.height as usize * self.format.bytes_per_pixel();
+        let mut pixels = vec![0_u8; size];
+
+        unsafe {
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.pbo);
+            let data = gl::MapBufferRange(
+                gl::PIXEL_PACK_BUFFER,
+                0,
+                size as GLsizeiptr,
+                gl::MAP_READ_BIT,
+            ) as *const u8;
+            if !data.is_null() {
+                std::ptr::copy_nonoverlapping(data, pixels.as_mut_ptr(), size);
+                gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            }
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            if data.is_null() {
+                return Err(anyhow!("Failed to map the readback buffer"));
+            }
+        }
+
+        image_from_pixels(self.width, self.height, self.format, pixels)
+            .ok_or_else(|| anyhow!("Readback buffer does not have the size of the image"))
+    }
+}
+
+impl Drop for PendingReadback {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSync(self.fence);
+            gl::DeleteBuffers(1, &self.pbo);
+        }
+    }
+}
@@ -7,8 +7,8 @@ use crate::pipeline::Expr;
 
 #[derive(Debug)]
 pub struct ShaderProgram {
-    frag_shader: GLuint,
-    vert_shader: GLuint,
+    shaders: Vec<GLuint>,
+    compute: bool,
     pub program_id: GLuint,
 }
 
@@ -18,17 +18,49 @@ impl ShaderProgram {
             .with_context(|| "Failed to frag shader")?;
         let vert_shader = Self::create_shader(vert, gl::VERTEX_SHADER)
             .with_context(|| "Failed to vert shader")?;
-        let program_id = Self::create_program(frag_shader, vert_shader)
-            .with_context(|| "Failed to load program")?;
+        let shaders = vec![frag_shader, vert_shader];
+        let program_id =
+            Self::create_program(&shaders).with_context(|| "Failed to load program")?;
 
         let program = ShaderProgram {
-            frag_shader,
-            vert_shader,
+            shaders,
+            compute: false,
             program_id,
         };
         Ok(program)
     }
 
+    pub fn compute(src: &str) -> Result<Self> {
+        let compute_shader = Self::create_shader(src, gl::COMPUTE_SHADER)
+            .with_context(|| "Failed to compute shader")?;
+        let shaders = vec![compute_shader];
+        let program_id =
+            Self::create_program(&shaders).with_context(|| "Failed to load program")?;
+
+        let program = ShaderProgram {
+            shaders,
+            compute: true,
+            program_id,
+        };
+        Ok(program)
+    }
+
+    pub fn is_compute(&self) -> bool {
+        self.compute
+    }
+
+    pub fn work_group_size(&self) -> [u32; 3] {
+        let mut size = [0 as GLint; 3];
+        unsafe {
+            gl::GetProgramiv(
+                self.program_id,
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                size.as_mut_ptr(),
+            );
+        }
+        size.map(|it| it.max(1) as u32)
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::UseProgram(self.program_id);
@@ -118,6 +150,12 @@ impl ShaderProgram {
         })
     }
 
+    pub fn uniform_matrix_4f(&self, name: &str, v: &[f32; 16]) -> Result<()> {
+        self.uniform(name, |id| unsafe {
+            gl::UniformMatrix4fv(id, 1, gl::FALSE, v.as_ptr())
+        })
+    }
+
     fn uniform<F: Fn(i32)>(&self, name: &str, f: F) -> Result<()> {
         let uniform_id = self.get_uniform_location(name)?;
 
@@ -149,6 +187,10 @@ impl ShaderProgram {
         typ
     }
 
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.get_uniform_location(name).is_ok()
+    }
+
     pub fn get_uniform_location(&self, name: &str) -> Result<i32> {
         let c_name = std::ffi::CString::new(name).unwrap();
         let uniform_id = unsafe {
@@ -175,17 +217,19 @@ impl ShaderProgram {
         Ok(uniform_id)
     }
 
-    fn create_program(frag_shader: GLuint, vert_shader: GLuint) -> Result<GLuint> {
+    fn create_program(shaders: &[GLuint]) -> Result<GLuint> {
         let id = unsafe { gl::CreateProgram() };
         unsafe {
-            gl::AttachShader(id, frag_shader);
-            gl::AttachShader(id, vert_shader);
+            for shader in shaders {
+                gl::AttachShader(id, *shader);
+            }
             gl::LinkProgram(id);
         }
         Self::program_link_status(id)?;
         unsafe {
-            gl::DetachShader(id, frag_shader);
-            gl::DetachShader(id, vert_shader);
+            for shader in shaders {
+                gl::DetachShader(id, *shader);
+            }
         }
 
         Ok(id)
@@ -261,8 +305,9 @@ impl ShaderProgram {
 impl Drop for ShaderProgram {
     fn drop(&mut self) {
         unsafe {
-            gl::DeleteShader(self.frag_shader);
-            gl::DeleteShader(self.vert_shader);
+            for shader in self.shaders.iter() {
+                gl::DeleteShader(*shader);
+            }
             gl::DeleteProgram(self.program_id);
         }
     }
@@ -0,0 +1,62 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec2 TextureCoords;
+    vec3 Position;
+} IN;
+
+out vec4 Color;
+
+uniform sampler2D albedo;
+uniform sampler2D normal;
+uniform sampler2D roughness;
+uniform int has_normal;
+uniform int has_roughness;
+uniform int sphere;
+uniform int tiles;
+
+#define PI 3.1415926535
+
+void main() {
+    vec3 n = vec3(0, 0, 1);
+    vec3 t = vec3(1, 0, 0);
+    vec3 b = vec3(0, -1, 0);
+    vec2 uv = IN.TextureCoords;
+
+    if (sphere != 0) {
+        vec2 p = IN.TextureCoords * 2 - 1;
+        p.y = -p.y;
+        float r = dot(p, p);
+        if (r > 1) {
+            Color = vec4(0.1, 0.1, 0.1, 1.0);
+            return;
+        }
+
+        n = vec3(p, sqrt(1 - r));
+        uv = vec2(atan(n.x, n.z) / (2 * PI) + 0.5, acos(n.y) / PI);
+        t = normalize(vec3(n.z, 0, -n.x));
+        b = cross(n, t);
+    }
+    uv *= tiles;
+
+    if (has_normal != 0) {
+        vec3 tn = texture(normal, uv).rgb * 2 - 1;
+        n = normalize(tn.x * t + tn.y * b + tn.z * n);
+    }
+
+    float rough = 0.5;
+    if (has_roughness != 0) {
+        rough = texture(roughness, uv).r;
+    }
+
+    vec3 base = texture(albedo, uv).rgb;
+    vec3 light = normalize(vec3(-0.5, 0.5, 1.0));
+    vec3 view = vec3(0, 0, 1);
+    vec3 h = normalize(light + view);
+
+    float shininess = mix(256.0, 4.0, rough);
+    float diffuse = max(dot(n, light), 0);
+    float specular = pow(max(dot(n, h), 0), shininess) * (1 - rough);
+
+    Color = vec4(base * (0.1 + 0.9 * diffuse) + vec3(specular), 1.0);
+}
@@ -0,0 +1,57 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec2 TextureCoords;
+    vec3 Position;
+    vec3 Normal;
+} IN;
+
+out vec4 Color;
+
+uniform sampler2D albedo;
+uniform sampler2D normal;
+uniform sampler2D roughness;
+uniform int has_normal;
+uniform int has_roughness;
+
+// Tangent frame from screen-space derivatives, so meshes do not need tangents.
+mat3 cotangent_frame(vec3 n, vec3 p, vec2 uv) {
+    vec3 dp1 = dFdx(p);
+    vec3 dp2 = dFdy(p);
+    vec2 duv1 = dFdx(uv);
+    vec2 duv2 = dFdy(uv);
+
+    vec3 dp2perp = cross(dp2, n);
+    vec3 dp1perp = cross(n, dp1);
+    vec3 t = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 b = dp2perp * duv1.y + dp1perp * duv2.y;
+
+    float invmax = inversesqrt(max(dot(t, t), dot(b, b)));
+    return mat3(t * invmax, -b * invmax, n);
+}
+
+void main() {
+    vec2 uv = IN.TextureCoords;
+    vec3 n = normalize(IN.Normal);
+
+    if (has_normal != 0) {
+        vec3 tn = texture(normal, uv).rgb * 2 - 1;
+        n = normalize(cotangent_frame(n, IN.Position, uv) * tn);
+    }
+
+    float rough = 0.5;
+    if (has_roughness != 0) {
+        rough = texture(roughness, uv).r;
+    }
+
+    vec3 base = texture(albedo, uv).rgb;
+    vec3 light = normalize(vec3(-0.5, 0.5, 1.0));
+    vec3 view = normalize(-IN.Position);
+    vec3 h = normalize(light + view);
+
+    float shininess = mix(256.0, 4.0, rough);
+    float diffuse = max(dot(n, light), 0);
+    float specular = pow(max(dot(n, h), 0), shininess) * (1 - rough);
+
+    Color = vec4(base * (0.1 + 0.9 * diffuse) + vec3(specular), 1.0);
+}
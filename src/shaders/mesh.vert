@@ -0,0 +1,36 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec3 FakePosition;
+layout (location = 2) in vec2 TextureCoords;
+layout (location = 3) in vec3 Normal;
+
+out VS_OUTPUT {
+    vec2 TextureCoords;
+    vec3 Position;
+    vec3 Normal;
+} OUT;
+
+uniform mat4 model_view;
+uniform mat4 projection;
+uniform sampler2D displacement;
+uniform int has_displacement;
+uniform float displacement_scale;
+uniform int tiles;
+
+void main()
+{
+    vec2 uv = TextureCoords * tiles;
+    vec3 position = Position;
+
+    if (has_displacement != 0) {
+        position += Normal * textureLod(displacement, uv, 0).r * displacement_scale;
+    }
+
+    vec4 view_position = model_view * vec4(position, 1.0);
+
+    gl_Position = projection * view_position;
+    OUT.TextureCoords = uv;
+    OUT.Position = view_position.xyz;
+    OUT.Normal = mat3(model_view) * Normal;
+}
@@ -0,0 +1,69 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec2 TextureCoords;
+    vec3 Position;
+} IN;
+
+out vec4 Color;
+
+uniform sampler2D image;
+uniform float zoom;
+uniform vec2 pan;
+uniform int tiles;
+uniform int mode;
+uniform int channel;
+
+#define MODE_COLOR 0
+#define MODE_CHANNEL 1
+#define MODE_HEIGHTMAP 2
+#define MODE_NORMAL 3
+
+vec3 ramp(float t) {
+    vec3 c0 = vec3(0.0, 0.0, 0.3);
+    vec3 c1 = vec3(0.0, 0.4, 1.0);
+    vec3 c2 = vec3(0.1, 0.8, 0.2);
+    vec3 c3 = vec3(1.0, 0.9, 0.0);
+    vec3 c4 = vec3(1.0, 0.1, 0.0);
+
+    t = clamp(t, 0, 1) * 4;
+    if (t < 1) return mix(c0, c1, t);
+    if (t < 2) return mix(c1, c2, t - 1);
+    if (t < 3) return mix(c2, c3, t - 2);
+    return mix(c3, c4, t - 3);
+}
+
+vec4 shade(vec4 c) {
+    if (mode == MODE_CHANNEL) {
+        float v = c[channel];
+        return vec4(v, v, v, 1.0);
+    }
+    if (mode == MODE_HEIGHTMAP) {
+        return vec4(ramp(c.r), 1.0);
+    }
+    if (mode == MODE_NORMAL) {
+        vec3 n = normalize(c.rgb * 2 - 1);
+        vec3 light = normalize(vec3(-0.5, 0.5, 1.0));
+        float v = 0.15 + 0.85 * max(dot(n, light), 0);
+        return vec4(v, v, v, 1.0);
+    }
+    return c;
+}
+
+vec4 background(vec2 pos) {
+    ivec2 cell = ivec2(floor(pos / 16.0));
+    float c = (cell.x + cell.y) % 2 == 0 ? 0.2 : 0.3;
+    return vec4(c, c, c, 1.0);
+}
+
+void main() {
+    vec2 uv = (IN.TextureCoords - 0.5) / zoom + 0.5 - pan;
+    uv *= tiles;
+
+    if (uv.x < 0 || uv.y < 0 || uv.x > tiles || uv.y > tiles) {
+        Color = background(gl_FragCoord.xy);
+        return;
+    }
+
+    Color = shade(texture(image, uv));
+}
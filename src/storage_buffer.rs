@@ -0,0 +1,60 @@
+use gl::types::{GLsizeiptr, GLuint};
+
+#[derive(Debug)]
+pub struct StorageBuffer {
+    id: GLuint,
+    len: u32,
+}
+
+impl StorageBuffer {
+    pub fn new(len: u32) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::CreateBuffers(1, &mut id);
+            gl::NamedBufferData(
+                id,
+                (len as usize * 4) as GLsizeiptr,
+                std::ptr::null(),
+                gl::DYNAMIC_COPY,
+            );
+        }
+
+        let buffer = Self { id, len };
+        buffer.clear();
+        buffer
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&self) {
+        unsafe {
+            gl::ClearNamedBufferData(
+                self.id,
+                gl::R32UI,
+                gl::RED_INTEGER,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+    }
+
+    pub fn bind(&self, binding: u32) {
+        unsafe {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, self.id);
+        }
+    }
+}
+
+impl Drop for StorageBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use image::{imageops, RgbaImage};
+
+use crate::{
+    atlas::grid_columns,
+    pipeline::Expr,
+    preview::{render_text, CHAR_WIDTH, LINE_HEIGHT},
+};
+
+const LABEL_COLOR: [u8; 4] = [230, 230, 230, 255];
+const BACKGROUND: [u8; 4] = [24, 24, 24, 255];
+
+/// `out/rock.png` is written to `out/rock_s3.png` for the variant 3.
+pub fn variant_suffix(variant: usize) -> String {
+    format!("_s{variant}")
+}
+
+pub fn label(variant: usize, values: &[(String, Expr)], width: u32) -> Vec<String> {
+    let columns = (width / CHAR_WIDTH) as usize;
+    std::iter::once(format!("#{variant}"))
+        .chain(
+            values
+                .iter()
+                .map(|(name, value)| format!("{name}: {}", value.to_yaml())),
+        )
+        .map(|line| line.chars().take(columns).collect())
+        .collect()
+}
+
+pub fn cell_image(image: &RgbaImage, width: u32) -> RgbaImage {
+    let height = (image.height() as u64 * width as u64 / image.width().max(1) as u64).max(1);
+    imageops::resize(image, width, height as u32, imageops::FilterType::Triangle)
+}
+
+pub fn contact_sheet(cells: &[(RgbaImage, Vec<String>)], columns: Option<u32>) -> RgbaImage {
+    let count = cells.len() as u32;
+    let columns = grid_columns(count, columns);
+    let rows = count.div_ceil(columns);
+    let width = cells.iter().map(|it| it.0.width()).max().unwrap_or(0);
+    let height = cells
+        .iter()
+        .map(|(image, label)| image.height() + label.len() as u32 * LINE_HEIGHT)
+        .max()
+        .unwrap_or(0);
+
+    let mut sheet = RgbaImage::from_pixel(columns * width, rows * height, BACKGROUND.into());
+    for (i, (image, label)) in cells.iter().enumerate() {
+        let i = i as u32;
+        let (x, y) = ((i % columns * width) as i64, (i / columns * height) as i64);
+        imageops::replace(&mut sheet, image, x, y);
+        let text = render_text(&label.join("\n"), LABEL_COLOR, BACKGROUND);
+        imageops::replace(&mut sheet, &text, x, y + image.height() as i64);
+    }
+    sheet
+}
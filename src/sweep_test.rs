@@ -0,0 +1,56 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    pipeline::Expr,
+    preview::{CHAR_WIDTH, LINE_HEIGHT},
+    sweep::{cell_image, contact_sheet, label, variant_suffix},
+};
+
+#[test]
+fn test_variant_suffix() {
+    assert_eq!(variant_suffix(0), "_s0");
+    assert_eq!(variant_suffix(12), "_s12");
+}
+
+#[test]
+fn test_label() {
+    let values = vec![
+        ("roughness".to_string(), Expr::Float(0.5)),
+        ("tint".to_string(), Expr::String("#ff0000".to_string())),
+    ];
+    assert_eq!(
+        label(3, &values, 128),
+        vec!["#3", "roughness: 0.5", "tint: '#ff0000'"]
+    );
+
+    let narrow = label(3, &values, 8 * CHAR_WIDTH);
+    assert_eq!(narrow[1], "roughnes");
+}
+
+#[test]
+fn test_cell_image() {
+    let image = RgbaImage::from_pixel(64, 32, Rgba([10, 20, 30, 255]));
+    let cell = cell_image(&image, 16);
+    assert_eq!(cell.dimensions(), (16, 8));
+    assert_eq!(cell.get_pixel(4, 4), &Rgba([10, 20, 30, 255]));
+}
+
+#[test]
+fn test_contact_sheet() {
+    let cells: Vec<(RgbaImage, Vec<String>)> = (0..5u8)
+        .map(|i| {
+            let image = RgbaImage::from_pixel(8, 8, Rgba([i * 50, 0, 0, 255]));
+            (image, vec![format!("#{i}"), "a: 1".to_string()])
+        })
+        .collect();
+
+    let sheet = contact_sheet(&cells, None);
+    let cell_height = 8 + 2 * LINE_HEIGHT;
+    assert_eq!(sheet.dimensions(), (3 * 8, 2 * cell_height));
+    assert_eq!(sheet.get_pixel(1, 1), &Rgba([0, 0, 0, 255]));
+    assert_eq!(sheet.get_pixel(8 + 1, 1), &Rgba([50, 0, 0, 255]));
+    assert_eq!(sheet.get_pixel(1, cell_height + 1), &Rgba([150, 0, 0, 255]));
+
+    let sheet = contact_sheet(&cells, Some(5));
+    assert_eq!(sheet.dimensions(), (5 * 8, cell_height));
+}
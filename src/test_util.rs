@@ -0,0 +1,42 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use image::{DynamicImage, Rgba, RgbaImage};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// Removed with its content when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let idx = NEXT_DIR.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("tw-{name}-{}-{idx}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> String {
+        self.0.to_string_lossy().to_string()
+    }
+
+    pub fn file(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 2x2 layers stacked like read back volumes, each filled with its index.
+pub fn strip(layers: u8) -> DynamicImage {
+    let strip = RgbaImage::from_fn(2, 2 * layers as u32, |_, y| {
+        Rgba([(y / 2) as u8, 0, 0, 255])
+    });
+    DynamicImage::ImageRgba8(strip)
+}
@@ -1,17 +1,139 @@
 use std::ffi::c_void;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use core::fmt::Debug;
-use gl::types::GLint;
+use gl::types::{GLenum, GLint};
 
-use image::RgbaImage;
+use image::{DynamicImage, ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
 
-use crate::framebuffer::Framebuffer;
+use crate::{
+    color_space::decode_srgb,
+    framebuffer::Framebuffer,
+    pipeline::{ColorSpace, Depth, Output},
+    readback::PendingReadback,
+    trace,
+};
+
+/// From `EXT_texture_sRGB_decode`, which the bindings are not generated with.
+const TEXTURE_SRGB_DECODE_EXT: GLenum = 0x8A48;
+const DECODE_EXT: GLenum = 0x8A49;
+const SKIP_DECODE_EXT: GLenum = 0x8A4A;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextureFormat {
+    #[default]
+    Rgba8,
+    Srgb8,
+    Rgba16,
+    Rgba16f,
+    Rgba32f,
+}
+
+impl TextureFormat {
+    fn internal_format(&self) -> GLint {
+        (match self {
+            TextureFormat::Rgba8 => gl::RGBA8,
+            TextureFormat::Srgb8 => gl::SRGB8_ALPHA8,
+            TextureFormat::Rgba16 => gl::RGBA16,
+            TextureFormat::Rgba16f => gl::RGBA16F,
+            TextureFormat::Rgba32f => gl::RGBA32F,
+        }) as GLint
+    }
+
+    /// Half floats are transferred as 32-bit floats.
+    pub fn pixel_type(&self) -> GLenum {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Srgb8 => gl::UNSIGNED_BYTE,
+            TextureFormat::Rgba16 => gl::UNSIGNED_SHORT,
+            TextureFormat::Rgba16f | TextureFormat::Rgba32f => gl::FLOAT,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Srgb8 => 4,
+            TextureFormat::Rgba16 => 8,
+            TextureFormat::Rgba16f | TextureFormat::Rgba32f => 16,
+        }
+    }
+
+    /// Only 8-bit sRGB is stored encoded.
+    pub fn new(depth: Depth, color_space: ColorSpace) -> Self {
+        match (depth, color_space) {
+            (Depth::U8, ColorSpace::Srgb) => Self::Srgb8,
+            _ => depth.into(),
+        }
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        *self == Self::Srgb8
+    }
+
+    pub fn of_image(image: &DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => Self::Rgba32f,
+            DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_) => Self::Rgba16,
+            _ => Self::Rgba8,
+        }
+    }
+}
+
+impl From<Depth> for TextureFormat {
+    fn from(depth: Depth) -> Self {
+        match depth {
+            Depth::U8 => Self::Rgba8,
+            Depth::U16 => Self::Rgba16,
+            Depth::F16 => Self::Rgba16f,
+            Depth::F32 => Self::Rgba32f,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextureShape {
+    #[default]
+    Flat,
+    Volume(u32),
+    Cube,
+}
+
+impl TextureShape {
+    pub fn of_output(output: &Output) -> Self {
+        match (output.volume, output.cubemap) {
+            (Some(volume), _) => Self::Volume(volume.slices),
+            (None, Some(_)) => Self::Cube,
+            (None, None) => Self::Flat,
+        }
+    }
+
+    /// Layers rendered one at a time, `None` for 2D textures.
+    pub fn layers(&self) -> Option<u32> {
+        match self {
+            Self::Flat => None,
+            Self::Volume(slices) => Some(*slices),
+            Self::Cube => Some(6),
+        }
+    }
+
+    fn target(&self) -> GLenum {
+        match self {
+            Self::Flat => gl::TEXTURE_2D,
+            Self::Volume(_) => gl::TEXTURE_3D,
+            Self::Cube => gl::TEXTURE_CUBE_MAP,
+        }
+    }
+}
 
 pub struct Texture {
-    image: RgbaImage,
     id: gl::types::GLuint,
     framebuffer: Framebuffer,
+    width: u32,
+    height: u32,
+    shape: TextureShape,
+    format: TextureFormat,
 }
 
 impl Debug for Texture {
@@ -19,6 +141,8 @@ impl Debug for Texture {
         f.debug_struct("Texture")
             .field("width", &self.width())
             .field("height", &self.height())
+            .field("shape", &self.shape)
+            .field("format", &self.format)
             .field("id", &self.id)
             .field("framebuffer", &self.framebuffer)
             .finish()
@@ -27,64 +151,202 @@ impl Debug for Texture {
 
 impl Texture {
     pub fn from_size(w: u32, h: u32) -> Result<Self> {
-        let image = RgbaImage::new(w, h);
+        Self::empty(w, h, TextureFormat::Rgba8)
+    }
+
+    pub fn empty(w: u32, h: u32, format: TextureFormat) -> Result<Self> {
+        Self::new(w, h, TextureShape::Flat, format, std::ptr::null())
+    }
 
-        Self::from_image(image)
+    pub fn empty_shaped(
+        w: u32,
+        h: u32,
+        shape: TextureShape,
+        format: TextureFormat,
+    ) -> Result<Self> {
+        Self::new(w, h, shape, format, std::ptr::null())
     }
 
-    pub fn from_file(fname: &str) -> Result<Self> {
-        let image = image::open(fname)
-            .with_context(|| format!("Failed to read image from '{}'", fname))?
-            .into_rgba8();
+    /// Keeps the precision of the file when `depth` is not set.
+    pub fn from_file(fname: &str, depth: Option<Depth>, color_space: ColorSpace) -> Result<Self> {
+        let image =
+            image::open(fname).with_context(|| format!("Failed to read image from '{}'", fname))?;
+        let format = depth.map_or_else(|| TextureFormat::of_image(&image), Into::into);
 
-        Self::from_image(image)
+        match (color_space, format) {
+            (ColorSpace::Srgb, TextureFormat::Rgba8) => {
+                Self::from_dynamic_image(&image, TextureFormat::Srgb8)
+            }
+            (ColorSpace::Srgb, _) => Self::from_dynamic_image(&decode_srgb(&image), format),
+            _ => Self::from_dynamic_image(&image, format),
+        }
     }
 
-    pub fn from_image(mut image: RgbaImage) -> Result<Self> {
-        let (w, h) = (image.width() as GLint, image.height() as GLint);
+    pub fn from_image(image: RgbaImage) -> Result<Self> {
+        Self::new(
+            image.width(),
+            image.height(),
+            TextureShape::Flat,
+            TextureFormat::Rgba8,
+            image.as_ptr() as *const c_void,
+        )
+    }
 
-        let mut id = 0;
-        unsafe {
-            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id);
-            gl::BindTexture(gl::TEXTURE_2D, id);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGBA8 as GLint,
+    pub fn from_dynamic_image(image: &DynamicImage, format: TextureFormat) -> Result<Self> {
+        let (w, h) = (image.width(), image.height());
+        match format {
+            TextureFormat::Rgba8 | TextureFormat::Srgb8 => Self::new(
                 w,
                 h,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                image.as_mut_ptr() as *mut c_void,
-            );
+                TextureShape::Flat,
+                format,
+                image.to_rgba8().as_ptr() as _,
+            ),
+            TextureFormat::Rgba16 => Self::new(
+                w,
+                h,
+                TextureShape::Flat,
+                format,
+                image.to_rgba16().as_ptr() as _,
+            ),
+            TextureFormat::Rgba16f | TextureFormat::Rgba32f => Self::new(
+                w,
+                h,
+                TextureShape::Flat,
+                format,
+                image.to_rgba32f().as_ptr() as _,
+            ),
+        }
+    }
 
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+    /// Left uninitialized when `pixels` is null.
+    fn new(
+        width: u32,
+        height: u32,
+        shape: TextureShape,
+        format: TextureFormat,
+        pixels: *const c_void,
+    ) -> Result<Self> {
+        let (w, h) = (width as GLint, height as GLint);
+        let target = shape.target();
+
+        if let TextureShape::Volume(slices) = shape {
+            let mut max = 0;
+            unsafe {
+                gl::GetIntegerv(gl::MAX_3D_TEXTURE_SIZE, &mut max);
+            }
+            if slices as GLint > max || w > max || h > max {
+                return Err(anyhow!(
+                    "Volume of {width}x{height}x{slices} exceeds the maximum 3D texture size {max}"
+                ));
+            }
+        }
+
+        let mut id = 0;
+        unsafe {
+            gl::CreateTextures(target, 1, &mut id);
+            gl::BindTexture(target, id);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_R, gl::REPEAT as i32);
+            gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            match shape {
+                TextureShape::Volume(slices) => gl::TexImage3D(
+                    target,
+                    0,
+                    format.internal_format(),
+                    w,
+                    h,
+                    slices as GLint,
+                    0,
+                    gl::RGBA,
+                    format.pixel_type(),
+                    pixels,
+                ),
+                TextureShape::Cube => {
+                    gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+                    for face in 0..6 {
+                        gl::TexImage2D(
+                            gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                            0,
+                            format.internal_format(),
+                            w,
+                            h,
+                            0,
+                            gl::RGBA,
+                            format.pixel_type(),
+                            pixels,
+                        );
+                    }
+                }
+                TextureShape::Flat => gl::TexImage2D(
+                    target,
+                    0,
+                    format.internal_format(),
+                    w,
+                    h,
+                    0,
+                    gl::RGBA,
+                    format.pixel_type(),
+                    pixels,
+                ),
+            }
+
+            gl::BindTexture(target, 0);
         }
 
         let framebuffer = Framebuffer::new();
 
         let texture = Self {
-            image,
             id,
             framebuffer,
+            width,
+            height,
+            shape,
+            format,
         };
 
         texture.framebuffer.bind();
-        texture.framebuffer.attach_texture(&texture);
+        match shape {
+            TextureShape::Flat => texture.framebuffer.attach_texture(&texture),
+            _ => texture.framebuffer.attach_layer(&texture, 0),
+        }
         texture.framebuffer.unbind();
 
         Ok(texture)
     }
 
     pub fn activate_bind(&self, idx: u32) {
+        self.bind_with_decode(idx, true);
+    }
+
+    /// Binds without decoding sRGB textures.
+    pub fn activate_bind_raw(&self, idx: u32) {
+        self.bind_with_decode(idx, false);
+    }
+
+    fn bind_with_decode(&self, idx: u32, decode: bool) {
         unsafe {
+            if self.format.is_srgb() {
+                let mode = if decode { DECODE_EXT } else { SKIP_DECODE_EXT };
+                gl::TextureParameteri(self.id, TEXTURE_SRGB_DECODE_EXT, mode as i32);
+            }
             gl::ActiveTexture(gl::TEXTURE0 + idx);
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::BindTexture(self.target(), self.id);
+        }
+    }
+
+    pub fn set_clamped(&self, clamped: bool) {
+        let mode = if clamped {
+            gl::CLAMP_TO_EDGE
+        } else {
+            gl::REPEAT
+        };
+        unsafe {
+            gl::TextureParameteri(self.id, gl::TEXTURE_WRAP_S, mode as i32);
+            gl::TextureParameteri(self.id, gl::TEXTURE_WRAP_T, mode as i32);
+            gl::TextureParameteri(self.id, gl::TEXTURE_WRAP_R, mode as i32);
         }
     }
 
@@ -92,11 +354,42 @@ impl Texture {
         self.framebuffer.bind();
         unsafe {
             gl::Viewport(0, 0, self.width() as i32, self.height() as i32);
+            if self.format.is_srgb() {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            }
+        }
+    }
+
+    pub fn bind_layer_as_canvas(&self, layer: u32) {
+        self.framebuffer.bind();
+        self.framebuffer.attach_layer(self, layer);
+        self.bind_as_canvas();
+    }
+
+    /// Volumes and cubemaps are bound with all their layers.
+    pub fn bind_as_image(&self, unit: u32) {
+        let layered = match self.shape {
+            TextureShape::Flat => gl::FALSE,
+            _ => gl::TRUE,
+        };
+        unsafe {
+            gl::BindImageTexture(
+                unit,
+                self.id,
+                0,
+                layered,
+                0,
+                gl::READ_WRITE,
+                self.format.internal_format() as GLenum,
+            );
         }
     }
 
     pub fn unbind_as_canvas(&self) {
         self.framebuffer.unbind();
+        unsafe {
+            gl::Disable(gl::FRAMEBUFFER_SRGB);
+        }
     }
 
     pub fn get_id(&self) -> u32 {
@@ -104,32 +397,213 @@ impl Texture {
     }
 
     pub fn width(&self) -> u32 {
-        self.image.width()
+        self.width
     }
 
     pub fn height(&self) -> u32 {
-        self.image.height()
+        self.height
     }
 
-    pub fn save_to_file(&self, fname: &str) -> Result<()> {
-        let (w, h) = (self.image.width() as GLint, self.image.height() as GLint);
+    pub fn shape(&self) -> TextureShape {
+        self.shape
+    }
+
+    pub fn layers(&self) -> Option<u32> {
+        self.shape.layers()
+    }
+
+    fn target(&self) -> GLenum {
+        self.shape.target()
+    }
+
+    pub fn copy_layer(&self, layer: u32, target: &Texture) {
+        unsafe {
+            gl::CopyImageSubData(
+                self.id,
+                self.target(),
+                0,
+                0,
+                0,
+                layer as GLint,
+                target.id,
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                0,
+                self.width as GLint,
+                self.height as GLint,
+                1,
+            );
+        }
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn upload(&self, image: &Rgba32FImage) {
+        debug_assert_eq!(image.dimensions(), (self.width, self.height));
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                self.width as GLint,
+                self.height as GLint,
+                gl::RGBA,
+                gl::FLOAT,
+                image.as_ptr() as *const c_void,
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    pub fn clear(&self) {
+        let color = [0.0_f32; 4];
+        if self.shape != TextureShape::Flat {
+            // The framebuffer holds a single layer.
+            unsafe {
+                gl::ClearTexImage(self.id, 0, gl::RGBA, gl::FLOAT, color.as_ptr() as _);
+            }
+            return;
+        }
         self.framebuffer.bind();
+        unsafe {
+            gl::ClearBufferfv(gl::COLOR, 0, color.as_ptr());
+        }
+        self.framebuffer.unbind();
+    }
+
+    pub fn read_pixel(&self, x: u32, y: u32) -> [f32; 4] {
+        let mut pixel = [0_f32; 4];
 
         unsafe {
-            gl::ReadPixels(
+            gl::GetTextureSubImage(
+                self.id,
                 0,
+                x as GLint,
+                y as GLint,
+                0,
+                1,
+                1,
+                1,
+                gl::RGBA,
+                gl::FLOAT,
+                std::mem::size_of_val(&pixel) as i32,
+                pixel.as_mut_ptr() as *mut c_void,
+            );
+        }
+
+        pixel
+    }
+
+    pub fn save_to_file(&self, fname: &str) -> Result<()> {
+        let _span = trace::span("save", "save_to_file").arg("file", fname);
+        let image = self.read_back_async().wait()?;
+        write_dynamic_image(&image, fname)
+    }
+
+    pub fn read_back_async(&self) -> PendingReadback {
+        PendingReadback::issue(self.id, self.width, self.strip_height(), self.format)
+    }
+
+    /// sRGB textures are copied as stored, layers are stacked from top to bottom.
+    pub fn read_back(&self) -> RgbaImage {
+        let _span = trace::span("save", "readback");
+        let mut image = RgbaImage::new(self.width, self.strip_height());
+
+        unsafe {
+            gl::GetTextureImage(
+                self.id,
                 0,
-                w,
-                h,
                 gl::RGBA,
                 gl::UNSIGNED_BYTE,
-                self.image.as_ptr() as *mut c_void,
+                image.len() as i32,
+                image.as_mut_ptr() as *mut c_void,
             );
         }
-        self.image.save(fname)?;
-        self.framebuffer.unbind();
 
-        Ok(())
+        image
+    }
+
+    pub fn read_back_f32(&self) -> Rgba32FImage {
+        let _span = trace::span("save", "readback");
+        let mut image = Rgba32FImage::new(self.width, self.strip_height());
+
+        unsafe {
+            gl::GetTextureImage(
+                self.id,
+                0,
+                gl::RGBA,
+                gl::FLOAT,
+                (image.len() * std::mem::size_of::<f32>()) as i32,
+                image.as_mut_ptr() as *mut c_void,
+            );
+        }
+
+        image
+    }
+
+    fn strip_height(&self) -> u32 {
+        self.height * self.layers().unwrap_or(1)
+    }
+}
+
+pub fn write_image(image: &RgbaImage, fname: &str) -> Result<()> {
+    let _span = trace::span("save", "encode").arg("file", fname);
+    image.save(fname)?;
+    Ok(())
+}
+
+/// Floats for EXR, up to 16 bits for PNG and TIFF and 8 bits for the others.
+pub fn write_dynamic_image(image: &DynamicImage, fname: &str) -> Result<()> {
+    let extension = std::path::Path::new(fname)
+        .extension()
+        .map(|it| it.to_string_lossy().to_lowercase());
+
+    let converted = match (extension.as_deref(), image) {
+        (Some("exr"), DynamicImage::ImageRgba32F(_)) => None,
+        (Some("exr"), _) => Some(DynamicImage::ImageRgba32F(image.to_rgba32f())),
+        (Some("png" | "tif" | "tiff"), DynamicImage::ImageRgba32F(_)) => {
+            Some(DynamicImage::ImageRgba16(image.to_rgba16()))
+        }
+        (Some("png" | "tif" | "tiff"), _) => None,
+        (_, DynamicImage::ImageRgba8(image)) => return write_image(image, fname),
+        _ => Some(DynamicImage::ImageRgba8(image.to_rgba8())),
+    };
+
+    let _span = trace::span("save", "encode").arg("file", fname);
+    converted.as_ref().unwrap_or(image).save(fname)?;
+    Ok(())
+}
+
+pub fn image_from_pixels(
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    data: Vec<u8>,
+) -> Option<DynamicImage> {
+    match format {
+        TextureFormat::Rgba8 | TextureFormat::Srgb8 => {
+            RgbaImage::from_raw(width, height, data).map(Into::into)
+        }
+        TextureFormat::Rgba16 => {
+            let data = data
+                .chunks_exact(2)
+                .map(|it| u16::from_ne_bytes([it[0], it[1]]))
+                .collect();
+            ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, data).map(Into::into)
+        }
+        TextureFormat::Rgba16f | TextureFormat::Rgba32f => {
+            let data = data
+                .chunks_exact(4)
+                .map(|it| f32::from_ne_bytes([it[0], it[1], it[2], it[3]]))
+                .collect();
+            ImageBuffer::<Rgba<f32>, _>::from_raw(width, height, data).map(Into::into)
+        }
     }
 }
 
@@ -140,3 +614,15 @@ impl Drop for Texture {
         }
     }
 }
+
+/// 1 for images of different sizes.
+pub fn max_difference(a: &Rgba32FImage, b: &Rgba32FImage) -> f32 {
+    if a.dimensions() != b.dimensions() {
+        return 1.0;
+    }
+
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0, f32::max)
+}
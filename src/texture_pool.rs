@@ -0,0 +1,56 @@
+use anyhow::Result;
+
+use crate::texture::{Texture, TextureFormat, TextureShape};
+
+const MAX_FREE: usize = 16;
+
+/// Recycles the render targets of the stages between executions.
+#[derive(Debug, Default)]
+pub struct TexturePool {
+    free: Vec<Texture>,
+}
+
+impl TexturePool {
+    pub fn acquire(&mut self, width: u32, height: u32, format: TextureFormat) -> Result<Texture> {
+        self.acquire_shaped(width, height, TextureShape::Flat, format)
+    }
+
+    pub fn acquire_shaped(
+        &mut self,
+        width: u32,
+        height: u32,
+        shape: TextureShape,
+        format: TextureFormat,
+    ) -> Result<Texture> {
+        let found = self.free.iter().position(|it| {
+            it.width() == width
+                && it.height() == height
+                && it.shape() == shape
+                && it.format() == format
+        });
+
+        let texture = match found {
+            Some(idx) => self.free.remove(idx),
+            None => Texture::empty_shaped(width, height, shape, format)?,
+        };
+        texture.clear();
+
+        Ok(texture)
+    }
+
+    /// Drops the oldest texture when the pool is full.
+    pub fn release(&mut self, texture: Texture) {
+        if self.free.len() >= MAX_FREE {
+            self.free.remove(0);
+        }
+        self.free.push(texture);
+    }
+
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+}
@@ -0,0 +1,96 @@
+use image::{ImageBuffer, Rgba, Rgba32FImage};
+
+use crate::{
+    test_util::TempDir,
+    texture::{image_from_pixels, max_difference, write_dynamic_image, TextureFormat},
+};
+
+#[test]
+fn test_max_difference() {
+    let a = Rgba32FImage::from_pixel(4, 4, Rgba([0.125, 0.25, 0.5, 1.0]));
+    let mut b = a.clone();
+
+    assert_eq!(max_difference(&a, &b), 0.0);
+
+    b.put_pixel(2, 3, Rgba([0.125, 0.25, 0.75, 1.0]));
+    assert_eq!(max_difference(&a, &b), 0.25);
+    assert_eq!(max_difference(&b, &a), 0.25);
+
+    // Differences finer than an 8-bit step are measured.
+    b.put_pixel(2, 3, Rgba([0.125, 0.25, 0.5 + 1.0 / 1024.0, 1.0]));
+    assert_eq!(max_difference(&a, &b), 1.0 / 1024.0);
+}
+
+#[test]
+fn test_max_difference_of_different_sizes() {
+    let a = Rgba32FImage::new(4, 4);
+    let b = Rgba32FImage::new(2, 4);
+
+    assert_eq!(max_difference(&a, &b), 1.0);
+}
+
+#[test]
+fn test_write_16_bit_png() {
+    let dir = TempDir::new("texture");
+    let image = ImageBuffer::<Rgba<u16>, _>::from_pixel(2, 2, Rgba([1000, 20000, 65535, 3]));
+    let fname = dir.file("height.png");
+
+    write_dynamic_image(&image.into(), &fname).unwrap();
+
+    let read = image::open(&fname).unwrap();
+    assert_eq!(TextureFormat::of_image(&read), TextureFormat::Rgba16);
+    assert_eq!(
+        read.to_rgba16().get_pixel(1, 1),
+        &Rgba([1000, 20000, 65535, 3])
+    );
+}
+
+#[test]
+fn test_write_exr() {
+    let dir = TempDir::new("texture");
+    let image = ImageBuffer::<Rgba<f32>, _>::from_pixel(2, 2, Rgba([2.5, 0.125, 0.0, 1.0]));
+    let fname = dir.file("sky.exr");
+
+    write_dynamic_image(&image.into(), &fname).unwrap();
+
+    let read = image::open(&fname).unwrap();
+    assert_eq!(TextureFormat::of_image(&read), TextureFormat::Rgba32f);
+    assert_eq!(
+        read.to_rgba32f().get_pixel(0, 1),
+        &Rgba([2.5, 0.125, 0.0, 1.0])
+    );
+}
+
+#[test]
+fn test_write_float_to_8_bit_format() {
+    let dir = TempDir::new("texture");
+    let image = ImageBuffer::<Rgba<f32>, _>::from_pixel(2, 2, Rgba([1.0, 0.0, 1.0, 1.0]));
+    let fname = dir.file("preview.bmp");
+
+    write_dynamic_image(&image.into(), &fname).unwrap();
+
+    let read = image::open(&fname).unwrap().to_rgba8();
+    assert_eq!(read.get_pixel(0, 0), &Rgba([255, 0, 255, 255]));
+}
+
+#[test]
+fn test_image_from_pixels() {
+    let data: Vec<u8> = [1.5_f32, 2.0, 0.0, 1.0]
+        .iter()
+        .flat_map(|it| it.to_ne_bytes())
+        .collect();
+    let image = image_from_pixels(1, 1, TextureFormat::Rgba32f, data).unwrap();
+    assert_eq!(
+        image.to_rgba32f().get_pixel(0, 0),
+        &Rgba([1.5, 2.0, 0.0, 1.0])
+    );
+
+    let data: Vec<u8> = [512_u16, 0, 65535, 7]
+        .iter()
+        .flat_map(|it| it.to_ne_bytes())
+        .collect();
+    let image = image_from_pixels(1, 1, TextureFormat::Rgba16, data).unwrap();
+    assert_eq!(image.to_rgba16().get_pixel(0, 0), &Rgba([512, 0, 65535, 7]));
+
+    assert!(image_from_pixels(2, 2, TextureFormat::Rgba8, vec![0; 4]).is_none());
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref TRACER: Mutex<Tracer> = Mutex::new(Tracer::new());
+}
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD_ID: u64 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Complete event of the Chrome trace-event format, in microseconds.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub name: String,
+    pub cat: String,
+    pub ph: String,
+    pub ts: f64,
+    pub dur: f64,
+    pub pid: u32,
+    pub tid: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct TraceFile {
+    #[serde(rename = "traceEvents")]
+    trace_events: Vec<TraceEvent>,
+}
+
+#[derive(Debug)]
+pub struct Tracer {
+    enabled: bool,
+    origin: Instant,
+    events: Vec<TraceEvent>,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            origin: Instant::now(),
+            events: vec![],
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.events.clear();
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn record(&mut self, span: &Span, end: Instant) {
+        if !self.enabled {
+            return;
+        }
+
+        let ts = span.start.saturating_duration_since(self.origin);
+        self.events.push(TraceEvent {
+            name: span.name.clone(),
+            cat: span.cat.to_string(),
+            ph: "X".to_string(),
+            ts: ts.as_secs_f64() * 1e6,
+            dur: end.duration_since(span.start).as_secs_f64() * 1e6,
+            pid: std::process::id(),
+            tid: THREAD_ID.with(|it| *it),
+            args: span.args.clone(),
+        });
+    }
+
+    pub fn take_events(&mut self) -> Vec<TraceEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+pub struct Span {
+    cat: &'static str,
+    name: String,
+    args: BTreeMap<String, String>,
+    start: Instant,
+}
+
+impl Span {
+    pub fn new(cat: &'static str, name: impl Into<String>) -> Self {
+        Self {
+            cat,
+            name: name.into(),
+            args: BTreeMap::new(),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn arg(mut self, key: &str, value: impl ToString) -> Self {
+        self.args.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let end = Instant::now();
+        if let Ok(mut tracer) = TRACER.lock() {
+            tracer.record(self, end);
+        }
+    }
+}
+
+pub fn span(cat: &'static str, name: impl Into<String>) -> Span {
+    Span::new(cat, name)
+}
+
+pub fn set_enabled(enabled: bool) {
+    TRACER.lock().unwrap().set_enabled(enabled);
+}
+
+pub fn discard() {
+    TRACER.lock().unwrap().take_events();
+}
+
+pub fn to_json(events: Vec<TraceEvent>) -> Result<String> {
+    let file = TraceFile {
+        trace_events: events,
+    };
+    Ok(serde_json::to_string(&file)?)
+}
+
+/// Starts a new trace after writing.
+pub fn write(fname: &str) -> Result<()> {
+    let events = TRACER.lock().unwrap().take_events();
+    std::fs::write(fname, to_json(events)?)
+        .with_context(|| format!("Failed to write trace: {fname}"))?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+
+use crate::trace::{to_json, Span, Tracer};
+
+#[test]
+fn test_tracer_disabled_records_nothing() {
+    let mut tracer = Tracer::new();
+    let span = Span::new("stage", "blur.frag");
+    tracer.record(&span, Instant::now());
+
+    assert!(tracer.take_events().is_empty());
+}
+
+#[test]
+fn test_tracer_records_complete_events() {
+    let mut tracer = Tracer::new();
+    tracer.set_enabled(true);
+
+    let span = Span::new("stage", "blur.frag").arg("output", "blur.png");
+    let end = Instant::now() + Duration::from_millis(2);
+    tracer.record(&span, end);
+
+    let events = tracer.take_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "blur.frag");
+    assert_eq!(events[0].cat, "stage");
+    assert_eq!(events[0].ph, "X");
+    assert!(events[0].dur >= 2000.0);
+    assert_eq!(events[0].args["output"], "blur.png");
+
+    assert!(tracer.take_events().is_empty());
+}
+
+#[test]
+fn test_trace_json() {
+    let mut tracer = Tracer::new();
+    tracer.set_enabled(true);
+    let span = Span::new("compile", "noise.frag");
+    tracer.record(&span, Instant::now());
+
+    let json = to_json(tracer.take_events()).unwrap();
+    assert!(json.starts_with("{\"traceEvents\":["));
+    assert!(json.contains("\"ph\":\"X\""));
+    assert!(!json.contains("\"args\""));
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use image::{imageops, DynamicImage, ImageBuffer};
+
+use crate::{atlas::grid_columns, pipeline::Volume, texture::write_dynamic_image};
+
+pub fn write(strip: &DynamicImage, volume: Volume, fname: &str) -> Result<()> {
+    let raw = std::path::Path::new(fname)
+        .extension()
+        .is_some_and(|it| it.eq_ignore_ascii_case("raw"));
+    if raw {
+        std::fs::write(fname, raw_bytes(strip))?;
+        return Ok(());
+    }
+    write_dynamic_image(&slice_sheet(strip, volume), fname)
+}
+
+pub fn slice_sheet(strip: &DynamicImage, volume: Volume) -> DynamicImage {
+    let slices = volume.slices.max(1);
+    let columns = grid_columns(slices, volume.columns);
+    let rows = slices.div_ceil(columns);
+    let (w, h) = (strip.width(), strip.height() / slices);
+
+    let mut sheet = blank_like(strip, columns * w, rows * h);
+    for slice in 0..slices {
+        let view = strip.crop_imm(0, slice * h, w, h);
+        let (x, y) = ((slice % columns) * w, (slice / columns) * h);
+        imageops::replace(&mut sheet, &view, x as i64, y as i64);
+    }
+    sheet
+}
+
+/// X first and the slices last, little endian.
+pub fn raw_bytes(strip: &DynamicImage) -> Vec<u8> {
+    match strip {
+        DynamicImage::ImageRgba16(image) => image.iter().flat_map(|it| it.to_le_bytes()).collect(),
+        DynamicImage::ImageRgba32F(image) => image.iter().flat_map(|it| it.to_le_bytes()).collect(),
+        _ => strip.to_rgba8().into_raw(),
+    }
+}
+
+pub fn blank_like(image: &DynamicImage, w: u32, h: u32) -> DynamicImage {
+    match image {
+        DynamicImage::ImageRgba16(_) => DynamicImage::new_rgba16(w, h),
+        DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgba32F(ImageBuffer::new(w, h)),
+        _ => DynamicImage::new_rgba8(w, h),
+    }
+}
@@ -0,0 +1,90 @@
+use image::{DynamicImage, ImageBuffer, Rgba};
+
+use crate::{
+    context::validate_output,
+    pipeline::{Stage, Volume},
+    test_util::{strip, TempDir},
+    volume::{raw_bytes, slice_sheet, write},
+};
+
+#[test]
+fn test_volume_parse() {
+    let stage = r#"
+        shader: noise.glsl
+        output: { dst: file, name: out/noise.raw, width: 8, height: 8, volume: { slices: 8 } }
+    "#;
+    let stage: Stage = serde_yaml::from_str(stage).unwrap();
+
+    assert_eq!(
+        stage.output.volume,
+        Some(Volume {
+            slices: 8,
+            columns: None
+        })
+    );
+}
+
+#[test]
+fn test_volume_without_slices() {
+    let stage = r#"
+        shader: noise.glsl
+        output: { dst: memory, name: noise, width: 8, height: 8, volume: { slices: 0 } }
+    "#;
+    let stage: Stage = serde_yaml::from_str(stage).unwrap();
+
+    let err = validate_output(&stage).unwrap_err();
+    assert!(err.to_string().contains("has no slices"));
+}
+
+#[test]
+fn test_slice_sheet() {
+    let volume = Volume {
+        slices: 5,
+        columns: Some(2),
+    };
+    let sheet = slice_sheet(&strip(5), volume).to_rgba8();
+
+    assert_eq!(sheet.dimensions(), (4, 6));
+    assert_eq!(sheet.get_pixel(3, 1), &Rgba([1, 0, 0, 255]));
+    assert_eq!(sheet.get_pixel(1, 3), &Rgba([2, 0, 0, 255]));
+    assert_eq!(sheet.get_pixel(0, 5), &Rgba([4, 0, 0, 255]));
+    assert_eq!(sheet.get_pixel(3, 5), &Rgba([0, 0, 0, 0]));
+}
+
+#[test]
+fn test_slice_sheet_keeps_precision() {
+    let strip = DynamicImage::ImageRgba32F(ImageBuffer::new(1, 4));
+    let volume = Volume {
+        slices: 4,
+        columns: None,
+    };
+    let sheet = slice_sheet(&strip, volume);
+
+    assert!(matches!(sheet, DynamicImage::ImageRgba32F(_)));
+    assert_eq!((sheet.width(), sheet.height()), (2, 2));
+}
+
+#[test]
+fn test_raw_bytes() {
+    assert_eq!(raw_bytes(&strip(2))[16..20], [1, 0, 0, 255]);
+
+    let strip = DynamicImage::ImageRgba16(ImageBuffer::from_pixel(1, 1, Rgba([1, 2, 3, 0xff00])));
+    assert_eq!(raw_bytes(&strip), [1, 0, 2, 0, 3, 0, 0x00, 0xff]);
+
+    let strip = DynamicImage::ImageRgba32F(ImageBuffer::from_pixel(1, 1, Rgba([1.0; 4])));
+    assert_eq!(raw_bytes(&strip)[..4], 1.0f32.to_le_bytes());
+}
+
+#[test]
+fn test_write_raw() {
+    let dir = TempDir::new("volume");
+    let fname = dir.file("density.RAW");
+    let volume = Volume {
+        slices: 3,
+        columns: None,
+    };
+
+    write(&strip(3), volume, &fname).unwrap();
+
+    assert_eq!(std::fs::read(&fname).unwrap(), raw_bytes(&strip(3)));
+}